@@ -0,0 +1,182 @@
+//! Command-line interface for running simulations, with or without visualization.
+use clamms::config::{apply_override, load_config, Config, ConfigError};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "clamms",
+    version,
+    about = "Run CLAMMS agent-based simulations."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub common: CommonArgs,
+}
+
+/// Subcommands determining whether the model is loaded and saved. When no subcommand is given,
+/// `LOAD_MODEL` and `SAVE_MODEL` are taken from the config file.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a simulation, without saving the model.
+    Run {
+        /// Model checkpoint file to start from.
+        #[arg(long)]
+        load: Option<String>,
+    },
+    /// Train the model and save a checkpoint at the end of the run.
+    Train {
+        /// Model checkpoint file to continue training from.
+        #[arg(long)]
+        load: Option<String>,
+    },
+    /// Evaluate a saved model with a greedy policy and no learning.
+    Evaluate {
+        /// Model checkpoint file to evaluate.
+        #[arg(long)]
+        load: String,
+    },
+    /// Check the config for errors without running a simulation.
+    Check,
+    /// Print the resolved config without running a simulation.
+    PrintConfig,
+    /// Run a parameter sweep over the config, with values given in a sweep file.
+    Sweep {
+        /// Sweep file listing values for config keys and the number of replicates.
+        file: PathBuf,
+        /// Number of runs to execute in parallel.
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+    },
+    /// Rerun the simulation recorded in an output or checkpoint file and check it reproduces the
+    /// recorded results. Outputs are written to `--output-dir` (by default `rerun` alongside the
+    /// file); the config options are ignored in favour of the recorded config.
+    Rerun {
+        /// Output (`output.json`) or model checkpoint file to rerun.
+        file: PathBuf,
+    },
+}
+
+/// Arguments shared across all subcommands.
+#[derive(Args, Debug)]
+pub struct CommonArgs {
+    /// Path to the config file.
+    #[arg(
+        long,
+        global = true,
+        env = "CLAMMS_CONFIG",
+        default_value = "clamms-config.toml"
+    )]
+    pub config: PathBuf,
+
+    /// Random seed, overriding `world.RANDOM_SEED`.
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Number of steps, overriding `world.N_STEPS`.
+    #[arg(long, global = true)]
+    pub steps: Option<i32>,
+
+    /// Directory to write outputs to, overriding `simulation.OUTPUT_DIR`.
+    #[arg(long, global = true)]
+    pub output_dir: Option<PathBuf>,
+
+    /// File to write the agent histories to [default: `output.json` in the output directory].
+    #[arg(long, global = true)]
+    pub output: Option<PathBuf>,
+
+    /// Override a config value, e.g. `--set world.N_AGENTS=50`. May be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+}
+
+impl Cli {
+    /// Loads the layered config and applies overrides from the command line.
+    pub fn resolve_config(&self) -> Result<Config, ConfigError> {
+        let mut config = load_config(&self.common.config)?;
+        for assignment in &self.common.overrides {
+            config = apply_override(&config, assignment)?;
+        }
+        if let Some(seed) = self.common.seed {
+            config.world.RANDOM_SEED = seed;
+        }
+        if let Some(steps) = self.common.steps {
+            config.world.N_STEPS = steps;
+        }
+        if let Some(output_dir) = &self.common.output_dir {
+            config.simulation.OUTPUT_DIR = output_dir.to_string_lossy().into_owned();
+        }
+        match &self.command {
+            None
+            | Some(Command::Check)
+            | Some(Command::PrintConfig)
+            | Some(Command::Sweep { .. })
+            | Some(Command::Rerun { .. }) => {}
+            Some(Command::Run { load }) => {
+                set_checkpoint(&mut config, load);
+                config.rl.SAVE_MODEL = false;
+            }
+            Some(Command::Train { load }) => {
+                set_checkpoint(&mut config, load);
+                config.rl.SAVE_MODEL = true;
+            }
+            Some(Command::Evaluate { load }) => {
+                set_checkpoint(&mut config, &Some(load.to_owned()));
+                config.rl.SAVE_MODEL = false;
+                // Act greedily and leave the Q-values unchanged
+                config.rl.EPSILON = 0.0;
+                config.rl.ALPHA = 0.0;
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Sets whether the model is loaded, and from which checkpoint file.
+fn set_checkpoint(config: &mut Config, checkpoint_file: &Option<String>) {
+    config.rl.LOAD_MODEL = checkpoint_file.is_some();
+    if checkpoint_file.is_some() {
+        config.rl.MODEL_CHECKPOINT_FILE = checkpoint_file.to_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Config {
+        let args = ["clamms", "--config", "clamms-config-test.toml"]
+            .iter()
+            .chain(args);
+        Cli::parse_from(args).resolve_config().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_config() {
+        let config = parse(&["--seed", "7", "--steps", "10", "--set", "world.N_AGENTS=3"]);
+        assert_eq!(config.world.RANDOM_SEED, 7);
+        assert_eq!(config.simulation.OUTPUT_DIR, ".");
+        assert_eq!(config.world.N_STEPS, 10);
+        assert_eq!(config.world.N_AGENTS, 3);
+        assert!(!config.rl.LOAD_MODEL);
+        assert!(!config.rl.SAVE_MODEL);
+
+        let config = parse(&["train", "--seed", "7", "--output-dir", "results"]);
+        assert_eq!(config.world.RANDOM_SEED, 7);
+        assert_eq!(config.simulation.OUTPUT_DIR, "results");
+        assert!(!config.rl.LOAD_MODEL);
+        assert!(config.rl.SAVE_MODEL);
+
+        let config = parse(&["evaluate", "--load", "model.json"]);
+        assert!(config.rl.LOAD_MODEL);
+        assert!(!config.rl.SAVE_MODEL);
+        assert_eq!(
+            config.rl.MODEL_CHECKPOINT_FILE,
+            Some("model.json".to_string())
+        );
+        assert_eq!(config.rl.EPSILON, 0.0);
+    }
+}
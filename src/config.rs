@@ -1,14 +1,17 @@
 // Adapted from https://github.com/alan-turing-institute/trustchain/blob/main/trustchain-core/src/config.rs
 
 //! Core configuration types and utilities.
-use lazy_static::lazy_static;
 // use rand::Error;
+use crate::event_log::{Category, LogLevel};
 use crate::model::action::Action;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::f32::consts::PI;
+use std::fmt::Display;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+use thiserror::Error;
 use toml;
 
 pub type ResourceAbundance = f32;
@@ -16,38 +19,232 @@ pub type ResourceAbundance = f32;
 /// Environment variable name for CLAMMS config file.
 pub const CLAMMS_CONFIG: &str = "CLAMMS_CONFIG";
 
-lazy_static! {
-    /// Lazy static reference to core configuration loaded from `clamms_config.toml`.
-    pub static ref CORE_CONFIG: Config = open_config_file(Path::new(std::env::var(CLAMMS_CONFIG).unwrap().as_str()));
-}
-
-fn open_config_file(path: &Path) -> Config {
-    parse_toml(
-        &fs::read_to_string(path).expect(
-            format!(
-                "Unable to find the file {}. Please check the path is correct and this file exists",
-                CLAMMS_CONFIG
-            )
-            .as_str(),
-        ),
-    )
-    .expect(
-        format!(
-            "Unable to read the file {}. Please check the contents of this file.",
-            CLAMMS_CONFIG
-        )
-        .as_str(),
-    )
+/// Prefix of environment variables overriding config values, e.g. `CLAMMS__RL__EPSILON`.
+pub const ENV_OVERRIDE_PREFIX: &str = "CLAMMS__";
+
+/// Config file key listing other config files to be layered underneath it.
+const INCLUDE_KEY: &str = "include";
+
+/// Config keys holding paths, which are resolved relative to the config file that sets them.
+const PATH_KEYS: [&str; 5] = [
+    "world.RESOURCE_LOCATIONS_FILE",
+    "world.MAP_FILE",
+    "world.TIMELINE_FILE",
+    "rl.MODEL_CHECKPOINT_FILE",
+    "simulation.OUTPUT_DIR",
+];
+
+/// An error relating to loading or modifying the configuration.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Config file could not be read.
+    #[error("Unable to read the config file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Config file contents could not be parsed.
+    #[error("Unable to parse the config: {0}")]
+    Parse(#[from] toml::de::Error),
+    /// Config could not be serialized for applying overrides.
+    #[error("Unable to serialize the config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    /// Override not of the form `section.KEY=VALUE`.
+    #[error("Invalid override '{0}', expected the form section.KEY=VALUE")]
+    MalformedOverride(String),
+    /// Override refers to a key that is not part of the config.
+    #[error("Unknown config key '{0}'")]
+    UnknownKey(String),
+    /// Config file includes itself, directly or indirectly.
+    #[error("Config file {0} is included in a cycle")]
+    IncludeCycle(PathBuf),
+    /// Config file `include` key is not a path or list of paths.
+    #[error("Config file {0} has an include that is not a path or list of paths")]
+    MalformedInclude(PathBuf),
+}
+
+/// Parses the value of an override, falling back to a string if not a valid TOML value.
+fn parse_override_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::value::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// Whether a TOML value is a table with the given `NAME`.
+fn has_name(value: &toml::Value, name: &str) -> bool {
+    value.get("NAME").and_then(toml::Value::as_str) == Some(name)
 }
 
-/// Parses and returns core configuration.
-fn parse_toml(toml_str: &str) -> Result<Config, toml::de::Error> {
-    toml::from_str::<Config>(toml_str)
+/// Gets a field of a TOML table, or the table with the field as its `NAME` in an array of tables
+/// (e.g. `Food` in `resources.Food.ACQUIRE_RATE`).
+fn get_field<'a>(value: &'a toml::Value, field: &str) -> Option<&'a toml::Value> {
+    match value {
+        toml::Value::Table(table) => table.get(field),
+        toml::Value::Array(array) => array.iter().find(|value| has_name(value, field)),
+        _ => None,
+    }
+}
+
+/// Gets a field of a TOML table mutably, as in `get_field`.
+fn get_field_mut<'a>(value: &'a mut toml::Value, field: &str) -> Option<&'a mut toml::Value> {
+    match value {
+        toml::Value::Table(table) => table.get_mut(field),
+        toml::Value::Array(array) => array.iter_mut().find(|value| has_name(value, field)),
+        _ => None,
+    }
 }
 
-/// Gets `clamms-core` configuration variables.
-pub fn core_config() -> &'static CORE_CONFIG {
-    &CORE_CONFIG
+/// Looks up a dotted key path (e.g. `world.N_AGENTS`) in a TOML value.
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.').try_fold(value, get_field)
+}
+
+/// Sets the value at a dotted key path (e.g. `world.N_AGENTS`) in a TOML value, where every
+/// table along the path already exists.
+fn set_value(root: &mut toml::Value, key: &str, value: toml::Value) -> Result<(), ConfigError> {
+    let unknown = || ConfigError::UnknownKey(key.to_string());
+    let (path, field) = key.rsplit_once('.').ok_or_else(unknown)?;
+    let mut table = root;
+    for section in path.split('.') {
+        table = get_field_mut(table, section).ok_or_else(unknown)?;
+    }
+    table
+        .as_table_mut()
+        .ok_or_else(unknown)?
+        .insert(field.to_string(), value);
+    Ok(())
+}
+
+/// Deserializes a config from a TOML value, checking the given keys are all used.
+fn config_from_value(root: toml::Value, keys: &[String]) -> Result<Config, ConfigError> {
+    let config: Config = root.try_into()?;
+    // Unknown keys are silently ignored when deserializing, so check each key is present once the
+    // config is serialized again.
+    let serialized = toml::Value::try_from(&config)?;
+    if let Some(key) = keys.iter().find(|key| lookup(&serialized, key).is_none()) {
+        return Err(ConfigError::UnknownKey(key.to_string()));
+    }
+    Ok(config)
+}
+
+/// Sets the value of a key (e.g. `world.N_AGENTS`) in a config.
+pub fn set_config_value(
+    config: &Config,
+    key: &str,
+    value: toml::Value,
+) -> Result<Config, ConfigError> {
+    let mut root = toml::Value::try_from(config)?;
+    set_value(&mut root, key, value)?;
+    config_from_value(root, &[key.to_string()])
+}
+
+/// Applies an override of the form `section.KEY=VALUE` to a config.
+pub fn apply_override(config: &Config, assignment: &str) -> Result<Config, ConfigError> {
+    let malformed = || ConfigError::MalformedOverride(assignment.to_string());
+    let (key, value) = assignment.split_once('=').ok_or_else(malformed)?;
+    let (key, value) = (key.trim(), value.trim());
+    if !key.contains('.') {
+        return Err(malformed());
+    }
+    set_config_value(config, key, parse_override_value(value))
+}
+
+/// Recursively merges `overlay` into `base`, with values from `overlay` taking precedence.
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Resolves any relative paths set in a config file against the directory containing it.
+fn resolve_paths(root: &mut toml::Value, dir: &Path) {
+    for key in PATH_KEYS {
+        let (section, field) = key.split_once('.').expect("path keys are dotted");
+        if let Some(toml::Value::String(path)) = root
+            .get_mut(section)
+            .and_then(|section| section.get_mut(field))
+        {
+            *path = dir.join(&path).to_string_lossy().into_owned();
+        }
+    }
+}
+
+/// Reads a config file as a TOML value, with any files listed in its `include` key merged
+/// underneath it. Included paths, and paths given as config values, are relative to the file
+/// that sets them.
+fn read_layered(path: &Path, visited: &mut Vec<PathBuf>) -> Result<toml::Value, ConfigError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+    if visited.contains(&canonical) {
+        return Err(ConfigError::IncludeCycle(path.to_path_buf()));
+    }
+    visited.push(canonical);
+
+    let toml_str =
+        fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+    let mut value: toml::Value = toml::from_str(&toml_str)?;
+    let includes = match value.as_table_mut().and_then(|t| t.remove(INCLUDE_KEY)) {
+        None => Vec::new(),
+        Some(toml::Value::String(include)) => vec![include],
+        Some(toml::Value::Array(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                toml::Value::String(include) => Ok(include),
+                _ => Err(ConfigError::MalformedInclude(path.to_path_buf())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(ConfigError::MalformedInclude(path.to_path_buf())),
+    };
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    resolve_paths(&mut value, dir);
+    let mut layered = toml::Value::Table(toml::value::Table::new());
+    for include in includes {
+        merge(&mut layered, read_layered(&dir.join(include), visited)?);
+    }
+    merge(&mut layered, value);
+    visited.pop();
+    Ok(layered)
+}
+
+/// Gets the config key (e.g. `rl.EPSILON`) overridden by an environment variable (e.g.
+/// `CLAMMS__RL__EPSILON`), if any.
+fn env_override_key(var: &str) -> Option<String> {
+    let (section, field) = var.strip_prefix(ENV_OVERRIDE_PREFIX)?.split_once("__")?;
+    Some(format!("{}.{}", section.to_lowercase(), field))
+}
+
+/// Loads a config by layering, in increasing order of precedence: the built-in defaults, any
+/// included files, the given file and overrides from `CLAMMS__SECTION__KEY` environment
+/// variables.
+pub fn load_config(path: &Path) -> Result<Config, ConfigError> {
+    load_config_with_env(path, std::env::vars())
+}
+
+/// Loads a layered config as in `load_config`, taking overrides from the given variables.
+pub fn load_config_with_env(
+    path: &Path,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Config, ConfigError> {
+    let mut root = toml::Value::try_from(Config::default())?;
+    merge(&mut root, read_layered(path, &mut Vec::new())?);
+    let mut keys = Vec::new();
+    for (var, value) in vars {
+        if let Some(key) = env_override_key(&var) {
+            set_value(&mut root, &key, parse_override_value(&value))?;
+            keys.push(key);
+        }
+    }
+    config_from_value(root, &keys)
 }
 
 pub fn degree2radians(deg: f32) -> f32 {
@@ -58,54 +255,548 @@ pub fn action2rotation(action: Action) -> f32 {
     let degs = match action {
         Action::ToAgent => 180.0,
         Action::Stationary => 0.0,
-        Action::ToFood => 0.0,
-        Action::ToWater => 0.0,
+        Action::ToResource(_) => 0.0,
     };
     degree2radians(degs)
 }
 
 /// Configuration variables for `trustchain-core` crate.
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct AgentConfig {
     /// Config param for Agent
-    pub INIT_FOOD: i32,
-    pub INIT_WATER: i32,
-    pub FOOD_ACQUIRE_RATE: i32,
-    pub WATER_ACQUIRE_RATE: i32,
-    pub FOOD_CONSUME_RATE: u32,
-    pub WATER_CONSUME_RATE: u32,
-    pub FOOD_MAX_INVENTORY: i32,
-    pub WATER_MAX_INVENTORY: i32,
-    pub FOOD_LOT_SIZE: u32,
-    pub WATER_LOT_SIZE: u32,
     pub MAX_TRADE_LOTS: u32,
-    pub INVENTORY_LEVEL_CRITICAL_LOW: i32,
-    pub INVENTORY_LEVEL_LOW_MEDIUM: i32,
-    pub INVENTORY_LEVEL_MEDIUM_HIGH: i32,
+    /// Thresholds discretising the steps to the nearest other agent in the agent state.
     pub DISTANCE_LEVEL_CRITICAL_LOW: u32,
     pub DISTANCE_LEVEL_LOW_MEDIUM: u32,
     pub DISTANCE_LEVEL_MEDIUM_HIGH: u32,
+    /// Rule for the death of agents, selected by `TYPE`.
+    #[serde(default)]
+    pub MORTALITY: MortalityConfig,
+    /// Rule for the reproduction of agents, selected by `TYPE`.
+    #[serde(default)]
+    pub REPRODUCTION: ReproductionConfig,
 }
 
 /// Configuration variables for `trustchain-core` crate.
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct WorldConfig {
     /// Config params for simulation world.
     pub N_STEPS: i32,
     pub RANDOM_SEED: u64,
-    pub LAND_PROP: f32,
-    pub FOOD_ABUNDANCE: f32,
-    pub WATER_ABUNDANCE: f32,
-    pub TREE_PROB: f32,
-    pub SWEET_PROB: f32,
+    /// Resource locations map file, relative to the config file setting it.
     pub RESOURCE_LOCATIONS_FILE: Option<String>,
+    /// Grid map file giving the terrain or resource of every patch and the agent spawn points,
+    /// relative to the config file setting it. Not to be given with `RESOURCE_LOCATIONS_FILE`.
+    pub MAP_FILE: Option<String>,
     pub WIDTH: u16,
     pub HEIGHT: u16,
-    pub N_AGENTS: u8,
+    /// Whether the edges of the board are bounds or wrap around.
+    #[serde(default)]
+    pub TOPOLOGY: Topology,
+    pub N_AGENTS: u32,
     pub HAS_TRADING: bool,
+    /// Generator of the resource map when `RESOURCE_LOCATIONS_FILE` is not given.
+    #[serde(default)]
+    pub MAP_GENERATOR: MapGeneratorConfig,
+    /// Stocks of resources on patches and their regrowth, selected by `TYPE`.
+    #[serde(default)]
+    pub REGROWTH: RegrowthConfig,
+    /// Placement of agents not in any of the `[[groups]]`, selected by `TYPE`.
+    #[serde(default)]
+    pub PLACEMENT: PlacementConfig,
+    /// File of `[[timeline]]` regimes applied in addition to those in the config, relative to the
+    /// config file setting it.
+    pub TIMELINE_FILE: Option<String>,
+}
+
+/// Topology of the board.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    /// Agents cannot move beyond the edges of the board.
+    #[default]
+    Bounded,
+    /// Opposite edges of the board are joined, so that agents moving beyond an edge reappear at
+    /// the opposite edge.
+    Torus,
+}
+
+/// Procedural map generator and its parameters, selected by `TYPE`.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum MapGeneratorConfig {
+    /// Each patch independently has a resource with probability given by its abundance.
+    #[default]
+    Scatter,
+    /// A river of `RIVER_RESOURCE` meandering from south to north through the middle of the
+    /// board, and a square block of `BLOCK_RESOURCE` in the south-west corner.
+    River {
+        #[serde(default = "default_river_width")]
+        RIVER_WIDTH: u16,
+        /// Largest sideways shift of the river from one row to the next.
+        #[serde(default = "default_meander")]
+        MEANDER: u16,
+        /// Width of the block, in patches.
+        #[serde(default = "default_food_block")]
+        FOOD_BLOCK: u16,
+        #[serde(default = "default_river_resource")]
+        RIVER_RESOURCE: String,
+        #[serde(default = "default_block_resource")]
+        BLOCK_RESOURCE: String,
+    },
+    /// Resources in Gaussian clusters around random centres, with numbers of patches given by
+    /// their abundances.
+    Clusters {
+        /// Number of clusters of each resource.
+        #[serde(default = "default_n_clusters")]
+        N_CLUSTERS: u32,
+        /// Standard deviation of the distance of patches from the centre of their cluster.
+        #[serde(default = "default_spread")]
+        SPREAD: f32,
+    },
+    /// Islands of each resource grown by a cellular automaton from randomly filled patches.
+    Islands {
+        /// Probability of a patch initially having the resource.
+        #[serde(default = "default_fill")]
+        FILL: f32,
+        /// Number of iterations of the cellular automaton.
+        #[serde(default = "default_iterations")]
+        ITERATIONS: u32,
+    },
+    /// Resources where value noise fields are highest, with numbers of patches given by their
+    /// abundances.
+    Noise {
+        /// Spacing of the lattice of random values, in patches.
+        #[serde(default = "default_scale")]
+        SCALE: u16,
+    },
+}
+
+/// Model of the stocks of resources on patches, which are depleted by harvesting and regrow each
+/// step up to their capacity, selected by `TYPE`.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum RegrowthConfig {
+    /// Resources are never depleted.
+    #[default]
+    Unlimited,
+    /// Stocks regrow by `RATE` each step.
+    Constant {
+        #[serde(default = "default_stock_capacity")]
+        CAPACITY: i32,
+        #[serde(default = "default_regrowth_rate")]
+        RATE: f32,
+    },
+    /// Stocks regrow in proportion to both the stock and the remaining capacity, at a relative
+    /// growth rate `RATE`. Depleted stocks regrow as from a stock of one.
+    Logistic {
+        #[serde(default = "default_stock_capacity")]
+        CAPACITY: i32,
+        #[serde(default = "default_logistic_rate")]
+        RATE: f32,
+    },
+    /// As in Sugarscape, each patch has its own capacity, drawn uniformly between one and
+    /// `CAPACITY`, and stocks regrow by `RATE` each step.
+    Sugarscape {
+        #[serde(default = "default_stock_capacity")]
+        CAPACITY: i32,
+        #[serde(default = "default_regrowth_rate")]
+        RATE: f32,
+    },
+}
+
+/// Rule for the death of agents, selected by `TYPE`. Dead agents are removed from the board and
+/// the schedule, and their histories end with a terminal transition.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum MortalityConfig {
+    /// Agents never die, however depleted their inventories.
+    #[default]
+    Immortal,
+    /// Agents die after `STEPS` consecutive steps ending with the inventory of any resource below
+    /// `THRESHOLD`.
+    Starvation {
+        #[serde(default)]
+        THRESHOLD: i32,
+        #[serde(default = "default_starvation_steps")]
+        STEPS: u32,
+    },
+}
+
+/// Rule for the reproduction of agents, selected by `TYPE`. Offspring are placed on a patch next
+/// to their parent and join the schedule from the next step.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum ReproductionConfig {
+    /// Agents never reproduce.
+    #[default]
+    Off,
+    /// Agents ending a step with more than `THRESHOLD` of every resource, or the threshold given
+    /// for the resource by name in `THRESHOLDS`, spawn an offspring and give it `SHARE` of their
+    /// inventory of each resource. With `MULTI_POLICY`, the offspring inherits a copy of the
+    /// Q-table of its parent with Gaussian noise of standard deviation `NOISE` added to every
+    /// Q-value.
+    Surplus {
+        #[serde(default = "default_reproduction_threshold")]
+        THRESHOLD: i32,
+        #[serde(default)]
+        THRESHOLDS: BTreeMap<String, i32>,
+        #[serde(default = "default_reproduction_share")]
+        SHARE: f32,
+        #[serde(default)]
+        NOISE: f32,
+    },
+}
+
+/// Placement of agents on the board at the start of a run, selected by `TYPE`. Agents are only
+/// placed on patches they can enter, and are placed uniformly at random if no patch is eligible.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum PlacementConfig {
+    /// Uniformly at random over the spawn points of the grid map if it has any, or otherwise over
+    /// the whole board.
+    #[default]
+    Random,
+    /// Uniformly at random over the spawn points of the grid map, or those of a group of spawn
+    /// points if `GROUP` is given.
+    SpawnPoints { GROUP: Option<String> },
+    /// In Gaussian clusters around random centres.
+    Clusters {
+        #[serde(default = "default_n_clusters")]
+        N_CLUSTERS: u32,
+        /// Standard deviation of the distance of agents from the centre of their cluster.
+        #[serde(default = "default_spread")]
+        SPREAD: f32,
+    },
+    /// Uniformly at random over the patches within `RADIUS` steps of a patch of `RESOURCE`.
+    NearResource {
+        RESOURCE: String,
+        #[serde(default = "default_radius")]
+        RADIUS: u32,
+    },
+}
+
+/// Group of agents sharing a placement and initial inventories.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct AgentGroupConfig {
+    pub N_AGENTS: u32,
+    /// Initial inventories of agents in the group by resource name, in place of the `INIT` of the
+    /// resources.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub INIT: BTreeMap<String, i32>,
+    /// Placement of agents in the group. Last, as TOML cannot give values after tables.
+    #[serde(default)]
+    pub PLACEMENT: PlacementConfig,
+}
+
+fn default_stock_capacity() -> i32 {
+    50
+}
+
+fn default_regrowth_rate() -> f32 {
+    1.0
+}
+
+fn default_starvation_steps() -> u32 {
+    10
+}
+
+fn default_reproduction_threshold() -> i32 {
+    100
+}
+
+fn default_reproduction_share() -> f32 {
+    0.5
+}
+
+fn default_logistic_rate() -> f32 {
+    0.1
+}
+
+fn default_river_width() -> u16 {
+    5
+}
+
+fn default_meander() -> u16 {
+    2
+}
+
+fn default_food_block() -> u16 {
+    6
+}
+
+fn default_river_resource() -> String {
+    "Water".to_string()
+}
+
+fn default_block_resource() -> String {
+    "Food".to_string()
+}
+
+fn default_n_clusters() -> u32 {
+    3
+}
+
+fn default_spread() -> f32 {
+    2.0
+}
+
+fn default_radius() -> u32 {
+    3
+}
+
+fn default_fill() -> f32 {
+    0.45
+}
+
+fn default_iterations() -> u32 {
+    4
+}
+
+fn default_scale() -> u16 {
+    5
+}
+
+/// Resource found on patches, held in the inventories of agents and traded between them.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct ResourceConfig {
+    pub NAME: String,
+    /// Probability of a patch having the resource, or the proportion of patches with the resource
+    /// for generators placing numbers of patches.
+    pub ABUNDANCE: f32,
+    /// Initial inventory of agents.
+    #[serde(default)]
+    pub INIT: i32,
+    /// Amount acquired per step by an agent on a patch of the resource.
+    pub ACQUIRE_RATE: i32,
+    /// Amount consumed per step by every agent.
+    pub CONSUME_RATE: u32,
+    pub MAX_INVENTORY: i32,
+    /// Amount of the resource in each lot traded.
+    pub LOT_SIZE: u32,
+    /// Thresholds discretising the inventory of the resource in the agent state.
+    #[serde(default = "default_inventory_level_critical_low")]
+    pub INVENTORY_LEVEL_CRITICAL_LOW: i32,
+    #[serde(default = "default_inventory_level_low_medium")]
+    pub INVENTORY_LEVEL_LOW_MEDIUM: i32,
+    #[serde(default = "default_inventory_level_medium_high")]
+    pub INVENTORY_LEVEL_MEDIUM_HIGH: i32,
+    /// Thresholds discretising the steps to the nearest patch of the resource in the agent state.
+    #[serde(default = "default_distance_level_critical_low")]
+    pub DISTANCE_LEVEL_CRITICAL_LOW: u32,
+    #[serde(default = "default_distance_level_low_medium")]
+    pub DISTANCE_LEVEL_LOW_MEDIUM: u32,
+    #[serde(default = "default_distance_level_medium_high")]
+    pub DISTANCE_LEVEL_MEDIUM_HIGH: u32,
+    /// Name of the texture rendering the resource in the visualization.
+    pub TEXTURE: String,
+}
+
+impl ResourceConfig {
+    /// Thresholds between the critical, low, medium and high levels of the inventory.
+    pub fn inventory_levels(&self) -> [i32; 3] {
+        [
+            self.INVENTORY_LEVEL_CRITICAL_LOW,
+            self.INVENTORY_LEVEL_LOW_MEDIUM,
+            self.INVENTORY_LEVEL_MEDIUM_HIGH,
+        ]
+    }
+
+    /// Thresholds between the critical, low, medium and high levels of the distance to the
+    /// resource.
+    pub fn distance_levels(&self) -> [u32; 3] {
+        [
+            self.DISTANCE_LEVEL_CRITICAL_LOW,
+            self.DISTANCE_LEVEL_LOW_MEDIUM,
+            self.DISTANCE_LEVEL_MEDIUM_HIGH,
+        ]
+    }
+}
+
+fn default_inventory_level_critical_low() -> i32 {
+    0
+}
+
+fn default_inventory_level_low_medium() -> i32 {
+    10
+}
+
+fn default_inventory_level_medium_high() -> i32 {
+    50
+}
+
+fn default_distance_level_critical_low() -> u32 {
+    2
+}
+
+fn default_distance_level_low_medium() -> u32 {
+    10
+}
+
+fn default_distance_level_medium_high() -> u32 {
+    30
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+fn default_resources() -> Vec<ResourceConfig> {
+    let food = ResourceConfig {
+        NAME: "Food".to_string(),
+        ABUNDANCE: 0.1,
+        INIT: 0,
+        ACQUIRE_RATE: 10,
+        CONSUME_RATE: 1,
+        MAX_INVENTORY: 100,
+        LOT_SIZE: 6,
+        INVENTORY_LEVEL_CRITICAL_LOW: default_inventory_level_critical_low(),
+        INVENTORY_LEVEL_LOW_MEDIUM: default_inventory_level_low_medium(),
+        INVENTORY_LEVEL_MEDIUM_HIGH: default_inventory_level_medium_high(),
+        DISTANCE_LEVEL_CRITICAL_LOW: default_distance_level_critical_low(),
+        DISTANCE_LEVEL_LOW_MEDIUM: default_distance_level_low_medium(),
+        DISTANCE_LEVEL_MEDIUM_HIGH: default_distance_level_medium_high(),
+        TEXTURE: "fruit".to_string(),
+    };
+    let water = ResourceConfig {
+        NAME: "Water".to_string(),
+        LOT_SIZE: 2,
+        TEXTURE: "water".to_string(),
+        ..food.clone()
+    };
+    vec![food, water]
+}
+
+/// Terrain type of patches without a resource.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct TerrainConfig {
+    pub NAME: String,
+    /// Probability of a patch without a resource having this terrain type.
+    pub PROB: f32,
+    /// Whether agents can move onto patches of this terrain type.
+    pub PASSABLE: bool,
+    /// Multiple of the consumption rates for an agent moving onto a patch of this terrain type.
+    pub MOVEMENT_COST: u32,
+    /// Name of the texture rendering the terrain type in the visualization.
+    pub TEXTURE: String,
+}
+
+impl TerrainConfig {
+    pub fn new(name: &str, prob: f32, passable: bool, movement_cost: u32, texture: &str) -> Self {
+        TerrainConfig {
+            NAME: name.to_string(),
+            PROB: prob,
+            PASSABLE: passable,
+            MOVEMENT_COST: movement_cost,
+            TEXTURE: texture.to_string(),
+        }
+    }
+}
+
+/// Environmental regime, changing the environment while active on the timeline.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct RegimeConfig {
+    pub NAME: String,
+    /// First step the regime is active.
+    pub START: u64,
+    /// Number of steps the regime is active for, or until the end of the run if not given.
+    pub DURATION: Option<u64>,
+    /// Number of steps after which the regime recurs, e.g. for seasons.
+    pub PERIOD: Option<u64>,
+    /// Change to the environment while the regime is active.
+    #[serde(flatten)]
+    pub CHANGE: RegimeChange,
+}
+
+impl RegimeConfig {
+    /// Whether the regime is active at a step.
+    pub fn is_active(&self, step: u64) -> bool {
+        let Some(elapsed) = step.checked_sub(self.START) else {
+            return false;
+        };
+        let elapsed = match self.PERIOD {
+            Some(period) if period > 0 => elapsed % period,
+            _ => elapsed,
+        };
+        self.DURATION.is_none_or(|duration| elapsed < duration)
+    }
+}
+
+/// Change to the environment made by a regime, selected by `TYPE`.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(tag = "TYPE", rename_all = "snake_case")]
+pub enum RegimeChange {
+    /// Patches of a resource within the region disappear, leaving the terrain type `TERRAIN`
+    /// (by default the first terrain type).
+    Disappear {
+        RESOURCE: String,
+        REGION: Option<Region>,
+        TERRAIN: Option<String>,
+    },
+    /// Amounts of a resource acquired per step are multiplied by `MULTIPLIER`.
+    AcquireRate { RESOURCE: String, MULTIPLIER: f32 },
+    /// Patches of a resource within the region move by (`DX`, `DY`), wrapping around the board
+    /// and leaving the terrain type `TERRAIN` (by default the first terrain type).
+    Shift {
+        RESOURCE: String,
+        DX: i32,
+        DY: i32,
+        REGION: Option<Region>,
+        TERRAIN: Option<String>,
+    },
+}
+
+/// Rectangular region of the board, including its bounds.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub struct Region {
+    pub X_MIN: i32,
+    pub Y_MIN: i32,
+    pub X_MAX: i32,
+    pub Y_MAX: i32,
+}
+
+impl Region {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.X_MIN..=self.X_MAX).contains(&x) && (self.Y_MIN..=self.Y_MAX).contains(&y)
+    }
+}
+
+/// Contents of a timeline file.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+struct TimelineFile {
+    #[serde(default)]
+    timeline: Vec<RegimeConfig>,
+}
+
+/// Reads the regimes of a timeline file, given as `[[timeline]]` tables as in the config.
+pub fn read_timeline(input: &str) -> Result<Vec<RegimeConfig>, ConfigError> {
+    Ok(toml::from_str::<TimelineFile>(input)?.timeline)
+}
+
+/// Loads the regimes of a timeline file.
+pub fn load_timeline(path: &Path) -> Result<Vec<RegimeConfig>, ConfigError> {
+    let input = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+    read_timeline(&input)
+}
+
+fn default_terrain() -> Vec<TerrainConfig> {
+    vec![
+        TerrainConfig::new("land", 0.7, true, 1, "land"),
+        TerrainConfig::new("bush", 0.3, true, 1, "tree"),
+    ]
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct RLConfig {
     pub INIT_Q_VALUES: f32,
     pub SARSA_N: u8,
@@ -113,75 +804,227 @@ pub struct RLConfig {
     pub ALPHA: f32,
     pub EPSILON: f32,
     pub MULTI_POLICY: bool,
+    /// Whether to save the model at the end of a run. Set from the command-line subcommand when
+    /// one is given.
+    #[serde(default)]
     pub SAVE_MODEL: bool,
+    /// Whether to load the model from `MODEL_CHECKPOINT_FILE`. Set from the command-line
+    /// subcommand when one is given.
+    #[serde(default)]
     pub LOAD_MODEL: bool,
+    /// Model checkpoint file to load, relative to the config file setting it.
     pub MODEL_CHECKPOINT_FILE: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct TradeConfig {
     pub MAX_TRADE_DISTANCE: u32,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct SimulationConfig {
-    // TODO: consider replacing with a logging
-    /// Increasing verbosity levels of printed output:
-    ///   - 0: No printed output
-    ///   - 1: Verbose printed output
-    ///   - 2 or more: Additionally verbose printed output
-    pub VERBOSITY: u32,
+    /// Directory to write the agent histories and saved model checkpoints to.
+    #[serde(default = "default_output_dir")]
+    pub OUTPUT_DIR: String,
 }
+
+fn default_output_dir() -> String {
+    ".".to_string()
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct LogConfig {
+    /// Most detailed level of events printed to the console (`off`, `info`, `debug` or `trace`).
+    pub CONSOLE_LEVEL: LogLevel,
+    /// Most detailed level of events written to `FILE`.
+    pub FILE_LEVEL: LogLevel,
+    /// JSON-lines file to write events to, relative to `OUTPUT_DIR`. Not written if not given.
+    pub FILE: Option<String>,
+    /// Categories of events to log (`trade`, `acquisition`, `movement`, `learning_update`,
+    /// `step_summary`, `regime`, `death` and `birth`).
+    pub CATEGORIES: Vec<Category>,
+}
+
 /// Wrapper struct for parsing the `core` table.
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Config {
     /// Core configuration data.
     pub simulation: SimulationConfig,
+    #[serde(default)]
+    pub log: LogConfig,
     pub agent: AgentConfig,
     pub world: WorldConfig,
     pub trade: TradeConfig,
     pub rl: RLConfig,
+    /// Resources, indexed by `Resource`.
+    #[serde(default = "default_resources")]
+    pub resources: Vec<ResourceConfig>,
+    /// Terrain types of patches without a resource, indexed by `EnvItem::Terrain`.
+    #[serde(default = "default_terrain")]
+    pub terrain: Vec<TerrainConfig>,
+    /// Timeline of environmental regimes. Not serialized if empty, as TOML cannot give an empty
+    /// array after tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<RegimeConfig>,
+    /// Groups of agents, given the first agent ids in order. Not serialized if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<AgentGroupConfig>,
+}
+
+impl Config {
+    /// Merges the regimes of the timeline file given by `TIMELINE_FILE`, if any, into the
+    /// timeline, clearing `TIMELINE_FILE` so that they are merged only once. Returns the path
+    /// and contents of the file.
+    pub fn resolve_timeline(&mut self) -> Result<Option<(PathBuf, String)>, ConfigError> {
+        let Some(file) = self.world.TIMELINE_FILE.take() else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(file);
+        let input = fs::read_to_string(&path).map_err(|err| ConfigError::Io(path.clone(), err))?;
+        self.timeline.extend(read_timeline(&input)?);
+        Ok(Some((path, input)))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            simulation: Default::default(),
+            log: Default::default(),
+            agent: Default::default(),
+            world: Default::default(),
+            trade: Default::default(),
+            rl: Default::default(),
+            resources: default_resources(),
+            terrain: default_terrain(),
+            timeline: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl Display for Config {
+    /// Formats the config as TOML.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            toml::to_string_pretty(self).map_err(|_| std::fmt::Error)?
+        )
+    }
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        AgentConfig {
+            MAX_TRADE_LOTS: 1,
+            DISTANCE_LEVEL_CRITICAL_LOW: 2,
+            DISTANCE_LEVEL_LOW_MEDIUM: 10,
+            DISTANCE_LEVEL_MEDIUM_HIGH: 30,
+            MORTALITY: MortalityConfig::default(),
+            REPRODUCTION: ReproductionConfig::default(),
+        }
+    }
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            N_STEPS: 50000,
+            RANDOM_SEED: 0,
+            RESOURCE_LOCATIONS_FILE: None,
+            MAP_FILE: None,
+            WIDTH: 20,
+            HEIGHT: 20,
+            TOPOLOGY: Topology::default(),
+            N_AGENTS: 30,
+            HAS_TRADING: true,
+            MAP_GENERATOR: MapGeneratorConfig::default(),
+            REGROWTH: RegrowthConfig::default(),
+            PLACEMENT: PlacementConfig::default(),
+            TIMELINE_FILE: None,
+        }
+    }
+}
+
+impl Default for RLConfig {
+    fn default() -> Self {
+        RLConfig {
+            INIT_Q_VALUES: -10000.0,
+            SARSA_N: 60,
+            GAMMA: 0.99,
+            ALPHA: 0.01,
+            EPSILON: 0.01,
+            MULTI_POLICY: false,
+            SAVE_MODEL: false,
+            LOAD_MODEL: false,
+            MODEL_CHECKPOINT_FILE: None,
+        }
+    }
+}
+
+impl Default for TradeConfig {
+    fn default() -> Self {
+        TradeConfig {
+            MAX_TRADE_DISTANCE: 2,
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            OUTPUT_DIR: default_output_dir(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            CONSOLE_LEVEL: LogLevel::Info,
+            FILE_LEVEL: LogLevel::Trace,
+            FILE: None,
+            CATEGORIES: Category::iter().collect(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::TestDir;
+    use lazy_static::lazy_static;
+    use regex::Regex;
+
+    /// Parses and returns core configuration, without defaults.
+    fn parse_toml(toml_str: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str::<Config>(toml_str)
+    }
 
     #[test]
     fn test_deserialize() {
         let config_string = r##"
         [simulation]
-        VERBOSITY = 1
+
+        [log]
+        CONSOLE_LEVEL = "debug"
+        FILE_LEVEL = "info"
+        CATEGORIES = ["trade", "step_summary"]
 
         [world]
         N_STEPS = 100
         RANDOM_SEED = 123
-        LAND_PROP = 0.7
-
-        FOOD_ABUNDANCE = 0.1
-        WATER_ABUNDANCE = 0.1
-        TREE_PROB = 0.1
-        SWEET_PROB = 0.01
         N_AGENTS = 10
         WIDTH = 10
         HEIGHT = 10
         HAS_TRADING = true
 
         [agent]
-        INIT_FOOD = 0
-        INIT_WATER = 0
-        FOOD_ACQUIRE_RATE = 1
-        WATER_ACQUIRE_RATE = 1
-        FOOD_CONSUME_RATE = 1
-        WATER_CONSUME_RATE = 1
-        FOOD_MAX_INVENTORY = 456
-        WATER_MAX_INVENTORY = 1
-        FOOD_LOT_SIZE = 6
-        WATER_LOT_SIZE = 2
         MAX_TRADE_LOTS = 1
-        INVENTORY_LEVEL_CRITICAL_LOW = 0
-        INVENTORY_LEVEL_LOW_MEDIUM = 10
-        INVENTORY_LEVEL_MEDIUM_HIGH = 50
         DISTANCE_LEVEL_CRITICAL_LOW = 2
         DISTANCE_LEVEL_LOW_MEDIUM = 10
         DISTANCE_LEVEL_MEDIUM_HIGH = 30
@@ -196,13 +1039,57 @@ mod tests {
         ALPHA = 0.01
         EPSILON = 0.01
         MULTI_POLICY = false
+
+        [[resources]]
+        NAME = "Food"
+        ABUNDANCE = 0.1
+        ACQUIRE_RATE = 1
+        CONSUME_RATE = 1
+        MAX_INVENTORY = 456
+        LOT_SIZE = 6
+        TEXTURE = "fruit"
+
+        [[resources]]
+        NAME = "Water"
+        ABUNDANCE = 0.1
+        ACQUIRE_RATE = 1
+        CONSUME_RATE = 1
+        MAX_INVENTORY = 1
+        LOT_SIZE = 2
+        INVENTORY_LEVEL_MEDIUM_HIGH = 80
+        TEXTURE = "water"
+
+        [[terrain]]
+        NAME = "land"
+        PROB = 0.9
+        PASSABLE = true
+        MOVEMENT_COST = 1
+        TEXTURE = "land"
+
+        [[terrain]]
+        NAME = "rock"
+        PROB = 0.1
+        PASSABLE = false
+        MOVEMENT_COST = 1
+        TEXTURE = "evergreen_tree"
         "##;
 
         let config: Config = parse_toml(config_string).unwrap();
         println!("config fixture {:?}", config.world);
 
         assert_eq!(config.world.RANDOM_SEED, 123);
-        assert_eq!(config.agent.FOOD_MAX_INVENTORY, 456);
+        assert_eq!(config.resources[0].MAX_INVENTORY, 456);
+        // Discretisation levels not given take their defaults
+        assert_eq!(config.resources[1].INVENTORY_LEVEL_LOW_MEDIUM, 10);
+        assert_eq!(config.resources[1].INVENTORY_LEVEL_MEDIUM_HIGH, 80);
+        assert_eq!(config.log.CONSOLE_LEVEL, LogLevel::Debug);
+        assert_eq!(
+            config.log.CATEGORIES,
+            vec![Category::Trade, Category::StepSummary]
+        );
+        assert_eq!(config.terrain.len(), 2);
+        assert_eq!(config.terrain[1].NAME, "rock");
+        assert!(!config.terrain[1].PASSABLE);
     }
 
     #[test]
@@ -211,8 +1098,9 @@ mod tests {
         [world]
         RANDOM_SEED = 123
 
-        [agent]
-        FOOD_MAX_INVENTORY = 456
+        [[resources]]
+        NAME = "Food"
+        MAX_INVENTORY = 456
         "##;
 
         let config_extras_params = r##"
@@ -227,7 +1115,6 @@ mod tests {
         for config_string in [config_missing_params, config_extras_params] {
             let actual = parse_toml(config_string);
             assert!(actual.is_err());
-            assert!(!actual.is_ok());
             let actual_msg = actual.unwrap_err().to_string();
             println!("test_malformed_config:actual_msg = {actual_msg}");
             assert!(RE.is_match(actual_msg.as_str()));
@@ -235,8 +1122,307 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_missing_config() {
-        open_config_file(Path::new("does_not_exist.fakefile"));
+        let actual = load_config(Path::new("does_not_exist.fakefile"));
+        assert!(matches!(actual, Err(ConfigError::Io(..))));
+    }
+
+    #[test]
+    fn test_apply_override() {
+        let config = load_config_with_env(Path::new("clamms-config-test.toml"), []).unwrap();
+
+        let updated = apply_override(&config, "world.N_AGENTS=50").unwrap();
+        assert_eq!(updated.world.N_AGENTS, 50);
+        let updated = apply_override(&updated, "rl.EPSILON = 0.05").unwrap();
+        assert_eq!(updated.rl.EPSILON, 0.05);
+        let updated = apply_override(&updated, "world.RESOURCE_LOCATIONS_FILE=map.json").unwrap();
+        assert_eq!(
+            updated.world.RESOURCE_LOCATIONS_FILE,
+            Some("map.json".to_string())
+        );
+        // Other values are unchanged
+        assert_eq!(updated.world.WIDTH, config.world.WIDTH);
+
+        // Resources are selected by name
+        let updated = apply_override(&config, "resources.Water.ACQUIRE_RATE=3").unwrap();
+        assert_eq!(updated.resources[1].ACQUIRE_RATE, 3);
+        assert_eq!(updated.resources[0], config.resources[0]);
+        assert!(matches!(
+            apply_override(&config, "resources.Salt.ACQUIRE_RATE=3"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+
+        // Map generator parameters not given take their defaults
+        let updated = apply_override(&config, "world.MAP_GENERATOR.TYPE=noise").unwrap();
+        assert_eq!(
+            updated.world.MAP_GENERATOR,
+            MapGeneratorConfig::Noise { SCALE: 5 }
+        );
+        let updated = apply_override(&updated, "world.MAP_GENERATOR.SCALE=3").unwrap();
+        assert_eq!(
+            updated.world.MAP_GENERATOR,
+            MapGeneratorConfig::Noise { SCALE: 3 }
+        );
+        assert!(matches!(
+            apply_override(&updated, "world.MAP_GENERATOR.FILL=0.5"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+
+        // Agents do not reproduce unless a reproduction rule is selected
+        assert_eq!(config.agent.REPRODUCTION, ReproductionConfig::Off);
+        let updated = apply_override(&config, "agent.REPRODUCTION.TYPE=surplus").unwrap();
+        let updated = apply_override(&updated, "agent.REPRODUCTION.THRESHOLDS.Water=50").unwrap();
+        assert_eq!(
+            updated.agent.REPRODUCTION,
+            ReproductionConfig::Surplus {
+                THRESHOLD: 100,
+                THRESHOLDS: BTreeMap::from([("Water".to_string(), 50)]),
+                SHARE: 0.5,
+                NOISE: 0.0
+            }
+        );
+
+        // Agents are immortal unless a mortality rule is selected
+        assert_eq!(config.agent.MORTALITY, MortalityConfig::Immortal);
+        let updated = apply_override(&config, "agent.MORTALITY.TYPE=starvation").unwrap();
+        assert_eq!(
+            updated.agent.MORTALITY,
+            MortalityConfig::Starvation {
+                THRESHOLD: 0,
+                STEPS: 10
+            }
+        );
+
+        assert!(matches!(
+            apply_override(&config, "world.N_AGENT=50"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+        assert!(matches!(
+            apply_override(&config, "N_AGENTS=50"),
+            Err(ConfigError::MalformedOverride(_))
+        ));
+        assert!(matches!(
+            apply_override(&config, "world.N_AGENTS"),
+            Err(ConfigError::MalformedOverride(_))
+        ));
+        assert!(matches!(
+            apply_override(&config, "world.N_AGENTS=many"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    /// Writes a config file to a path relative to a temporary directory, returning its path.
+    fn write_temp_config(dir: &TestDir, file_name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(file_name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_config_defaults() {
+        let dir = TestDir::new("defaults");
+        let path = write_temp_config(&dir, "config.toml", "");
+        let config = load_config_with_env(&path, []).unwrap();
+        assert_eq!(config, Config::default());
+
+        let config = load_config_with_env(Path::new("clamms-config-test.toml"), []).unwrap();
+        assert_eq!(config.world.WIDTH, 6);
+        assert_eq!(config.resources[0].CONSUME_RATE, 5);
+        assert_eq!(config.rl.SARSA_N, RLConfig::default().SARSA_N);
+    }
+
+    #[test]
+    fn test_load_config_include() {
+        let dir = TestDir::new("include");
+        write_temp_config(
+            &dir,
+            "base.toml",
+            r##"
+            [world]
+            WIDTH = 42
+            N_AGENTS = 3
+            "##,
+        );
+        let path = write_temp_config(
+            &dir,
+            "scenario.toml",
+            r##"
+            include = ["base.toml"]
+            [world]
+            N_AGENTS = 5
+            "##,
+        );
+        let config = load_config_with_env(&path, []).unwrap();
+        assert_eq!(config.world.WIDTH, 42);
+        assert_eq!(config.world.N_AGENTS, 5);
+        assert_eq!(config.world.HEIGHT, WorldConfig::default().HEIGHT);
+
+        let path = write_temp_config(&dir, "cycle.toml", r##"include = "cycle.toml""##);
+        assert!(matches!(
+            load_config_with_env(&path, []),
+            Err(ConfigError::IncludeCycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_config_paths() {
+        let temp_dir = TestDir::new("paths");
+        let dir = write_temp_config(
+            &temp_dir,
+            "base.toml",
+            r##"
+            [world]
+            RESOURCE_LOCATIONS_FILE = "map.json"
+            "##,
+        )
+        .parent()
+        .unwrap()
+        .to_path_buf();
+        let path = write_temp_config(
+            &temp_dir,
+            "scenarios/scenario.toml",
+            r##"
+            include = "../base.toml"
+            [simulation]
+            OUTPUT_DIR = "out"
+            [rl]
+            MODEL_CHECKPOINT_FILE = "/checkpoints/model.json"
+            "##,
+        );
+        let config = load_config_with_env(&path, []).unwrap();
+        assert_eq!(
+            config.world.RESOURCE_LOCATIONS_FILE.map(PathBuf::from),
+            Some(dir.join("scenarios/../map.json"))
+        );
+        assert_eq!(
+            PathBuf::from(config.simulation.OUTPUT_DIR),
+            dir.join("scenarios/out")
+        );
+        assert_eq!(
+            config.rl.MODEL_CHECKPOINT_FILE,
+            Some("/checkpoints/model.json".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_config_env() {
+        let path = Path::new("clamms-config-test.toml");
+        let vars = [
+            ("CLAMMS__RL__EPSILON".to_string(), "0.05".to_string()),
+            (
+                "CLAMMS__WORLD__HAS_TRADING".to_string(),
+                "false".to_string(),
+            ),
+            ("CLAMMS_CONFIG".to_string(), "ignored.toml".to_string()),
+        ];
+        let config = load_config_with_env(path, vars).unwrap();
+        assert_eq!(config.rl.EPSILON, 0.05);
+        assert!(!config.world.HAS_TRADING);
+
+        let vars = [("CLAMMS__RL__EPSILONN".to_string(), "0.05".to_string())];
+        assert!(matches!(
+            load_config_with_env(path, vars),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_timeline() {
+        let regimes = read_timeline(
+            r##"
+            [[timeline]]
+            NAME = "drought"
+            START = 10
+            DURATION = 5
+            TYPE = "disappear"
+            RESOURCE = "Water"
+            REGION = { X_MIN = 0, Y_MIN = 0, X_MAX = 9, Y_MAX = 19 }
+
+            [[timeline]]
+            NAME = "winter"
+            START = 50
+            DURATION = 25
+            PERIOD = 100
+            TYPE = "acquire_rate"
+            RESOURCE = "Food"
+            MULTIPLIER = 0.5
+            "##,
+        )
+        .unwrap();
+        assert!(matches!(
+            regimes[0].CHANGE,
+            RegimeChange::Disappear {
+                RESOURCE: ref resource,
+                REGION: Some(_),
+                TERRAIN: None
+            } if resource == "Water"
+        ));
+        let drought: Vec<u64> = (0..200).filter(|&t| regimes[0].is_active(t)).collect();
+        assert_eq!(drought, (10..15).collect::<Vec<u64>>());
+        let winter: Vec<u64> = (0..200).filter(|&t| regimes[1].is_active(t)).collect();
+        assert_eq!(winter, (50..75).chain(150..175).collect::<Vec<u64>>());
+
+        // Regimes round trip through the config, including for overrides
+        let config = Config {
+            timeline: regimes,
+            ..Default::default()
+        };
+        assert_eq!(parse_toml(&config.to_string()).unwrap(), config);
+        let updated = apply_override(&config, "world.N_AGENTS=5").unwrap();
+        assert_eq!(updated.timeline, config.timeline);
+
+        let regimes = load_timeline(Path::new("clamms-timeline.toml")).unwrap();
+        assert_eq!(regimes.len(), 3);
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut config = Config::default();
+        config.world.PLACEMENT = apply_override(&config, "world.PLACEMENT.TYPE=clusters")
+            .unwrap()
+            .world
+            .PLACEMENT;
+        let config = parse_toml(&format!(
+            r#"
+            {}
+            [[groups]]
+            N_AGENTS = 5
+            INIT = {{ Food = 80 }}
+            PLACEMENT = {{ TYPE = "near_resource", RESOURCE = "Food" }}
+
+            [[groups]]
+            N_AGENTS = 5
+            "#,
+            config
+        ))
+        .unwrap();
+        assert_eq!(
+            config.world.PLACEMENT,
+            PlacementConfig::Clusters {
+                N_CLUSTERS: 3,
+                SPREAD: 2.0
+            }
+        );
+        assert_eq!(
+            config.groups[0].PLACEMENT,
+            PlacementConfig::NearResource {
+                RESOURCE: "Food".to_string(),
+                RADIUS: 3
+            }
+        );
+        assert_eq!(config.groups[0].INIT["Food"], 80);
+        assert!(config.groups[1].INIT.is_empty());
+        assert_eq!(config.groups[1].PLACEMENT, PlacementConfig::Random);
+
+        // Groups round trip through the config
+        assert_eq!(parse_toml(&config.to_string()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut config = load_config_with_env(Path::new("clamms-config.toml"), []).unwrap();
+        config.rl.EPSILON = 0.05;
+        assert_eq!(parse_toml(&config.to_string()).unwrap(), config);
     }
 }
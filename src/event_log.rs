@@ -0,0 +1,312 @@
+//! Structured log of simulation events, with a pretty console sink and a JSON-lines file sink.
+use crate::config::LogConfig;
+use crate::model::action::Action;
+use crate::model::board::ClammsInt2D;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use strum_macros::EnumIter;
+
+/// Increasing levels of detail of logged events. A sink logs events at or below its level.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// No events.
+    Off,
+    /// Step summaries and changes of environmental regime.
+    Info,
+    /// Trades, deaths and births.
+    Debug,
+    /// Acquisitions, movements and learning updates of every agent.
+    Trace,
+}
+
+/// Categories of logged events.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Trade,
+    Acquisition,
+    Movement,
+    LearningUpdate,
+    StepSummary,
+    Regime,
+    Death,
+    Birth,
+}
+
+/// Details of a logged event.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "category", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Trade settled between two agents, with the lots of each resource the agent receives.
+    Trade {
+        agent: u32,
+        counterparty: u32,
+        /// Lots received by resource name, negative for lots given.
+        lots: BTreeMap<String, i32>,
+    },
+    /// Resource gathered by an agent from the patch it is on.
+    Acquisition {
+        agent: u32,
+        resource: String,
+        amount: i32,
+    },
+    /// Action taken by an agent and the resulting move.
+    Movement {
+        agent: u32,
+        action: Action,
+        from: ClammsInt2D,
+        to: ClammsInt2D,
+    },
+    /// SARSA update of the Q-value of an agent's state-action at time `tau`.
+    LearningUpdate {
+        agent: u32,
+        tau: usize,
+        old_q: f32,
+        new_q: f32,
+    },
+    /// Summary at the end of a step.
+    StepSummary {
+        trades: usize,
+        mean_reward: f32,
+        /// Mean reward of agent 0 over the last 100 steps.
+        agent_0_recent_mean_reward: i32,
+    },
+    /// Environmental regime on the timeline starting or ending at the start of a step.
+    Regime { name: String, active: bool },
+    /// Death of an agent at the end of a step, with its inventory of each resource by name.
+    Death {
+        agent: u32,
+        inventory: BTreeMap<String, i32>,
+    },
+    /// Birth of an agent to a parent at the end of a step.
+    Birth { agent: u32, parent: u32 },
+}
+
+impl EventKind {
+    pub fn category(&self) -> Category {
+        match self {
+            EventKind::Trade { .. } => Category::Trade,
+            EventKind::Acquisition { .. } => Category::Acquisition,
+            EventKind::Movement { .. } => Category::Movement,
+            EventKind::LearningUpdate { .. } => Category::LearningUpdate,
+            EventKind::StepSummary { .. } => Category::StepSummary,
+            EventKind::Regime { .. } => Category::Regime,
+            EventKind::Death { .. } => Category::Death,
+            EventKind::Birth { .. } => Category::Birth,
+        }
+    }
+
+    pub fn level(&self) -> LogLevel {
+        match self.category() {
+            Category::StepSummary | Category::Regime => LogLevel::Info,
+            Category::Trade | Category::Death | Category::Birth => LogLevel::Debug,
+            Category::Acquisition | Category::Movement | Category::LearningUpdate => {
+                LogLevel::Trace
+            }
+        }
+    }
+}
+
+/// A logged event.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Event {
+    pub step: u64,
+    pub level: LogLevel,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let category = serde_json::to_value(self.kind.category()).map_err(|_| std::fmt::Error)?;
+        write!(
+            f,
+            "[step {:>6}] {:<5} {:<15}",
+            self.step,
+            format!("{:?}", self.level).to_uppercase(),
+            category.as_str().unwrap_or_default()
+        )?;
+        if let serde_json::Value::Object(fields) =
+            serde_json::to_value(&self.kind).map_err(|_| std::fmt::Error)?
+        {
+            for (key, value) in fields.iter().filter(|(key, _)| *key != "category") {
+                write!(f, " {key}={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Event log writing events to the console and, optionally, a JSON-lines file.
+#[derive(Debug)]
+pub struct EventLog {
+    console_level: LogLevel,
+    file_level: LogLevel,
+    categories: Vec<Category>,
+    file: Option<BufWriter<File>>,
+    /// First error writing to the file sink, returned when the log is flushed.
+    error: Option<std::io::Error>,
+}
+
+impl EventLog {
+    /// Constructs an event log writing to the console only.
+    pub fn new(config: &LogConfig) -> Self {
+        EventLog {
+            console_level: config.CONSOLE_LEVEL,
+            file_level: config.FILE_LEVEL,
+            categories: config.CATEGORIES.to_owned(),
+            file: None,
+            error: None,
+        }
+    }
+
+    /// Adds a JSON-lines file sink, creating (or truncating) the file at the given path.
+    pub fn with_file(mut self, path: &Path) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.file = Some(BufWriter::new(File::create(path)?));
+        Ok(self)
+    }
+
+    /// Whether events of the given category and level are written to any sink.
+    pub fn enabled(&self, category: Category, level: LogLevel) -> bool {
+        self.categories.contains(&category)
+            && (level <= self.console_level || (self.file.is_some() && level <= self.file_level))
+    }
+
+    /// Logs an event at the given step.
+    pub fn log(&mut self, step: u64, kind: EventKind) {
+        let level = kind.level();
+        if !self.enabled(kind.category(), level) {
+            return;
+        }
+        let event = Event { step, level, kind };
+        if level <= self.console_level {
+            println!("{event}");
+        }
+        if let Some(file) = self.file.as_mut().filter(|_| level <= self.file_level) {
+            // A failure to write the log is not fatal to the run, so the sink is dropped and the
+            // error kept until the log is flushed
+            if let Err(err) = serde_json::to_writer(&mut *file, &event)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(file))
+            {
+                self.error = Some(err);
+                self.file = None;
+            }
+        }
+    }
+
+    /// Flushes the file sink, if any, or returns the error that stopped writing to it.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{test_config, TestDir};
+
+    fn trade() -> EventKind {
+        EventKind::Trade {
+            agent: 0,
+            counterparty: 1,
+            lots: BTreeMap::from([("Food".to_string(), 2), ("Water".to_string(), -2)]),
+        }
+    }
+
+    #[test]
+    fn test_event_display() {
+        let event = Event {
+            step: 3,
+            level: LogLevel::Debug,
+            kind: trade(),
+        };
+        assert_eq!(
+            event.to_string(),
+            "[step      3] DEBUG trade           agent=0 counterparty=1 lots={\"Food\":2,\"Water\":-2}"
+        );
+    }
+
+    #[test]
+    fn test_event_log() {
+        let mut config = test_config().log;
+        config.CONSOLE_LEVEL = LogLevel::Off;
+        config.FILE_LEVEL = LogLevel::Debug;
+        config.CATEGORIES = vec![Category::Trade, Category::Acquisition];
+        let dir = TestDir::new("event_log");
+        let path = dir.path().join("events.jsonl");
+        let mut log = EventLog::new(&config).with_file(&path).unwrap();
+        assert!(log.enabled(Category::Trade, LogLevel::Debug));
+        assert!(!log.enabled(Category::Acquisition, LogLevel::Trace));
+        assert!(!log.enabled(Category::StepSummary, LogLevel::Info));
+
+        log.log(1, trade());
+        log.log(
+            1,
+            EventKind::Acquisition {
+                agent: 0,
+                resource: "Food".to_string(),
+                amount: 10,
+            },
+        );
+        log.log(
+            2,
+            EventKind::StepSummary {
+                trades: 1,
+                mean_reward: 0.0,
+                agent_0_recent_mean_reward: 0,
+            },
+        );
+        log.flush().unwrap();
+
+        let events: Vec<Event> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![Event {
+                step: 1,
+                level: LogLevel::Debug,
+                kind: trade()
+            }]
+        );
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with(r#"{"step":1,"level":"debug","category":"trade","agent":0"#));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_event_log_write_error() {
+        // Writing to a full device fails once the buffer fills, after which logging goes on
+        // without the file sink and the error is returned on flushing
+        let mut config = test_config().log;
+        config.CONSOLE_LEVEL = LogLevel::Off;
+        config.FILE_LEVEL = LogLevel::Debug;
+        let mut log = EventLog::new(&config)
+            .with_file(Path::new("/dev/full"))
+            .unwrap();
+        for step in 0..1000 {
+            log.log(step, trade());
+        }
+        assert!(log.file.is_none());
+        assert!(log.flush().is_err());
+        assert!(log.flush().is_ok());
+    }
+}
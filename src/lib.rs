@@ -0,0 +1,15 @@
+//! CLAMMS: a library for agent-based simulations of foraging and trading agents that learn with
+//! tabular reinforcement learning.
+//!
+//! Simulations are built from a [`config::Config`] with [`simulation::Simulation::builder`],
+//! optionally with a map, model and policy in place of those given by the config.
+pub mod config;
+pub mod event_log;
+pub mod model;
+pub mod provenance;
+pub mod simulation;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod sweep;
+pub mod validation;
+#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
+pub mod visualization;
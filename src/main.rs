@@ -1,117 +1,122 @@
-use krabmaga::*;
-mod config;
-mod model;
-use crate::config::core_config;
-use crate::model::{
-    action::Action,
-    agent_state::{AgentStateItems, InvLevel},
-    board::Board,
-    tabular_rl::SARSAModel,
-};
-use krabmaga::engine::{schedule::Schedule, state::State};
-use strum::IntoEnumIterator;
+mod cli;
 
 // Visualization specific imports
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 use {
-    crate::visualization::board_vis::BoardVis, krabmaga::bevy::prelude::Color,
+    clamms::model::board::Board, clamms::simulation::SimulationBuilder,
+    clamms::visualization::board_vis::BoardVis, krabmaga::bevy::prelude::Color,
     krabmaga::visualization::visualization::Visualization,
 };
 
-#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
-mod visualization;
-
 // Main used when only the simulation should run, without any visualization.
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
 fn main() {
-    let seed = core_config().world.RANDOM_SEED;
-    let n_steps = core_config().world.N_STEPS;
-    let num_agents = core_config().world.N_AGENTS;
-    let dim: (u16, u16) = (core_config().world.WIDTH, core_config().world.HEIGHT);
-    let has_trading = core_config().world.HAS_TRADING;
-    let multi_policy = core_config().rl.MULTI_POLICY;
+    use crate::cli::{Cli, Command};
+    use clamms::simulation;
+    use clamms::sweep::{run_sweep, SweepConfig};
+    use clamms::validation;
+    use clap::Parser;
 
-    let model;
-    if core_config().rl.LOAD_MODEL {
-        model = SARSAModel::load(
-            core_config()
-                .rl
-                .MODEL_CHECKPOINT_FILE
-                .as_ref()
-                .expect("path to model checkpoint file needed to load model"),
-        );
-    } else {
-        model = SARSAModel::new(
-            (0..num_agents).map(|n| n.into()).collect(),
-            AgentStateItems::iter().collect::<Vec<AgentStateItems>>(),
-            InvLevel::iter().collect::<Vec<InvLevel>>(),
-            Action::iter().collect::<Vec<Action>>(),
-            multi_policy,
-        );
+    let cli = Cli::parse();
+    if let Some(Command::Rerun { file }) = &cli.command {
+        match simulation::rerun(
+            file,
+            cli.common.output_dir.as_deref(),
+            cli.common.output.as_deref(),
+        ) {
+            Ok(outcome) => {
+                if let Some(version) = &outcome.recorded_version {
+                    eprintln!(
+                        "Warning: {} was produced by version {version}, rerunning with version {}",
+                        file.display(),
+                        env!("CARGO_PKG_VERSION")
+                    );
+                }
+                if outcome.reproduced {
+                    println!("Rerun reproduced the recorded results.")
+                } else {
+                    eprintln!("Rerun did not reproduce the recorded results.");
+                    std::process::exit(1)
+                }
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1)
+            }
+        }
+        return;
     }
-
-    // let mut board = Board::new_with_seed(dim, num_agents, seed, model);
-    let mut board = if let Some(file_name) = &core_config().world.RESOURCE_LOCATIONS_FILE {
-        Board::new_with_seed_resources(dim, num_agents, seed, file_name, model, has_trading)
-    } else {
-        Board::new_with_seed(dim, num_agents, seed, model, has_trading)
-    };
-
-    // Use scheduler and run directly once
-    let mut schedule: Schedule = Schedule::new();
-    board.init(&mut schedule);
-    for _ in 0..n_steps {
-        schedule.step(&mut board);
+    let config = cli.resolve_config().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1)
+    });
+    if let Some(Command::PrintConfig) = cli.command {
+        print!("{config}");
+        return;
+    }
+    if let Err(err) = validation::validate(&config) {
+        eprintln!("{err}");
+        std::process::exit(1)
+    }
+    if let Some(Command::Check) = cli.command {
+        println!("Config is valid.");
+        return;
+    }
+    if let Some(Command::Sweep { file, jobs }) = &cli.command {
+        if let Err(err) = SweepConfig::open(file)
+            .and_then(|sweep| run_sweep(&sweep, &config, *jobs, |run| println!("Starting {run}")))
+        {
+            eprintln!("{err}");
+            std::process::exit(1)
+        }
+        return;
     }
 
-    // Open output file and write history
-    let mut f = File::create("output.json").unwrap();
-    writeln!(
-        f,
-        "{}",
-        serde_json::to_string_pretty(&board.agent_histories).unwrap()
-    )
-    .unwrap();
-
-    // Save model to file
-    if core_config().rl.SAVE_MODEL {
-        board.model.save()
+    // Run, then write history and any saved model to the output directory
+    if let Err(err) = simulation::run(config)
+        .and_then(|simulation| simulation.write_outputs(cli.common.output.as_deref()))
+    {
+        eprintln!("{err}");
+        std::process::exit(1)
     }
 }
 
 // Main used when a visualization feature is applied.
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 fn main() {
-    let num_agents = core_config().world.N_AGENTS;
-    let seed = core_config().world.RANDOM_SEED;
-    let dim: (u16, u16) = (core_config().world.WIDTH, core_config().world.HEIGHT);
-    let has_trading = core_config().world.HAS_TRADING;
-    let multi_policy = core_config().rl.MULTI_POLICY;
+    use crate::cli::{Cli, Command};
+    use clamms::validation;
+    use clap::Parser;
 
-    let model;
-    if core_config().rl.LOAD_MODEL {
-        model = SARSAModel::load(
-            core_config()
-                .rl
-                .MODEL_CHECKPOINT_FILE
-                .as_ref()
-                .expect("path to model checkpoint file needed to load model"),
-        );
-    } else {
-        model = SARSAModel::new(
-            (0..num_agents).map(|n| n.into()).collect(),
-            AgentStateItems::iter().collect::<Vec<AgentStateItems>>(),
-            InvLevel::iter().collect::<Vec<InvLevel>>(),
-            Action::iter().collect::<Vec<Action>>(),
-            multi_policy,
-        );
+    let cli = Cli::parse();
+    if let Some(Command::Sweep { .. } | Command::Rerun { .. }) = cli.command {
+        eprintln!("Sweeps and reruns are not supported with the visualization.");
+        std::process::exit(1)
+    }
+    let config = cli.resolve_config().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1)
+    });
+    if let Some(Command::PrintConfig) = cli.command {
+        print!("{config}");
+        return;
+    }
+    if let Err(err) = validation::validate(&config) {
+        eprintln!("{err}");
+        std::process::exit(1)
+    }
+    if let Some(Command::Check) = cli.command {
+        println!("Config is valid.");
+        return;
     }
+    let dim: (u16, u16) = (config.world.WIDTH, config.world.HEIGHT);
 
-    let state = if let Some(file_name) = &core_config().world.RESOURCE_LOCATIONS_FILE {
-        Board::new_with_seed_resources(dim, num_agents, seed, file_name, model, has_trading)
-    } else {
-        Board::new_with_seed(dim, num_agents, seed, model, has_trading)
-    };
+    let (state, _) = SimulationBuilder::new(config)
+        .build_board()
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1)
+        });
     Visualization::default()
         // .with_window_dimensions((dim.0+2).into(), (dim.1+2).into())
         .with_simulation_dimensions((dim.0 + 1).into(), (dim.1 + 1).into())
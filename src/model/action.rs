@@ -1,24 +1,20 @@
-use rand::{
-    distributions::{Distribution, Standard},
-    Rng,
-};
+use super::environment::Resource;
+use crate::config::ResourceConfig;
 use serde::{Deserialize, Serialize};
-use strum_macros::EnumIter;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, EnumIter, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Hash, Eq)]
 pub enum Action {
-    ToFood,
-    ToWater,
+    ToResource(Resource),
     ToAgent,
     Stationary,
 }
 
-impl Distribution<Action> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Action {
-        match rng.gen_range(0..=2) {
-            0 => Action::ToFood,
-            1 => Action::ToWater,
-            _ => Action::Stationary,
-        }
+impl Action {
+    /// Gets every action for the configured resources, with moves before staying stationary.
+    pub fn all(resources: &[ResourceConfig]) -> Vec<Action> {
+        Resource::all(resources)
+            .map(Action::ToResource)
+            .chain([Action::ToAgent, Action::Stationary])
+            .collect()
     }
 }
@@ -0,0 +1,170 @@
+//! Agents on the board indexed by id and by location, kept in step with the agent grid.
+use super::routing::Position;
+use super::trader::Trader;
+use crate::config::Topology;
+use krabmaga::engine::location::Int2D;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Store of the agents on the board by id, with an index of the agents at each location.
+///
+/// Like the agent grid, the registry is double buffered: agents written during a step are only
+/// readable after the next `lazy_update`, so that every agent reads the board as it was at the
+/// start of the step.
+#[derive(Default)]
+pub struct AgentRegistry {
+    /// Agents readable during the current step, by id.
+    agents: BTreeMap<u32, Trader>,
+    /// Ids of the readable agents at each location, in increasing order.
+    cells: HashMap<Int2D, Vec<u32>>,
+    /// Agents written during the current step, readable after the next update.
+    written: BTreeMap<u32, Trader>,
+}
+
+impl AgentRegistry {
+    /// Writes an agent, readable after the next update.
+    pub fn set(&mut self, trader: Trader) {
+        self.written.insert(trader.id(), trader);
+    }
+
+    /// Makes the agents written since the last update readable, as for the agent grid.
+    pub fn lazy_update(&mut self) {
+        self.agents = std::mem::take(&mut self.written);
+        self.cells.clear();
+        for (&id, trader) in &self.agents {
+            self.cells
+                .entry(trader.get_position())
+                .or_default()
+                .push(id);
+        }
+    }
+
+    /// Removes every agent.
+    pub fn clear(&mut self) {
+        *self = AgentRegistry::default();
+    }
+
+    /// Gets an agent by id.
+    pub fn get(&self, id: &u32) -> Option<&Trader> {
+        self.agents.get(id)
+    }
+
+    /// Gets the agents in increasing order of id.
+    pub fn agents(&self) -> impl Iterator<Item = &Trader> + '_ {
+        self.agents.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Gets the ids of the agents at a location.
+    pub fn at(&self, pos: &Int2D) -> &[u32] {
+        self.cells.get(pos).map_or(&[], Vec::as_slice)
+    }
+
+    /// Gets the ids of the agents within a number of steps of a location under a topology,
+    /// ignoring obstacles, in increasing order of id.
+    pub fn within(&self, pos: &Int2D, steps: u32, topology: Topology, dim: (u16, u16)) -> Vec<u32> {
+        let radius = i32::try_from(steps).unwrap_or(i32::MAX);
+        let n_cells = (2 * i64::from(radius) + 1).pow(2);
+        let mut ids: Vec<u32> = if n_cells > self.agents.len() as i64 {
+            // Fewer agents than locations in range, so check every agent
+            self.agents
+                .iter()
+                .filter(|(_, trader)| {
+                    topology.step_distance(pos, &trader.get_position(), dim) <= steps
+                })
+                .map(|(&id, _)| id)
+                .collect()
+        } else {
+            let (width, height) = (i32::from(dim.0), i32::from(dim.1));
+            let mut visited = HashSet::new();
+            let mut ids = Vec::new();
+            for dx in -radius..=radius {
+                let reach = radius - dx.abs();
+                for dy in -reach..=reach {
+                    let (x, y) = (pos.x + dx, pos.y + dy);
+                    let cell = match topology {
+                        Topology::Bounded => {
+                            if !((0..width).contains(&x) && (0..height).contains(&y)) {
+                                continue;
+                            }
+                            Int2D { x, y }
+                        }
+                        Topology::Torus => Int2D {
+                            x: x.rem_euclid(width),
+                            y: y.rem_euclid(height),
+                        },
+                    };
+                    if visited.insert(cell) {
+                        ids.extend_from_slice(self.at(&cell));
+                    }
+                }
+            }
+            ids
+        };
+        ids.sort_unstable();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::forager::Forager;
+    use crate::model::test_config;
+
+    #[test]
+    fn test_registry() {
+        let resources = test_config().resources;
+        let trader = |id, x, y| Trader::new(Forager::new(id, Int2D { x, y }, &[0, 0], &resources));
+        let dim = (10, 10);
+        let mut registry = AgentRegistry::default();
+        let scattered = [(0, 0), (1, 0), (9, 0), (5, 5), (5, 6)];
+        let north_row = (0..10).map(|x| (x, 9));
+        for (id, (x, y)) in scattered.into_iter().chain(north_row).enumerate() {
+            registry.set(trader(id as u32, x, y));
+        }
+
+        // Written agents are readable after an update
+        assert!(registry.is_empty());
+        registry.lazy_update();
+        assert_eq!(registry.len(), 15);
+        assert!(registry.get(&3).unwrap().get_position() == Int2D { x: 5, y: 5 });
+        assert_eq!(registry.at(&Int2D { x: 5, y: 6 }), &[4]);
+
+        // Neighbours found by scanning the agents or the locations in range agree
+        let origin = Int2D { x: 0, y: 0 };
+        assert_eq!(
+            registry.within(&origin, 1, Topology::Bounded, dim),
+            vec![0, 1]
+        );
+        assert_eq!(
+            registry.within(&origin, 1, Topology::Torus, dim),
+            vec![0, 1, 2, 5]
+        );
+        assert_eq!(
+            registry.within(&Int2D { x: 4, y: 9 }, 1, Topology::Bounded, dim),
+            vec![8, 9, 10]
+        );
+        assert_eq!(
+            registry.within(&Int2D { x: 5, y: 5 }, 0, Topology::Bounded, dim),
+            vec![3]
+        );
+        assert_eq!(
+            registry.within(&Int2D { x: 4, y: 5 }, 2, Topology::Bounded, dim),
+            vec![3, 4]
+        );
+
+        // Agents move when rewritten, and are gone unless rewritten before an update
+        registry.set(trader(3, 1, 1));
+        registry.lazy_update();
+        assert_eq!(registry.len(), 1);
+        assert!(registry.at(&Int2D { x: 5, y: 5 }).is_empty());
+        assert_eq!(registry.within(&origin, 2, Topology::Bounded, dim), vec![3]);
+    }
+}
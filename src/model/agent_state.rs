@@ -1,129 +1,120 @@
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
-use crate::config::core_config;
+use super::environment::Resource;
+use crate::config::{Config, ResourceConfig};
 
 pub trait DiscrRep<S, L> {
-    fn representation(&self) -> Vec<(S, L)>;
+    fn representation(&self, config: &Config) -> Vec<(S, L)>;
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentState {
-    pub food: i32,
-    pub water: i32,
-    pub min_steps_to_food: Option<u32>,
-    pub min_steps_to_water: Option<u32>,
+    /// Inventory of each resource, indexed by `Resource`.
+    pub inventory: Vec<i32>,
+    /// Steps to the nearest patch of each resource, indexed by `Resource`.
+    pub min_steps_to_resources: Vec<Option<u32>>,
     pub min_steps_to_trader: Option<u32>,
 }
 
-#[derive(Debug, Clone, PartialEq, EnumIter, Hash, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Hash, Eq, Serialize, Deserialize)]
 pub enum AgentStateItems {
-    Food,
-    Water,
-    MinStepsToFood,
-    MinStepsToWater,
+    Inventory(Resource),
+    MinStepsToResource(Resource),
     MinStepsToTrader,
 }
 
+impl AgentStateItems {
+    /// Gets the items of the agent state for the configured resources, in the order of its
+    /// representation.
+    pub fn all(resources: &[ResourceConfig]) -> Vec<AgentStateItems> {
+        Resource::all(resources)
+            .map(AgentStateItems::Inventory)
+            .chain(Resource::all(resources).map(AgentStateItems::MinStepsToResource))
+            .chain(std::iter::once(AgentStateItems::MinStepsToTrader))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentStateDiscrete {
-    pub food: InvLevel,
-    pub water: InvLevel,
-    pub min_steps_to_food: InvLevel,
-    pub min_steps_to_water: InvLevel,
+    pub inventory: Vec<InvLevel>,
+    pub min_steps_to_resources: Vec<InvLevel>,
     pub min_steps_to_trader: InvLevel,
 }
 
 impl DiscrRep<AgentStateItems, InvLevel> for AgentState {
-    fn representation(&self) -> Vec<(AgentStateItems, InvLevel)> {
-        let discr = self.discretise();
-
-        vec![
-            (AgentStateItems::Food, discr.food),
-            (AgentStateItems::Water, discr.water),
-            (AgentStateItems::MinStepsToFood, discr.min_steps_to_food),
-            (AgentStateItems::MinStepsToWater, discr.min_steps_to_water),
-            (AgentStateItems::MinStepsToTrader, discr.min_steps_to_trader),
-        ]
+    fn representation(&self, config: &Config) -> Vec<(AgentStateItems, InvLevel)> {
+        let discr = self.discretise(config);
+
+        let inventory = discr
+            .inventory
+            .into_iter()
+            .enumerate()
+            .map(|(i, level)| (AgentStateItems::Inventory(Resource(i)), level));
+        let min_steps_to_resources = discr
+            .min_steps_to_resources
+            .into_iter()
+            .enumerate()
+            .map(|(i, level)| (AgentStateItems::MinStepsToResource(Resource(i)), level));
+        inventory
+            .chain(min_steps_to_resources)
+            .chain(std::iter::once((
+                AgentStateItems::MinStepsToTrader,
+                discr.min_steps_to_trader,
+            )))
+            .collect()
     }
 }
 
-impl AgentState {
-    pub fn discretise(&self) -> AgentStateDiscrete {
-        let f: InvLevel;
-        let w: InvLevel;
-        let m_s_f: InvLevel;
-        let m_s_w: InvLevel;
-        let m_s_t: InvLevel;
-
-        if self.food < core_config().agent.INVENTORY_LEVEL_CRITICAL_LOW {
-            f = InvLevel::Critical
-        } else if self.food < core_config().agent.INVENTORY_LEVEL_LOW_MEDIUM {
-            f = InvLevel::Low
-        } else if self.food < core_config().agent.INVENTORY_LEVEL_MEDIUM_HIGH {
-            f = InvLevel::Medium
-        } else {
-            f = InvLevel::High
-        }
-
-        if self.water < core_config().agent.INVENTORY_LEVEL_CRITICAL_LOW {
-            w = InvLevel::Critical
-        } else if self.water < core_config().agent.INVENTORY_LEVEL_LOW_MEDIUM {
-            w = InvLevel::Low
-        } else if self.water < core_config().agent.INVENTORY_LEVEL_MEDIUM_HIGH {
-            w = InvLevel::Medium
-        } else {
-            w = InvLevel::High
-        }
-
-        if let Some(dist) = self.min_steps_to_food {
-            if dist < core_config().agent.DISTANCE_LEVEL_CRITICAL_LOW {
-                m_s_f = InvLevel::Critical
-            } else if dist < core_config().agent.DISTANCE_LEVEL_LOW_MEDIUM {
-                m_s_f = InvLevel::Low
-            } else if dist < core_config().agent.DISTANCE_LEVEL_MEDIUM_HIGH {
-                m_s_f = InvLevel::Medium
-            } else {
-                m_s_f = InvLevel::High
-            }
-        } else {
-            m_s_f = InvLevel::High
-        }
+/// Gets the level of a value given the thresholds between critical and low, low and medium, and
+/// medium and high levels.
+fn level<T: PartialOrd>(value: T, thresholds: [T; 3]) -> InvLevel {
+    let [critical_low, low_medium, medium_high] = thresholds;
+    if value < critical_low {
+        InvLevel::Critical
+    } else if value < low_medium {
+        InvLevel::Low
+    } else if value < medium_high {
+        InvLevel::Medium
+    } else {
+        InvLevel::High
+    }
+}
 
-        if let Some(dist) = self.min_steps_to_water {
-            if dist < core_config().agent.DISTANCE_LEVEL_CRITICAL_LOW {
-                m_s_w = InvLevel::Critical
-            } else if dist < core_config().agent.DISTANCE_LEVEL_LOW_MEDIUM {
-                m_s_w = InvLevel::Low
-            } else if dist < core_config().agent.DISTANCE_LEVEL_MEDIUM_HIGH {
-                m_s_w = InvLevel::Medium
-            } else {
-                m_s_w = InvLevel::High
-            }
-        } else {
-            m_s_w = InvLevel::High
-        }
+/// Gets the level of a distance, which is high if there is no route.
+fn distance_level(dist: Option<u32>, thresholds: [u32; 3]) -> InvLevel {
+    dist.map_or(InvLevel::High, |dist| level(dist, thresholds))
+}
 
-        if let Some(dist) = self.min_steps_to_trader {
-            if dist < core_config().agent.DISTANCE_LEVEL_CRITICAL_LOW {
-                m_s_t = InvLevel::Critical
-            } else if dist < core_config().agent.DISTANCE_LEVEL_LOW_MEDIUM {
-                m_s_t = InvLevel::Low
-            } else if dist < core_config().agent.DISTANCE_LEVEL_MEDIUM_HIGH {
-                m_s_t = InvLevel::Medium
-            } else {
-                m_s_t = InvLevel::High
-            }
-        } else {
-            m_s_t = InvLevel::High
-        }
+impl AgentState {
+    pub fn discretise(&self, config: &Config) -> AgentStateDiscrete {
+        let inventory = self
+            .inventory
+            .iter()
+            .zip(&config.resources)
+            .map(|(count, resource)| level(*count, resource.inventory_levels()))
+            .collect();
+        let min_steps_to_resources = self
+            .min_steps_to_resources
+            .iter()
+            .zip(&config.resources)
+            .map(|(dist, resource)| distance_level(*dist, resource.distance_levels()))
+            .collect();
+        let agent = &config.agent;
+        let min_steps_to_trader = distance_level(
+            self.min_steps_to_trader,
+            [
+                agent.DISTANCE_LEVEL_CRITICAL_LOW,
+                agent.DISTANCE_LEVEL_LOW_MEDIUM,
+                agent.DISTANCE_LEVEL_MEDIUM_HIGH,
+            ],
+        );
 
         AgentStateDiscrete {
-            food: f,
-            water: w,
-            min_steps_to_food: m_s_f,
-            min_steps_to_water: m_s_w,
-            min_steps_to_trader: m_s_t,
+            inventory,
+            min_steps_to_resources,
+            min_steps_to_trader,
         }
     }
 
@@ -1,15 +1,31 @@
 use super::agent_api::AgentAPI;
+use super::agent_registry::AgentRegistry;
 use super::environment::Resource;
-use super::history::History;
-use super::trader::Trader;
-use crate::config::core_config;
+use super::history::{History, SAR};
+use super::inventory::Inventory;
+use super::reward::Reward;
+use super::trader::{resolve_trades, Trader};
+use crate::config::{
+    AgentGroupConfig, Config, ConfigError, RegimeChange, RegrowthConfig, ReproductionConfig,
+    ResourceConfig, Topology,
+};
+use crate::event_log::{Category, EventKind, EventLog, LogLevel};
 
 use super::action::Action;
 use super::agent_state::{AgentState, AgentStateItems, InvLevel};
+use super::policy::{ActionPolicy, EpsilonGreedy};
 use super::tabular_rl::SARSAModel;
-use super::{environment::EnvItem, forager::Forager};
+use super::{
+    environment::{EnvItem, EnvItemDistribution, TerrainDistribution},
+    forager::{Direction, Forager, PendingStep},
+    grid_map::{GridMap, LegendEntry},
+    map_generator::generate_resource_locations,
+    placement::{place_agents, Sites},
+    routing::{PathDistances, Position, DIRECTIONS},
+    stock::Stock,
+    timeline::apply_change,
+};
 use itertools::Itertools;
-use krabmaga::cfg_if::cfg_if;
 use krabmaga::engine::fields::dense_object_grid_2d::DenseGrid2D;
 use krabmaga::engine::fields::field::Field;
 use krabmaga::engine::schedule::Schedule;
@@ -21,17 +37,29 @@ use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
-use strum::IntoEnumIterator;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Patch {
     pub id: u32,
     pub env_item: EnvItem,
+    /// Amount of the resource acquired per step on the patch, in place of the acquire rate in the
+    /// agent config.
+    pub resource_yield: Option<i32>,
+    /// Stock of the resource on the patch, if resources are depleted by harvesting.
+    pub stock: Option<Stock>,
 }
 
 impl Patch {
     pub fn new(id: u32, env_item: EnvItem) -> Self {
-        Patch { id, env_item }
+        Patch {
+            id,
+            env_item,
+            resource_yield: None,
+            stock: None,
+        }
     }
 }
 
@@ -73,149 +101,429 @@ impl From<ClammsInt2D> for Int2D {
         }
     }
 }
-///
-pub fn read_resource_locations(input: &str) -> BTreeMap<Resource, Vec<Int2D>> {
-    serde_json::from_str::<BTreeMap<Resource, Vec<ClammsInt2D>>>(input)
-        .unwrap()
-        .into_iter()
-        .map(|(k, v)| (k, v.into_iter().map(Int2D::from).collect()))
+
+impl From<Int2D> for ClammsInt2D {
+    fn from(value: Int2D) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+/// Reads resource locations from a JSON map of resource names to coordinates.
+pub fn read_resource_locations(
+    input: &str,
+) -> Result<BTreeMap<String, Vec<Int2D>>, serde_json::Error> {
+    Ok(
+        serde_json::from_str::<BTreeMap<String, Vec<ClammsInt2D>>>(input)?
+            .into_iter()
+            .map(|(k, v)| (k, v.into_iter().map(Int2D::from).collect()))
+            .collect(),
+    )
+}
+
+/// An error relating to loading a resource locations file.
+#[derive(Error, Debug)]
+pub enum MapError {
+    /// Map file could not be read.
+    #[error("Unable to read the resource locations file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Map file contents could not be parsed.
+    #[error("Unable to parse the resource locations file {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    /// Timeline file could not be read or parsed.
+    #[error("Unable to load the timeline file {0}: {1}")]
+    Timeline(PathBuf, ConfigError),
+}
+
+/// Loads resource locations from a JSON map file.
+pub fn load_resource_locations(path: &Path) -> Result<BTreeMap<String, Vec<Int2D>>, MapError> {
+    let input =
+        std::fs::read_to_string(path).map_err(|err| MapError::Io(path.to_path_buf(), err))?;
+    read_resource_locations(&input).map_err(|err| MapError::Parse(path.to_path_buf(), err))
+}
+
+/// Gets resource locations by configured resource from resource locations by name, ignoring
+/// resources that are not configured.
+pub fn index_resource_locations(
+    resource_locations: &BTreeMap<String, Vec<Int2D>>,
+    resources: &[ResourceConfig],
+) -> BTreeMap<Resource, Vec<Int2D>> {
+    Resource::all(resources)
+        .map(|resource| {
+            let locations = resource_locations
+                .get(resource.name(resources))
+                .cloned()
+                .unwrap_or_default();
+            (resource, locations)
+        })
         .collect()
 }
 
-pub fn example_board(dim: (u16, u16)) -> BTreeMap<Resource, Vec<ClammsInt2D>> {
-    let mut map = BTreeMap::new();
-    map.insert(Resource::Food, vec![]);
-    map.insert(Resource::Water, vec![]);
-    for i in 1..7 {
-        for j in 1..7 {
-            let v = map.get_mut(&Resource::Food).unwrap();
-            v.push(ClammsInt2D { x: i, y: j });
-        }
-    }
-    let mut rng = StdRng::seed_from_u64(1);
-    let mut river_width = 0i32;
-    for j in 1..dim.1 {
-        river_width += rng.gen_range(-2..=2);
-        for i in (dim.0 / 2 - 2)..=(dim.0 / 2 + 2) {
-            let v = map.get_mut(&Resource::Water).unwrap();
-            v.push(ClammsInt2D {
-                x: (i as i32 - 2 + river_width),
-                y: j.into(),
-            });
-        }
-    }
-    map
+/// Gets resource locations by resource name from resource locations by configured resource.
+pub fn name_resource_locations(
+    resource_locations: &BTreeMap<Resource, Vec<Int2D>>,
+    resources: &[ResourceConfig],
+) -> BTreeMap<String, Vec<Int2D>> {
+    resource_locations
+        .iter()
+        .map(|(resource, locs)| (resource.name(resources).to_string(), locs.to_owned()))
+        .collect()
+}
+
+/// Symbols for terrain types in board layouts, in order of preference.
+const LAYOUT_SYMBOLS: &str =
+    ".#~^:;%&*+=-0123456789ABCDEGHIJKLMNOPQRSTUVXYZabcdeghijklmnopqrstuvxyz";
+
+/// Chooses an unused symbol for a legend entry of a board layout: the initial of a resource
+/// (upper case for a patch with its own yield), or otherwise the symbol of its terrain type.
+fn layout_symbol(
+    env_item: &EnvItem,
+    entry: &LegendEntry,
+    legend: &BTreeMap<char, LegendEntry>,
+) -> char {
+    let preferred = match (env_item, &entry.resource, entry.resource_yield) {
+        (EnvItem::Resource(_), Some(name), None) => name.to_lowercase(),
+        (EnvItem::Resource(_), Some(name), Some(_)) => name.to_uppercase(),
+        (EnvItem::Resource(_), None, _) => String::new(),
+        (EnvItem::Terrain(index), ..) => LAYOUT_SYMBOLS.chars().skip(*index).collect(),
+    };
+    preferred
+        .chars()
+        .take(1)
+        .chain(LAYOUT_SYMBOLS.chars())
+        .find(|symbol| !legend.contains_key(symbol))
+        .expect("fewer legend entries than symbols")
 }
 
 // TODO: add a fast lookup by location for resources
 pub struct Board {
     pub step: u64,
     pub resource_grid: DenseGrid2D<Patch>,
+    /// Grid of agents, written through `set_agent` to keep the agent registry in step.
     pub agent_grid: DenseGrid2D<Trader>,
+    /// Agents by id and location, as in the agent grid.
+    pub agents: AgentRegistry,
     pub dim: (u16, u16),
-    pub num_agents: u8,
+    /// Number of agents generated at the start of the run, before any births or deaths.
+    pub num_agents: u32,
     pub agent_histories: BTreeMap<u32, History<AgentState, AgentStateItems, InvLevel, Action>>,
     pub resource_locations: BTreeMap<Resource, Vec<Int2D>>,
+    /// Path distances to the nearest resource locations of each type, updated with the resource
+    /// locations.
+    pub resource_distances: BTreeMap<Resource, PathDistances>,
+    /// Path distances to the nearest agent at the start of the current step.
+    pub trader_distances: PathDistances,
     pub rng: StdRng,
     pub model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
     pub loaded_map: bool,
+    /// Map giving the terrain and resource of every patch, if loaded from a grid map file.
+    pub grid_map: Option<GridMap>,
+    /// Locations agents are spawned at, or anywhere on the board if empty.
+    pub spawn_points: Vec<Int2D>,
+    /// Spawn points in each named group.
+    pub spawn_groups: BTreeMap<String, Vec<Int2D>>,
+    /// Patches as initialised, to which the active regimes of the timeline are applied.
+    pub initial_patches: Vec<(Int2D, Patch)>,
+    /// Indices of the regimes of the timeline active at the current step.
+    pub active_regimes: Vec<usize>,
     pub has_trading: bool,
     pub traded: HashMap<u32, Option<u32>>,
-    pub current_traders: Vec<Trader>,
+    /// Ids of the agents in the random order of the current step, in which trades are matched
+    /// and the steps of agents are committed.
+    pub step_order: Vec<u32>,
+    /// Steps taken by agents during the current step, committed at the end of the step.
+    pub pending_steps: BTreeMap<u32, PendingStep>,
+    /// Seed of the random streams of the agents, drawn when the agents are generated.
+    pub agent_seed: u64,
+    /// Number of living agents at the end of each step, including those born in the step.
+    pub population: Vec<u32>,
+    /// Configuration shared by the board, agents and model.
+    pub config: Arc<Config>,
+    /// Log of events during the simulation.
+    pub event_log: EventLog,
+    /// Policy selecting the actions of agents.
+    pub policy: Box<dyn ActionPolicy>,
 }
 
 impl Board {
-    pub fn new(
-        dim: (u16, u16),
-        num_agents: u8,
+    /// Constructs a board with dimensions, number of agents and trading from config.
+    fn from_config(
+        config: Arc<Config>,
         model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
-        has_trading: bool,
+        rng: StdRng,
+        resource_locations: BTreeMap<Resource, Vec<Int2D>>,
+        loaded_map: bool,
     ) -> Board {
+        let dim = (config.world.WIDTH, config.world.HEIGHT);
         Board {
             step: 0,
             agent_grid: DenseGrid2D::new(dim.0.into(), dim.1.into()),
+            agents: AgentRegistry::default(),
             resource_grid: DenseGrid2D::new(dim.0.into(), dim.1.into()),
             dim,
-            num_agents,
+            num_agents: config.world.N_AGENTS,
             agent_histories: BTreeMap::new(),
-            resource_locations: BTreeMap::new(),
-            rng: StdRng::from_entropy(),
+            resource_locations,
+            resource_distances: BTreeMap::new(),
+            trader_distances: PathDistances::from_sources(dim, Topology::Bounded, &[], |_| false),
+            rng,
             model,
-            loaded_map: false,
-            has_trading,
+            loaded_map,
+            grid_map: None,
+            spawn_points: Vec::new(),
+            spawn_groups: BTreeMap::new(),
+            initial_patches: Vec::new(),
+            active_regimes: Vec::new(),
+            has_trading: config.world.HAS_TRADING,
             traded: HashMap::new(),
-            current_traders: Vec::new(),
+            step_order: Vec::new(),
+            pending_steps: BTreeMap::new(),
+            agent_seed: 0,
+            population: Vec::new(),
+            event_log: EventLog::new(&config.log),
+            policy: Box::new(EpsilonGreedy),
+            config,
         }
     }
-    pub fn new_with_seed(
-        dim: (u16, u16),
-        num_agents: u8,
-        seed: u64,
+
+    pub fn new(
+        config: Arc<Config>,
         model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
-        has_trading: bool,
     ) -> Board {
-        Board {
-            step: 0,
-            agent_grid: DenseGrid2D::new(dim.0.into(), dim.1.into()),
-            resource_grid: DenseGrid2D::new(dim.0.into(), dim.1.into()),
-            dim,
-            num_agents,
-            agent_histories: BTreeMap::new(),
-            resource_locations: BTreeMap::new(),
-            rng: StdRng::seed_from_u64(seed),
+        Board::from_config(
+            config,
             model,
-            loaded_map: false,
-            has_trading,
-            traded: HashMap::new(),
-            current_traders: Vec::new(),
-        }
+            StdRng::from_entropy(),
+            BTreeMap::new(),
+            false,
+        )
     }
-    pub fn new_with_seed_resources(
-        dim: (u16, u16),
-        num_agents: u8,
-        seed: u64,
-        map_locations: &str,
+
+    pub fn new_with_seed(
+        config: Arc<Config>,
         model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
-        has_trading: bool,
     ) -> Board {
-        let path =
-            std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join(map_locations);
-        let resource_locations = read_resource_locations(&std::fs::read_to_string(path).unwrap());
+        let rng = StdRng::seed_from_u64(config.world.RANDOM_SEED);
+        Board::from_config(config, model, rng, BTreeMap::new(), false)
+    }
 
-        Board {
-            step: 0,
-            agent_grid: DenseGrid2D::new(dim.0.into(), dim.0.into()),
-            resource_grid: DenseGrid2D::new(dim.0.into(), dim.1.into()),
-            dim,
-            num_agents,
-            agent_histories: BTreeMap::new(),
+    pub fn new_with_seed_resources(
+        config: Arc<Config>,
+        map_locations: &Path,
+        model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+    ) -> Result<Board, MapError> {
+        let resource_locations = load_resource_locations(map_locations)?;
+        Ok(Board::new_with_seed_resource_locations(
+            config,
             resource_locations,
-            rng: StdRng::seed_from_u64(seed),
-            loaded_map: true,
             model,
-            has_trading,
-            traded: HashMap::new(),
-            current_traders: Vec::new(),
+        ))
+    }
+
+    /// Constructs a board with the given locations of resources by name, e.g. as embedded in
+    /// provenance.
+    pub fn new_with_seed_resource_locations(
+        config: Arc<Config>,
+        resource_locations: BTreeMap<String, Vec<Int2D>>,
+        model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+    ) -> Board {
+        let rng = StdRng::seed_from_u64(config.world.RANDOM_SEED);
+        let resource_locations = index_resource_locations(&resource_locations, &config.resources);
+        Board::from_config(config, model, rng, resource_locations, true)
+    }
+
+    /// Constructs a board with the terrain, resources and spawn points of the given grid map.
+    pub fn new_with_seed_grid_map(
+        config: Arc<Config>,
+        grid_map: GridMap,
+        model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+    ) -> Board {
+        let rng = StdRng::seed_from_u64(config.world.RANDOM_SEED);
+        let resource_locations =
+            index_resource_locations(&grid_map.resource_locations(), &config.resources);
+        let mut board = Board::from_config(config, model, rng, resource_locations, true);
+        board.spawn_points = grid_map.spawn_points();
+        board.spawn_groups = grid_map.spawn_groups();
+        board.grid_map = Some(grid_map);
+        board
+    }
+
+    /// Constructs a board from config, loading the map from the configured grid map or resource
+    /// locations file if given, or otherwise generating it with the configured map generator.
+    /// The regimes of any timeline file are merged into the timeline, as when the provenance of
+    /// a run is recorded.
+    pub fn from_config_with_seed(
+        config: Arc<Config>,
+        model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+    ) -> Result<Board, MapError> {
+        let config = match &config.world.TIMELINE_FILE {
+            Some(file) => {
+                let file = PathBuf::from(file);
+                let mut config = Config::clone(&config);
+                config
+                    .resolve_timeline()
+                    .map_err(|err| MapError::Timeline(file, err))?;
+                Arc::new(config)
+            }
+            None => config,
+        };
+        if let Some(file_name) = config.world.MAP_FILE.clone() {
+            let grid_map = GridMap::load(Path::new(&file_name))?;
+            Ok(Board::new_with_seed_grid_map(config, grid_map, model))
+        } else if let Some(file_name) = config.world.RESOURCE_LOCATIONS_FILE.clone() {
+            Board::new_with_seed_resources(config, Path::new(&file_name), model)
+        } else if let Some(resource_locations) = generate_resource_locations(&config) {
+            Ok(Board::new_with_seed_resource_locations(
+                config,
+                resource_locations,
+                model,
+            ))
+        } else {
+            Ok(Board::new_with_seed(config, model))
         }
     }
 
-    /// Randomly inits agents.
-    fn generate_agents_random(&mut self, schedule: &mut Schedule) {
-        for n in 0..self.num_agents {
-            let x: u16 = self.rng.gen_range(1..self.dim.0);
-            let y: u16 = self.rng.gen_range(1..self.dim.1);
+    /// Gets the patch at a location, if on the board.
+    pub fn patch(&self, pos: &Int2D) -> Option<Patch> {
+        self.resource_grid
+            .get_objects(pos)
+            .and_then(|patches| patches.first().copied())
+    }
 
-            let id: u32 = n.into();
+    /// Gets the patch at a location, including any update to its stock earlier in the step.
+    pub fn current_patch(&self, pos: &Int2D) -> Option<Patch> {
+        self.resource_grid
+            .get_objects_unbuffered(pos)
+            .or_else(|| self.resource_grid.get_objects(pos))
+            .and_then(|patches| patches.first().copied())
+    }
 
-            let agent = Trader::new(Forager::new(
-                id,
-                Int2D {
-                    x: x.into(),
-                    y: y.into(),
-                },
-                core_config().agent.INIT_FOOD,
-                core_config().agent.INIT_WATER,
-            ));
+    /// Gets the location one step in a direction from a location under the topology of the board,
+    /// or `None` if beyond the edge of a bounded board.
+    pub fn neighbour(&self, pos: &Int2D, dir: Direction) -> Option<Int2D> {
+        self.config.world.TOPOLOGY.neighbour(pos, dir, self.dim)
+    }
+
+    /// Whether agents can move onto a location: on the board and not impassable terrain.
+    pub fn can_enter(&self, pos: &Int2D) -> bool {
+        (0..i32::from(self.dim.0)).contains(&pos.x)
+            && (0..i32::from(self.dim.1)).contains(&pos.y)
+            && self
+                .patch(pos)
+                .is_none_or(|patch| patch.env_item.passable(&self.config.terrain))
+    }
+
+    /// Gets the numbers of steps along shortest paths from every location to the nearest of the
+    /// given locations.
+    pub fn path_distances(&self, sources: &[Int2D]) -> PathDistances {
+        PathDistances::from_sources(self.dim, self.config.world.TOPOLOGY, sources, |pos| {
+            self.can_enter(pos)
+        })
+    }
+
+    /// Updates the resource locations to the patches with stock left to harvest, and the path
+    /// distances to them, after a change to the resource grid.
+    fn update_resource_locations(&mut self) {
+        self.resource_locations = self.locate_resources();
+        self.update_resource_distances();
+    }
+
+    /// Updates the path distances to the resource locations.
+    fn update_resource_distances(&mut self) {
+        self.resource_distances = Resource::all(&self.config.resources)
+            .map(|resource| {
+                let locations = self
+                    .resource_locations
+                    .get(&resource)
+                    .map_or(&[][..], Vec::as_slice);
+                (resource, self.path_distances(locations))
+            })
+            .collect();
+    }
+
+    /// Gets the realised layout of the board as a grid map, with the terrain or resource of every
+    /// patch and the spawn points.
+    pub fn layout(&self) -> GridMap {
+        let mut legend: BTreeMap<char, LegendEntry> = BTreeMap::new();
+        let grid = (0..i32::from(self.dim.1))
+            .rev()
+            .map(|y| {
+                (0..i32::from(self.dim.0))
+                    .map(|x| {
+                        let pos = Int2D { x, y };
+                        let patch = self.patch(&pos).expect("patches set on every location");
+                        let entry = LegendEntry {
+                            terrain: patch
+                                .env_item
+                                .terrain(&self.config.terrain)
+                                .map(|t| t.NAME.to_owned()),
+                            resource: match patch.env_item {
+                                EnvItem::Resource(resource) => {
+                                    Some(resource.name(&self.config.resources).to_string())
+                                }
+                                EnvItem::Terrain(_) => None,
+                            },
+                            resource_yield: patch.resource_yield,
+                            spawn: self.spawn_points.contains(&pos),
+                            group: self
+                                .spawn_groups
+                                .iter()
+                                .find(|(_, locs)| locs.contains(&pos))
+                                .map(|(group, _)| group.to_owned()),
+                        };
+                        match legend.iter().find(|(_, e)| **e == entry) {
+                            Some((symbol, _)) => *symbol,
+                            None => {
+                                let symbol = layout_symbol(&patch.env_item, &entry, &legend);
+                                legend.insert(symbol, entry);
+                                symbol
+                            }
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        GridMap { legend, grid }
+    }
+
+    /// Inits agents in their groups, placed and given initial inventories as configured, with any
+    /// agents beyond the groups placed by the world config.
+    fn generate_agents(&mut self, schedule: &mut Schedule) {
+        let open: Vec<Int2D> = (0..i32::from(self.dim.0))
+            .flat_map(|x| (0..i32::from(self.dim.1)).map(move |y| Int2D { x, y }))
+            .filter(|pos| self.can_enter(pos))
+            .collect();
+        let sites = Sites {
+            dim: self.dim,
+            topology: self.config.world.TOPOLOGY,
+            open: &open,
+            spawn_points: &self.spawn_points,
+            spawn_groups: &self.spawn_groups,
+            resource_distances: &self.resource_distances,
+            resources: &self.config.resources,
+        };
+        let config = Arc::clone(&self.config);
+        let resources = &config.resources;
+        let ungrouped = AgentGroupConfig {
+            N_AGENTS: self.num_agents,
+            INIT: BTreeMap::new(),
+            PLACEMENT: self.config.world.PLACEMENT.clone(),
+        };
+        let mut agents: Vec<(Int2D, Vec<i32>)> = Vec::new();
+        for group in self.config.groups.iter().chain([&ungrouped]) {
+            let n_agents = (group.N_AGENTS as usize).min(self.num_agents as usize - agents.len());
+            let init: Vec<i32> = resources
+                .iter()
+                .map(|resource| *group.INIT.get(&resource.NAME).unwrap_or(&resource.INIT))
+                .collect();
+            for pos in place_agents(&group.PLACEMENT, n_agents, &sites, &mut self.rng) {
+                agents.push((pos, init.clone()));
+            }
+        }
+
+        for (id, (pos, init)) in (0..).zip(agents) {
+            let agent = Trader::new(Forager::new(id, pos, &init, resources));
 
             // Init empty history
             self.agent_histories.insert(id, History::new());
@@ -224,16 +532,152 @@ impl Board {
             schedule.schedule_repeating(Box::new(agent), 0., 0);
 
             // Set agent location
-            self.agent_grid
-                .set_object_location(agent, &agent.forager.pos)
+            self.set_agent(agent);
         }
+
+        // Seed the random streams of the agents, after placing them so that placements do not
+        // depend on the streams
+        self.agent_seed = self.rng.gen();
+    }
+
+    /// Gets the random stream of an agent for the current step, seeded from the agent seed, the
+    /// step and the id of the agent. Agents draw only from their own streams during their
+    /// steps, so that their draws do not depend on the order or thread they step in.
+    pub fn agent_rng(&self, id: u32) -> StdRng {
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&self.agent_seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&self.step.to_le_bytes());
+        seed[16..20].copy_from_slice(&id.to_le_bytes());
+        StdRng::from_seed(seed)
+    }
+
+    /// Commits the steps buffered by agents, in the random order of the step: logging their
+    /// movements, harvesting the resources of the patches they are on, writing them to the
+    /// agent grid and registry and pushing (s_n, a_n, r_n+1) to their histories. Agents dying
+    /// under the mortality rule are not written, so leave the board at the end of the step, and
+    /// their last transitions are terminal. Offspring of agents reproducing under the
+    /// reproduction rule are written and scheduled to step from the next step.
+    fn commit_steps(&mut self, schedule: &mut Schedule) {
+        let mut pending_steps = std::mem::take(&mut self.pending_steps);
+        let mut population = 0;
+        for id in self.step_order.clone() {
+            let Some(PendingStep {
+                mut forager,
+                agent_state,
+                action,
+                from,
+            }) = pending_steps.remove(&id)
+            else {
+                continue;
+            };
+            self.event_log.log(
+                self.step,
+                EventKind::Movement {
+                    agent: id,
+                    action: action.clone(),
+                    from: from.into(),
+                    to: forager.pos.into(),
+                },
+            );
+            forager.harvest(self);
+            let dies = forager.starve(&self.config.agent.MORTALITY);
+            if dies {
+                self.event_log.log(
+                    self.step,
+                    EventKind::Death {
+                        agent: id,
+                        inventory: Resource::all(&self.config.resources)
+                            .map(|r| {
+                                (
+                                    r.name(&self.config.resources).to_string(),
+                                    forager.count(&r),
+                                )
+                            })
+                            .collect(),
+                    },
+                );
+            } else {
+                let child_id = self
+                    .agent_histories
+                    .last_key_value()
+                    .map_or(0, |(id, _)| id + 1);
+                let child = forager.reproduce(
+                    child_id,
+                    &self.config.agent.REPRODUCTION,
+                    &self.config.resources,
+                );
+                self.set_agent(Trader::new(forager));
+                population += 1;
+                if let Some(mut child) = child {
+                    child.pos = self.birthplace(&forager.pos);
+                    self.spawn(child, id, schedule);
+                    population += 1;
+                }
+            }
+            self.agent_histories
+                .get_mut(&id)
+                .expect("HashMap initialised for all agents")
+                .push(
+                    SAR::new(
+                        agent_state,
+                        action,
+                        Reward::from_inv_count_linear(forager.inventory()),
+                    )
+                    .with_terminal(dies),
+                );
+        }
+        self.population.push(population);
+    }
+
+    /// Chooses at random a location next to a parent that agents can enter for its offspring, or
+    /// the location of the parent if there is none.
+    fn birthplace(&mut self, pos: &Int2D) -> Int2D {
+        let candidates: Vec<Int2D> = DIRECTIONS
+            .into_iter()
+            .filter_map(|dir| self.neighbour(pos, dir))
+            .filter(|pos| self.can_enter(pos))
+            .collect();
+        candidates.choose(&mut self.rng).copied().unwrap_or(*pos)
+    }
+
+    /// Adds an offspring to the board, with an empty history starting at the next step and the
+    /// policy of its parent, and schedules it to step from the next step.
+    fn spawn(&mut self, child: Forager, parent: u32, schedule: &mut Schedule) {
+        let id = child.id();
+        let noise = match self.config.agent.REPRODUCTION {
+            ReproductionConfig::Surplus { NOISE, .. } => NOISE,
+            ReproductionConfig::Off => 0.0,
+        };
+        self.agent_histories
+            .insert(id, History::starting_at(self.step as usize + 1));
+        self.model.inherit(parent, id, noise, &mut self.rng);
+        let trader = Trader::new(child);
+        self.set_agent(trader);
+        schedule.schedule_repeating(Box::new(trader), schedule.time + 1.0, 0);
+        self.event_log
+            .log(self.step, EventKind::Birth { agent: id, parent });
+    }
+
+    /// Writes an agent to the agent grid and registry at its location, readable after the next
+    /// update of the agents.
+    pub fn set_agent(&mut self, trader: Trader) {
+        self.agent_grid
+            .set_object_location(trader, &trader.get_position());
+        self.agents.set(trader);
+    }
+
+    /// Makes the agents written during the step readable from the agent grid and registry.
+    pub fn update_agents(&mut self) {
+        self.agent_grid.lazy_update();
+        self.agents.lazy_update();
     }
 
     /// Randomly sets resource locations from config.
     fn set_resources_random(&mut self) {
-        Resource::iter().for_each(|resource| {
+        Resource::all(&self.config.resources).for_each(|resource| {
             self.resource_locations.insert(resource, Vec::new());
         });
+        let distribution = EnvItemDistribution::from(self.config.as_ref());
         let mut id = 0;
         for i in 0..self.dim.0 {
             for j in 0..self.dim.1 {
@@ -241,7 +685,7 @@ impl Board {
                     x: i.into(),
                     y: j.into(),
                 };
-                let item = self.rng.gen();
+                let item = self.rng.sample(&distribution);
                 let patch = Patch::new(id, item);
                 self.resource_grid.set_object_location(patch, &pos);
                 if let EnvItem::Resource(resource) = patch.env_item {
@@ -263,6 +707,7 @@ impl Board {
                 resource_lookup.insert(*loc, res);
             }
         });
+        let terrain = TerrainDistribution::from(self.config.terrain.as_slice());
         let mut id = 0;
         for i in 0..self.dim.0 {
             for j in 0..self.dim.1 {
@@ -273,10 +718,8 @@ impl Board {
 
                 let item = if let Some(resource) = resource_lookup.get(&pos) {
                     EnvItem::Resource(*resource)
-                } else if self.rng.gen::<f32>() < core_config().world.LAND_PROP {
-                    EnvItem::Land
                 } else {
-                    EnvItem::Bush
+                    self.rng.sample(&terrain)
                 };
 
                 let patch = Patch::new(id, item);
@@ -285,14 +728,212 @@ impl Board {
             }
         }
     }
+    /// Sets terrain and resources based on loaded grid map.
+    fn set_resources_from_grid_map(&mut self, grid_map: &GridMap) {
+        // Ids as assigned by column then row when setting resources from config or map
+        let height = i32::from(self.dim.1);
+        for (pos, entry) in grid_map.patches() {
+            let env_item = match (&entry.resource, &entry.terrain) {
+                (Some(name), _) => EnvItem::Resource(
+                    Resource::named(&self.config.resources, name)
+                        .expect("resources in the grid map are validated"),
+                ),
+                (None, Some(name)) => EnvItem::Terrain(
+                    self.config
+                        .terrain
+                        .iter()
+                        .position(|t| t.NAME == *name)
+                        .expect("terrain types in the grid map are validated"),
+                ),
+                (None, None) => panic!("legend entries in the grid map are validated"),
+            };
+            let patch = Patch {
+                id: (pos.x * height + pos.y) as u32,
+                env_item,
+                resource_yield: entry.resource_yield,
+                stock: None,
+            };
+            self.resource_grid.set_object_location(patch, &pos);
+        }
+    }
+
     fn init_resources(&mut self) {
-        if self.loaded_map {
+        if let Some(grid_map) = self.grid_map.take() {
+            self.set_resources_from_grid_map(&grid_map);
+            self.grid_map = Some(grid_map);
+        } else if self.loaded_map {
             self.set_resources_from_map();
         } else {
             self.set_resources_random();
         }
+        self.init_stocks();
         // Call lazy_update on the resource grid
         self.resource_grid.lazy_update();
+        self.update_resource_distances();
+        // Record the initial patches for the timeline to change
+        self.initial_patches.clear();
+        if !self.config.timeline.is_empty() {
+            for i in 0..self.dim.0 {
+                for j in 0..self.dim.1 {
+                    let pos = Int2D {
+                        x: i.into(),
+                        y: j.into(),
+                    };
+                    if let Some(patch) = self.patch(&pos) {
+                        self.initial_patches.push((pos, patch));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the initial stocks of resource patches, after placing the resources so that the
+    /// layout does not depend on the regrowth model.
+    fn init_stocks(&mut self) {
+        if self.config.world.REGROWTH == RegrowthConfig::Unlimited {
+            return;
+        }
+        for i in 0..self.dim.0 {
+            for j in 0..self.dim.1 {
+                let pos = Int2D {
+                    x: i.into(),
+                    y: j.into(),
+                };
+                if let Some(mut patch) = self.current_patch(&pos) {
+                    if let EnvItem::Resource(_) = patch.env_item {
+                        patch.stock = Stock::initial(&self.config.world.REGROWTH, &mut self.rng);
+                        self.resource_grid.set_object_location(patch, &pos);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Regrows the stocks of resource patches at the end of a step, and updates the resource
+    /// locations to those with stock left to harvest.
+    ///
+    /// Patches harvested during the step are written to the "write" resource grid so that later
+    /// agents in the step see the depleted stock (see `current_patch()`). The lazy update clears
+    /// the "write" grid on swapping, so every patch is written before it.
+    fn update_resources(&mut self) {
+        if self.config.world.REGROWTH == RegrowthConfig::Unlimited {
+            return;
+        }
+        for i in 0..self.dim.0 {
+            for j in 0..self.dim.1 {
+                let pos = Int2D {
+                    x: i.into(),
+                    y: j.into(),
+                };
+                let Some(mut patch) = self.current_patch(&pos) else {
+                    continue;
+                };
+                if let Some(stock) = patch.stock.as_mut() {
+                    stock.regrow(&self.config.world.REGROWTH);
+                }
+                self.resource_grid.set_object_location(patch, &pos);
+            }
+        }
+        self.resource_grid.lazy_update();
+        self.update_resource_locations();
+    }
+
+    /// Gets the locations of resource patches with stock left to harvest, by column then row.
+    fn locate_resources(&self) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut resource_locations: BTreeMap<Resource, Vec<Int2D>> =
+            Resource::all(&self.config.resources)
+                .map(|resource| (resource, Vec::new()))
+                .collect();
+        for i in 0..self.dim.0 {
+            for j in 0..self.dim.1 {
+                let pos = Int2D {
+                    x: i.into(),
+                    y: j.into(),
+                };
+                if let Some(Patch {
+                    env_item: EnvItem::Resource(resource),
+                    stock,
+                    ..
+                }) = self.patch(&pos)
+                {
+                    if stock.is_none_or(|stock| !stock.is_depleted()) {
+                        resource_locations
+                            .get_mut(&resource)
+                            .expect("initialised for all resources")
+                            .push(pos);
+                    }
+                }
+            }
+        }
+        resource_locations
+    }
+
+    /// Applies the regimes of the timeline active at the current step, rewriting the resource
+    /// grid and resource locations when the active regimes change. Patches keep their stocks
+    /// when moved, while resources reappearing after a regime ends have their initial stocks.
+    fn apply_timeline(&mut self) {
+        let active: Vec<usize> = (0..self.config.timeline.len())
+            .filter(|&i| self.config.timeline[i].is_active(self.step))
+            .collect();
+        if active == self.active_regimes {
+            return;
+        }
+        for (i, regime) in self.config.timeline.iter().enumerate() {
+            if active.contains(&i) != self.active_regimes.contains(&i) {
+                self.event_log.log(
+                    self.step,
+                    EventKind::Regime {
+                        name: regime.NAME.to_owned(),
+                        active: active.contains(&i),
+                    },
+                );
+            }
+        }
+        self.active_regimes = active;
+
+        let stocks: HashMap<u32, Stock> = self
+            .initial_patches
+            .iter()
+            .filter_map(|(pos, _)| self.current_patch(pos))
+            .filter_map(|patch| Some((patch.id, patch.stock?)))
+            .collect();
+        let mut patches = self.initial_patches.clone();
+        for &i in &self.active_regimes {
+            apply_change(
+                &self.config.timeline[i].CHANGE,
+                &mut patches,
+                self.dim,
+                &self.config.resources,
+                &self.config.terrain,
+            );
+        }
+        for (pos, mut patch) in patches {
+            if let Some(stock) = stocks.get(&patch.id) {
+                patch.stock = Some(*stock);
+            }
+            self.resource_grid.set_object_location(patch, &pos);
+        }
+        self.resource_grid.lazy_update();
+        self.update_resource_locations();
+    }
+
+    /// Gets the amount of a resource acquired per step on a patch: the yield of the patch or the
+    /// acquire rate of the resource, scaled by the multipliers of any active regimes.
+    pub fn acquire_rate(&self, resource: &Resource, patch: &Patch) -> i32 {
+        let config = resource.config(&self.config.resources);
+        let rate = patch.resource_yield.unwrap_or(config.ACQUIRE_RATE);
+        let multiplier: f32 = self
+            .active_regimes
+            .iter()
+            .filter_map(|&i| match self.config.timeline[i].CHANGE {
+                RegimeChange::AcquireRate {
+                    ref RESOURCE,
+                    MULTIPLIER,
+                } if *RESOURCE == config.NAME => Some(MULTIPLIER),
+                _ => None,
+            })
+            .product();
+        (rate as f32 * multiplier).round() as i32
     }
 }
 
@@ -300,39 +941,73 @@ impl State for Board {
     fn init(&mut self, schedule: &mut Schedule) {
         // Init step
         self.step = 0;
-        // Generate agents
-        self.generate_agents_random(schedule);
+        self.active_regimes.clear();
         // Generate, set and lazy update resource grid
         self.init_resources();
+        // Generate agents, after the resources so that they are placed on patches they can enter
+        self.generate_agents(schedule);
     }
 
     fn before_step(&mut self, _: &mut krabmaga::engine::schedule::Schedule) {
-        // Get current agents in random order from grid to avoid repeat lookups
-        self.current_traders = self.get_agents();
-        self.current_traders.shuffle(&mut self.rng);
+        // Apply any change of environmental regime
+        self.apply_timeline();
+        let trader_locations: Vec<Int2D> = self
+            .agents
+            .agents()
+            .map(|trader| trader.get_position())
+            .collect();
+        self.trader_distances = self.path_distances(&trader_locations);
+        // Order agents at random for matching trades and committing steps
+        self.step_order = self.agents.agents().map(|trader| trader.id()).collect();
+        self.step_order.shuffle(&mut self.rng);
+        // Match trades, applied by the agents during their steps
+        resolve_trades(self);
     }
 
-    fn after_step(&mut self, _schedule: &mut krabmaga::engine::schedule::Schedule) {
-        // TODO: add random ordering using board.rng to events in scheduler so that agents are picked
-        // in a different random order each time during step.
+    fn after_step(&mut self, schedule: &mut krabmaga::engine::schedule::Schedule) {
+        // Commit the steps of the agents in the random order of the step
+        self.commit_steps(schedule);
 
         // Updates as state
         let step: i32 = i32::try_from(self.step).unwrap();
 
         // Update board model
         let board = self.as_any_mut().downcast_mut::<Board>().unwrap();
-        board.model.step(step, &board.agent_histories);
+        board.model.step(
+            step,
+            &board.agent_histories,
+            &board.config,
+            &mut board.event_log,
+        );
 
         // TODO: add better dashboard statistics for agents/optimization
         // Simple report of mean reward over last 100
-        let traj = &board.agent_histories.get(&0).unwrap().trajectory;
-        let recent_len = 100;
-        let recent_traj = &traj[(traj.len().max(recent_len) - recent_len)..traj.len()];
-        if core_config().simulation.VERBOSITY > 0 {
-            println!(
-                "Mean reward (over last 100 steps) for agent 0: {} at step: {step}",
-                recent_traj.iter().map(|sar| sar.reward.val).sum::<i32>()
-                    / i32::try_from(recent_traj.len()).unwrap()
+        if board
+            .event_log
+            .enabled(Category::StepSummary, LogLevel::Info)
+        {
+            let traj = &board.agent_histories.get(&0).unwrap().trajectory;
+            let recent_len = 100;
+            let recent_traj = &traj[(traj.len().max(recent_len) - recent_len)..traj.len()];
+            // Rewards of the agents stepping in this step, including any that died
+            let rewards = board
+                .step_order
+                .iter()
+                .filter_map(|id| board.agent_histories.get(id))
+                .filter_map(|hist| hist.trajectory.last())
+                .map(|sar| sar.reward.val as f32)
+                .collect_vec();
+            board.event_log.log(
+                board.step,
+                EventKind::StepSummary {
+                    trades: board.traded.values().filter(|cp| cp.is_some()).count() / 2,
+                    mean_reward: rewards.iter().sum::<f32>() / rewards.len().max(1) as f32,
+                    agent_0_recent_mean_reward: recent_traj
+                        .iter()
+                        .map(|sar| sar.reward.val)
+                        .sum::<i32>()
+                        / i32::try_from(recent_traj.len()).unwrap(),
+                },
             );
         }
     }
@@ -354,11 +1029,13 @@ impl State for Board {
     }
 
     fn update(&mut self, step: u64) {
-        // The agent_grid updated at end of timestep so set_object_location() is switched to "read" from "write"
-        self.agent_grid.lazy_update();
-        // Clear traded lookup and current traders
+        // The agents updated at end of timestep so set_agent() is switched to "read" from "write"
+        self.update_agents();
+        // Regrow resources harvested during the step
+        self.update_resources();
+        // Clear traded lookup and step order
         self.traded.clear();
-        self.current_traders.clear();
+        self.step_order.clear();
         self.step = step;
     }
 
@@ -366,51 +1043,47 @@ impl State for Board {
         self.step = 0;
         self.resource_grid = DenseGrid2D::new(self.dim.0.into(), self.dim.1.into());
         self.agent_grid = DenseGrid2D::new(self.dim.0.into(), self.dim.1.into());
+        self.agents.clear();
     }
 }
 
-// Additional API for accessing board.
-cfg_if! {
-    if #[cfg(any(feature = "parallel", feature = "visualization", feature = "visualization_wasm"))]{
-        impl AgentAPI<Trader> for Board {
-            fn get_agent_by_id(&self, id: &u32) -> Trader {
-                self.agent_grid
-                    .get(&Trader::dummy(*id))
-                    .expect("get agent by id")
-            }
-            fn get_agents(&self) -> Vec<Trader> {
-                self.agent_grid.obj2loc.keys().iter().map(|&k|k.to_owned()).collect()
-            }
-        }
-    } else {
-        impl AgentAPI<Trader> for Board {
-            fn get_agent_by_id(&self, id: &u32) -> Trader {
-                let loc = &self.agent_grid.get_location(&Trader::dummy(*id)).unwrap();
-                let traders: Vec<Trader> = self.agent_grid.get_objects(loc).unwrap();
-                *traders.into_iter().filter(|trader| trader.id() == *id).collect_vec().first().unwrap()
-            }
-            fn get_agents(&self) -> Vec<Trader> {
-                let mut traders: Vec<Trader> = Vec::new();
-                for i in  0..self.dim.0 {
-                    for j in 0..self.dim.1 {
-                        // Gets objects from "read" state (start of time step)
-                        if let Some(mut traders_at_loc) = self.agent_grid.get_objects(&Int2D {x: i.into(), y: j.into() }) {
-                                traders.append(&mut traders_at_loc);
-                            }
-                        }
-                    }
+/// Constructs a schedule for the agents of a board, stepping them on as many threads as are
+/// available with the `parallel` feature. The parallel schedule exits the process if stepped
+/// with no agents, so it must not be stepped once every agent has died (see
+/// `Simulation::is_extinct`).
+#[cfg(feature = "parallel")]
+pub fn new_schedule() -> Schedule {
+    Schedule::with_threads(std::thread::available_parallelism().map_or(1, usize::from))
+}
 
-                traders
-            }
-        }
+/// Constructs a schedule for the agents of a board, stepping them on as many threads as are
+/// available with the `parallel` feature.
+#[cfg(not(feature = "parallel"))]
+pub fn new_schedule() -> Schedule {
+    Schedule::new()
+}
+
+// Additional API for accessing board.
+impl AgentAPI<Trader> for Board {
+    fn get_agent_by_id(&self, id: &u32) -> Trader {
+        *self.agents.get(id).expect("get agent by id")
+    }
+    fn get_agents(&self) -> Vec<Trader> {
+        // Gets agents from "read" state (start of time step)
+        self.agents.agents().copied().collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use krabmaga::engine::schedule::Schedule;
-
-    use crate::model::{init, inventory::Inventory};
+    use crate::config::{
+        load_timeline, read_timeline, MortalityConfig, PlacementConfig, TerrainConfig,
+    };
+    use crate::model::routing::Router;
+    use crate::model::test_config;
+    use krabmaga::engine::agent::Agent;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
 
     use super::*;
 
@@ -421,15 +1094,13 @@ mod tests {
     impl TestInit for Board {
         fn init_with_test_agents(&mut self, schedule: &mut krabmaga::engine::schedule::Schedule) {
             self.step = 0;
-            let agent1 = Trader::new(Forager::new(0, Int2D { x: 2, y: 2 }, 0, 100));
-            let agent2 = Trader::new(Forager::new(1, Int2D { x: 2, y: 1 }, 100, 0));
-            let agent3 = Trader::new(Forager::new(2, Int2D { x: 4, y: 5 }, 0, 0));
-            self.agent_grid
-                .set_object_location(agent1, &agent1.forager.pos);
-            self.agent_grid
-                .set_object_location(agent2, &agent2.forager.pos);
-            self.agent_grid
-                .set_object_location(agent3, &agent3.forager.pos);
+            let resources = &self.config.resources;
+            let agent1 = Trader::new(Forager::new(0, Int2D { x: 2, y: 2 }, &[0, 100], resources));
+            let agent2 = Trader::new(Forager::new(1, Int2D { x: 2, y: 1 }, &[100, 0], resources));
+            let agent3 = Trader::new(Forager::new(2, Int2D { x: 4, y: 5 }, &[0, 0], resources));
+            self.set_agent(agent1);
+            self.set_agent(agent2);
+            self.set_agent(agent3);
             self.agent_histories.insert(0, History::new());
             self.agent_histories.insert(1, History::new());
             self.agent_histories.insert(2, History::new());
@@ -458,20 +1129,516 @@ mod tests {
       }"#;
     #[test]
     fn test_read_resources() {
-        let _ = read_resource_locations(TEST_LOCATIONS);
+        let _ = read_resource_locations(TEST_LOCATIONS).unwrap();
+        assert!(matches!(
+            load_resource_locations(Path::new("does_not_exist.json")),
+            Err(MapError::Io(..))
+        ));
     }
-    #[test]
-    fn test_example_board() {
-        println!(
-            "{}",
-            serde_json::to_string(&example_board((42, 42))).unwrap()
+
+    /// Constructs a board with a new model from the given config, with the terrain, resources
+    /// and spawn points of a grid map or the given resource locations by name in place of the
+    /// map of the config.
+    fn board_from_config(
+        config: Config,
+        grid_map: Option<GridMap>,
+        resource_locations: Option<BTreeMap<String, Vec<Int2D>>>,
+    ) -> Board {
+        let model = SARSAModel::new(
+            (0..config.world.N_AGENTS).collect(),
+            AgentStateItems::all(&config.resources),
+            InvLevel::iter().collect::<Vec<InvLevel>>(),
+            Action::all(&config.resources),
+            config.rl.MULTI_POLICY,
+            config.rl.INIT_Q_VALUES,
         );
+        let config = Arc::new(config);
+        match (grid_map, resource_locations) {
+            (Some(grid_map), _) => Board::new_with_seed_grid_map(config, grid_map, model),
+            (None, Some(resource_locations)) => {
+                Board::new_with_seed_resource_locations(config, resource_locations, model)
+            }
+            (None, None) => Board::from_config_with_seed(config, model).unwrap(),
+        }
     }
 
     #[test]
     fn test_scheduler_event_ordering() {
-        // Add test to confirm/randomize order of events in PriorityQueue
-        todo!()
+        // Confirm every agent event in the PriorityQueue is executed exactly once per step
+        let mut board = board_from_config(test_config(), None, None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        for step in 1..=3 {
+            schedule.step(&mut board);
+            assert_eq!(board.agent_histories.len(), board.num_agents as usize);
+            for history in board.agent_histories.values() {
+                assert_eq!(history.len(), step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_agent_registry() {
+        // The registry holds the agents of the grid, at their locations in the grid
+        let mut board = board_from_config(test_config(), None, None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        for _ in 0..3 {
+            schedule.step(&mut board);
+            assert_eq!(board.agents.len(), board.num_agents as usize);
+            for trader in board.get_agents() {
+                let pos = trader.get_position();
+                assert!(board
+                    .agent_grid
+                    .get_objects(&pos)
+                    .unwrap()
+                    .contains(&trader));
+                assert!(board.agents.at(&pos).contains(&trader.id()));
+                assert_eq!(board.get_agent_by_id(&trader.id()).id(), trader.id());
+            }
+        }
+    }
+
+    /// Gets the positions and food inventories of agents on a board.
+    fn get_positions_and_food(board: &Board) -> HashMap<u32, (Int2D, i32)> {
+        board
+            .get_agents()
+            .iter()
+            .map(|trader| {
+                (
+                    trader.id(),
+                    (trader.forager().pos, trader.forager().count(&Resource(0))),
+                )
+            })
+            .collect()
+    }
+
+    /// Steps a board as the schedule does, stepping its agents in the given order of ids, and
+    /// returns the number of trades in the step.
+    fn step_in_order(board: &mut Board, order: &[u32]) -> usize {
+        let mut schedule = new_schedule();
+        board.before_step(&mut schedule);
+        let mut agents: BTreeMap<u32, Trader> = board
+            .get_agents()
+            .into_iter()
+            .map(|trader| (trader.id(), trader))
+            .collect();
+        for id in order {
+            agents.get_mut(id).unwrap().step(board);
+        }
+        board.after_step(&mut schedule);
+        let trades = board.traded.values().filter(|cp| cp.is_some()).count() / 2;
+        board.update(board.step + 1);
+        trades
+    }
+
+    #[test]
+    fn test_step_order() {
+        // Agents trading and harvesting finite stocks reach the same state whichever order they
+        // step in, as when stepped in parallel
+        let mut config = test_config();
+        config.world.N_AGENTS = 12;
+        config.world.REGROWTH = RegrowthConfig::Constant {
+            CAPACITY: 15,
+            RATE: 0.25,
+        };
+        for resource in config.resources.iter_mut() {
+            resource.ABUNDANCE = 0.2;
+        }
+        let ids: Vec<u32> = (0..config.world.N_AGENTS).collect();
+        let reversed: Vec<u32> = ids.iter().rev().copied().collect();
+        let mut boards = [
+            board_from_config(config.clone(), None, None),
+            board_from_config(config, None, None),
+        ];
+        for board in boards.iter_mut() {
+            board.init(&mut new_schedule());
+            board.update(0);
+        }
+        let mut trades = 0;
+        for _ in 0..10 {
+            trades += step_in_order(&mut boards[0], &ids);
+            step_in_order(&mut boards[1], &reversed);
+            assert_eq!(
+                get_traders_display(&boards[0]),
+                get_traders_display(&boards[1])
+            );
+        }
+        assert!(trades > 0);
+        assert_eq!(boards[0].agent_histories, boards[1].agent_histories);
+    }
+
+    #[test]
+    fn test_schedule() {
+        // Agents stepped by the schedule, on several threads with the `parallel` feature, reach
+        // the same state for a fixed seed as when stepped one at a time in the order of ids
+        let mut config = test_config();
+        config.world.N_AGENTS = 12;
+        for resource in config.resources.iter_mut() {
+            resource.ABUNDANCE = 0.2;
+        }
+        let ids: Vec<u32> = (0..config.world.N_AGENTS).collect();
+        let mut scheduled = board_from_config(config.clone(), None, None);
+        let mut sequential = board_from_config(config, None, None);
+        #[cfg(feature = "parallel")]
+        let mut schedule = Schedule::with_threads(4);
+        #[cfg(not(feature = "parallel"))]
+        let mut schedule = new_schedule();
+        scheduled.init(&mut schedule);
+        sequential.init(&mut new_schedule());
+        sequential.update(0);
+        for _ in 0..10 {
+            schedule.step(&mut scheduled);
+            step_in_order(&mut sequential, &ids);
+            assert_eq!(
+                get_traders_display(&scheduled),
+                get_traders_display(&sequential)
+            );
+        }
+        assert_eq!(scheduled.agent_histories, sequential.agent_histories);
+    }
+
+    #[test]
+    fn test_terrain() {
+        let mut config = test_config();
+        config.terrain = vec![TerrainConfig::new("mud", 1.0, true, 3, "land")];
+        let mut board = board_from_config(config.clone(), None, None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        assert!(matches!(
+            board.patch(&Int2D { x: 2, y: 3 }).unwrap().env_item,
+            EnvItem::Terrain(0)
+        ));
+
+        // Moving onto mud consumes three times the resources of staying still
+        schedule.step(&mut board);
+        let before = get_positions_and_food(&board);
+        schedule.step(&mut board);
+        let after = get_positions_and_food(&board);
+        let rate = config.resources[0].CONSUME_RATE as i32;
+        let mut moved = 0;
+        for (id, (pos, food)) in after {
+            let (prev_pos, prev_food) = before[&id];
+            let cost = if pos == prev_pos { 1 } else { 3 };
+            if cost == 3 {
+                moved += 1;
+            }
+            assert_eq!(prev_food - food, cost * rate);
+        }
+        assert!(moved > 0);
+
+        // Agents never move onto impassable rock
+        config.terrain = vec![TerrainConfig::new("rock", 1.0, false, 1, "evergreen_tree")];
+        let mut board = board_from_config(config, None, None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        schedule.step(&mut board);
+        let before = get_positions_and_food(&board);
+        for _ in 0..5 {
+            schedule.step(&mut board);
+        }
+        for (id, (pos, _)) in get_positions_and_food(&board) {
+            assert!(pos == before[&id].0);
+        }
+    }
+
+    #[test]
+    fn test_grid_map() {
+        // Food everywhere yielding 7 per step, with a single spawn point
+        let grid_map = GridMap::read(
+            r##"{
+                "legend": {
+                    "f": {"resource": "Food", "yield": 7},
+                    "@": {"resource": "Food", "yield": 7, "spawn": true},
+                    "#": {"terrain": "bush"}
+                },
+                "grid": ["#ffff#", "ffffff", "ffffff", "ff@fff", "ffffff", "ffffff"]
+            }"##,
+        )
+        .unwrap();
+        let mut board = board_from_config(test_config(), Some(grid_map), None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        assert!(board.spawn_points == vec![Int2D { x: 2, y: 2 }]);
+        assert!(matches!(
+            board.patch(&Int2D { x: 0, y: 5 }).unwrap().env_item,
+            EnvItem::Terrain(1)
+        ));
+        assert_eq!(board.resource_locations[&Resource(0)].len(), 34);
+
+        schedule.step(&mut board);
+        for (_, (pos, food)) in get_positions_and_food(&board) {
+            assert!(Topology::Bounded.step_distance(&pos, &Int2D { x: 2, y: 2 }, board.dim) <= 1);
+            assert_eq!(food, 7 - board.config.resources[0].CONSUME_RATE as i32);
+        }
+    }
+
+    #[test]
+    fn test_regrowth() {
+        // Food everywhere with a stock of 15, regrowing by a quarter each step
+        let grid_map = GridMap::read(
+            r##"{
+                "legend": {
+                    "f": {"resource": "Food"},
+                    "@": {"resource": "Food", "spawn": true}
+                },
+                "grid": ["ffffff", "ffffff", "ffffff", "ff@fff", "ffffff", "ffffff"]
+            }"##,
+        )
+        .unwrap();
+        let mut config = test_config();
+        config.world.REGROWTH = RegrowthConfig::Constant {
+            CAPACITY: 15,
+            RATE: 0.25,
+        };
+        let mut board = board_from_config(config, Some(grid_map), None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        schedule.step(&mut board);
+
+        // Agents acquire exactly what is harvested from the stocks, and depleted patches,
+        // including the crowded spawn point, drop out of the resource locations
+        let mut harvested = 0;
+        let mut depleted = Vec::new();
+        for x in 0..6 {
+            for y in 0..6 {
+                let pos = Int2D { x, y };
+                let stock = board.patch(&pos).unwrap().stock.unwrap();
+                harvested += (stock.capacity - stock.level).ceil() as i32;
+                if stock.is_depleted() {
+                    depleted.push(pos);
+                }
+            }
+        }
+        let consumed = board.config.resources[0].CONSUME_RATE as i32 * board.num_agents as i32;
+        let acquired = get_positions_and_food(&board)
+            .values()
+            .map(|(_, food)| food + consumed / board.num_agents as i32)
+            .sum::<i32>();
+        assert_eq!(harvested, acquired);
+        assert!(depleted.contains(&Int2D { x: 2, y: 2 }));
+        let food_locations = &board.resource_locations[&Resource(0)];
+        assert_eq!(food_locations.len(), 36 - depleted.len());
+        assert!(depleted.iter().all(|pos| !food_locations.contains(pos)));
+        let stock = board.patch(&Int2D { x: 2, y: 2 }).unwrap().stock.unwrap();
+        assert_eq!(stock.level, 0.25);
+    }
+
+    #[test]
+    fn test_topology() {
+        // Non-square board with food on the east edge, opposite the spawn point on the west edge
+        let grid_map = GridMap::read(
+            r##"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "@": {"terrain": "land", "spawn": true},
+                    "f": {"resource": "Food"},
+                    "w": {"resource": "Water"}
+                },
+                "grid": ["......w", "@.....f", "......."]
+            }"##,
+        )
+        .unwrap();
+        let spawn = Int2D { x: 0, y: 1 };
+        for (topology, steps_to_food, steps_to_water) in
+            [(Topology::Bounded, 6, 7), (Topology::Torus, 1, 2)]
+        {
+            let mut config = test_config();
+            config.world.WIDTH = 7;
+            config.world.HEIGHT = 3;
+            config.world.TOPOLOGY = topology;
+            let mut board = board_from_config(config, Some(grid_map.clone()), None);
+            let mut schedule: Schedule = new_schedule();
+            board.init(&mut schedule);
+            let steps_to = |resource| board.resource_distances[&resource].get(&spawn);
+            assert_eq!(steps_to(Resource(0)), Some(steps_to_food));
+            assert_eq!(steps_to(Resource(1)), Some(steps_to_water));
+
+            // Agents start on the edge and move at most one step, wrapping around a torus
+            schedule.step(&mut board);
+            for (_, (pos, _)) in get_positions_and_food(&board) {
+                assert!(topology.step_distance(&pos, &spawn, board.dim) <= 1);
+                assert!(board.can_enter(&pos));
+            }
+        }
+    }
+
+    #[test]
+    fn test_groups() {
+        // Groups of spawn points on the west and east edges, with water in between
+        let grid_map = GridMap::read(
+            r###"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "#": {"terrain": "bush"},
+                    "W": {"terrain": "land", "spawn": true, "group": "west"},
+                    "E": {"terrain": "land", "spawn": true, "group": "east"},
+                    "w": {"resource": "Water"}
+                },
+                "grid": ["##.##", "W.w.E", "##.##"]
+            }"###,
+        )
+        .unwrap();
+        let mut config = test_config();
+        config.world.WIDTH = 5;
+        config.world.HEIGHT = 3;
+        config.world.PLACEMENT = PlacementConfig::NearResource {
+            RESOURCE: "Water".to_string(),
+            RADIUS: 1,
+        };
+        let group = |n_agents, group: &str, food| AgentGroupConfig {
+            N_AGENTS: n_agents,
+            INIT: BTreeMap::from([("Food".to_string(), food)]),
+            PLACEMENT: PlacementConfig::SpawnPoints {
+                GROUP: Some(group.to_string()),
+            },
+        };
+        config.groups = vec![group(2, "west", 40), group(3, "east", 60)];
+        let mut board = board_from_config(config, Some(grid_map), None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        board.update_agents();
+        let config = Arc::clone(&board.config);
+
+        // Groups are given the first ids, with the remaining agents placed by the world config
+        let agents = get_positions_and_food(&board);
+        assert_eq!(agents.len(), config.world.N_AGENTS as usize);
+        for (id, (pos, food)) in agents {
+            match id {
+                0..=1 => assert!(pos == Int2D { x: 0, y: 1 } && food == 40),
+                2..=4 => assert!(pos == Int2D { x: 4, y: 1 } && food == 60),
+                _ => {
+                    assert!(board.can_enter(&pos));
+                    assert!(
+                        Topology::Bounded.step_distance(&pos, &Int2D { x: 2, y: 1 }, board.dim)
+                            <= 1
+                    );
+                    assert_eq!(food, config.resources[0].INIT);
+                }
+            }
+        }
+
+        // The groups of spawn points are kept in the layout
+        assert!(board.layout().spawn_groups() == board.spawn_groups);
+    }
+
+    #[test]
+    fn test_timeline() {
+        // Food in the west column and water in the east column
+        let grid_map = GridMap::read(
+            r##"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "f": {"resource": "Food"},
+                    "w": {"resource": "Water"}
+                },
+                "grid": ["f....w", "f....w", "f....w", "f....w", "f....w", "f....w"]
+            }"##,
+        )
+        .unwrap();
+        let mut config = test_config();
+        config.timeline = read_timeline(
+            r##"
+            [[timeline]]
+            NAME = "lean harvest"
+            START = 0
+            DURATION = 1
+            TYPE = "acquire_rate"
+            RESOURCE = "Food"
+            MULTIPLIER = 0.5
+
+            [[timeline]]
+            NAME = "drought"
+            START = 1
+            DURATION = 2
+            TYPE = "disappear"
+            RESOURCE = "Water"
+
+            [[timeline]]
+            NAME = "migration"
+            START = 2
+            TYPE = "shift"
+            RESOURCE = "Food"
+            DX = 2
+            DY = 0
+            "##,
+        )
+        .unwrap();
+        let mut board = board_from_config(config, Some(grid_map), None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        let column = |x| (0..6).map(move |y| Int2D { x, y }).collect::<Vec<Int2D>>();
+        let food = board.patch(&Int2D { x: 0, y: 0 }).unwrap();
+        let centre = Int2D { x: 3, y: 3 };
+        let steps_to = |board: &Board, resource| board.resource_distances[&resource].get(&centre);
+
+        // Regimes are applied at the start of each step
+        schedule.step(&mut board);
+        assert_eq!(board.active_regimes, vec![0]);
+        assert_eq!(board.acquire_rate(&Resource(0), &food), 5);
+        assert_eq!(board.acquire_rate(&Resource(1), &food), 10);
+        schedule.step(&mut board);
+        assert_eq!(board.active_regimes, vec![1]);
+        assert_eq!(board.acquire_rate(&Resource(0), &food), 10);
+        assert!(board.resource_locations[&Resource(1)].is_empty());
+        assert_eq!(steps_to(&board, Resource(1)), None);
+        assert!(matches!(
+            board.patch(&Int2D { x: 5, y: 3 }).unwrap().env_item,
+            EnvItem::Terrain(0)
+        ));
+        schedule.step(&mut board);
+        assert_eq!(board.active_regimes, vec![1, 2]);
+        assert!(board.resource_locations[&Resource(0)] == column(2));
+        assert_eq!(steps_to(&board, Resource(0)), Some(1));
+        assert!(board.resource_locations[&Resource(1)].is_empty());
+
+        // Water returns once the drought ends, while the food stays where it moved to
+        schedule.step(&mut board);
+        assert_eq!(board.active_regimes, vec![2]);
+        assert!(board.resource_locations[&Resource(0)] == column(2));
+        assert!(board.resource_locations[&Resource(1)] == column(5));
+        assert_eq!(steps_to(&board, Resource(1)), Some(2));
+        assert!(matches!(
+            board.patch(&Int2D { x: 0, y: 3 }).unwrap().env_item,
+            EnvItem::Terrain(0)
+        ));
+        let ids: HashSet<u32> = (0..6)
+            .flat_map(column)
+            .map(|pos| board.patch(&pos).unwrap().id)
+            .collect();
+        assert_eq!(ids.len(), 36);
+
+        // A timeline file given by the config is merged into the timeline of the board
+        let mut config = test_config();
+        config.world.TIMELINE_FILE = Some("clamms-timeline.toml".to_string());
+        let board = board_from_config(config, None, None);
+        assert_eq!(board.config.world.TIMELINE_FILE, None);
+        assert_eq!(
+            board.config.timeline,
+            load_timeline(Path::new("clamms-timeline.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        // The layout of a generated board reproduces the board when loaded as a grid map
+        let config = test_config();
+        let mut board = board_from_config(config.clone(), None, None);
+        board.init(&mut new_schedule());
+        let layout = board.layout();
+        assert_eq!(layout.grid.len(), config.world.HEIGHT as usize);
+        assert!(layout.legend.values().all(|entry| !entry.spawn));
+
+        let mut rebuilt = board_from_config(config, Some(layout.clone()), None);
+        rebuilt.init(&mut new_schedule());
+        assert_eq!(rebuilt.layout(), layout);
+        for (resource, locs) in &board.resource_locations {
+            let mut locs = locs.clone();
+            let mut rebuilt_locs = rebuilt.resource_locations[resource].clone();
+            locs.sort_by_key(|pos| (pos.x, pos.y));
+            rebuilt_locs.sort_by_key(|pos| (pos.x, pos.y));
+            assert!(locs == rebuilt_locs);
+        }
     }
 
     /// Get inventories of agents on a board.
@@ -483,8 +1650,8 @@ mod tests {
                 acc.insert(
                     trader.id(),
                     (
-                        trader.forager().count(&Resource::Food),
-                        trader.forager().count(&Resource::Water),
+                        trader.forager().count(&Resource(0)),
+                        trader.forager().count(&Resource(1)),
                     ),
                 );
                 acc
@@ -503,28 +1670,11 @@ mod tests {
 
     #[test]
     fn test_board_update() {
-        init();
         // Set-up small board with three agents and no resources within trading radius that will make inverse offers
-        let seed = core_config().world.RANDOM_SEED;
-        let num_agents = core_config().world.N_AGENTS;
-        let dim: (u16, u16) = (core_config().world.WIDTH, core_config().world.HEIGHT);
-        let has_trading = core_config().world.HAS_TRADING;
-        let model = SARSAModel::new(
-            (0..num_agents).map(|n| n.into()).collect(),
-            AgentStateItems::iter().collect::<Vec<AgentStateItems>>(),
-            InvLevel::iter().collect::<Vec<InvLevel>>(),
-            Action::iter().collect::<Vec<Action>>(),
-            false,
-        );
-
-        let mut board = if let Some(file_name) = &core_config().world.RESOURCE_LOCATIONS_FILE {
-            Board::new_with_seed_resources(dim, num_agents, seed, file_name, model, has_trading)
-        } else {
-            Board::new_with_seed(dim, num_agents, seed, model, has_trading)
-        };
+        let mut board = board_from_config(test_config(), None, None);
 
         // Use scheduler and run directly once
-        let mut schedule: Schedule = Schedule::new();
+        let mut schedule: Schedule = new_schedule();
         board.init_with_test_agents(&mut schedule);
 
         // Get traders and check resource levels are as expected
@@ -550,4 +1700,206 @@ mod tests {
         assert_eq!(*inv2.get(&1).unwrap(), (89, -9));
         assert_eq!(*inv2.get(&2).unwrap(), (-10, -10));
     }
+
+    #[test]
+    fn test_three_resources() {
+        // A third resource is placed on the board, held by agents and observed in their state
+        let mut config = test_config();
+        config.resources.push(ResourceConfig {
+            NAME: "Shelter".to_string(),
+            ABUNDANCE: 0.2,
+            INIT: 30,
+            CONSUME_RATE: 2,
+            TEXTURE: "tree".to_string(),
+            ..config.resources[0].clone()
+        });
+        let shelter = Resource(2);
+        let mut board = board_from_config(config, None, None);
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        assert!(!board.resource_locations[&shelter].is_empty());
+        schedule.step(&mut board);
+        for trader in board.get_agents() {
+            let forager = trader.forager();
+            assert_eq!(forager.inventory().len(), 3);
+            assert!(forager.count(&shelter) >= 28);
+            let state = forager.agent_state(&board);
+            assert_eq!(state.min_steps_to_resources.len(), 3);
+            assert!(state.min_steps_to_resources[2].is_some());
+        }
+    }
+
+    /// Places agents with the given initial inventories on a board, `spacing` apart along its
+    /// bottom edge, and inits the resources, returning the schedule of the agents.
+    fn place_agents_in_row(board: &mut Board, inits: &[[i32; 2]], spacing: i32) -> Schedule {
+        let mut schedule: Schedule = new_schedule();
+        let resources = board.config.resources.clone();
+        for (id, init) in (0..).zip(inits) {
+            let pos = Int2D {
+                x: spacing * id as i32,
+                y: 0,
+            };
+            let agent = Trader::new(Forager::new(id, pos, init, &resources));
+            board.set_agent(agent);
+            board.agent_histories.insert(id, History::new());
+            schedule.schedule_repeating(Box::new(agent), 0., 0);
+        }
+        board.init_resources();
+        schedule
+    }
+
+    /// Steps a board and the schedule of its agents `n` times.
+    fn step_board(board: &mut Board, schedule: &mut Schedule, n: usize) {
+        for _ in 0..n {
+            schedule.step(board);
+        }
+    }
+
+    #[test]
+    fn test_move_to_agent() {
+        // Two agents on the bottom edge, either side of a wall with a gap at the top
+        let grid_map = GridMap::read(
+            r##"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "#": {"terrain": "rock"}
+                },
+                "grid": ["......", "......", "..#...", "..#...", "..#...", "..#..."]
+            }"##,
+        )
+        .unwrap();
+        let mut config = test_config();
+        config.terrain = vec![
+            TerrainConfig::new("land", 1.0, true, 1, "grass"),
+            TerrainConfig::new("rock", 0.0, false, 1, "evergreen_tree"),
+        ];
+        let mut board = board_from_config(config, Some(grid_map), None);
+        let mut schedule = place_agents_in_row(&mut board, &[[1000, 1000], [1000, 1000]], 4);
+        board.update(0);
+        board.before_step(&mut schedule);
+
+        // Each agent observes the other, not itself, as the nearest agent and moves towards it
+        let mut rng = StdRng::seed_from_u64(0);
+        for (id, other) in [(0, 1), (1, 0)] {
+            let forager = *board.get_agent_by_id(&id).forager();
+            let to_other = board.path_distances(&[board.get_agent_by_id(&other).get_position()]);
+            let steps = to_other.get(&forager.get_position());
+            assert_eq!(steps, Some(12));
+            assert_eq!(forager.agent_state(&board).min_steps_to_trader, steps);
+            let dir = forager
+                .try_move_towards_agent(&board, None, &mut rng)
+                .unwrap();
+            let next = board.neighbour(&forager.get_position(), dir).unwrap();
+            assert_eq!(to_other.get(&next), Some(11));
+        }
+    }
+
+    #[test]
+    fn test_mortality() {
+        // Agents die after two consecutive steps ending with an inventory below -12
+        let mut config = test_config();
+        config.agent.MORTALITY = MortalityConfig::Starvation {
+            THRESHOLD: -12,
+            STEPS: 2,
+        };
+        config.rl.SARSA_N = 2;
+        let mut board = board_from_config(config, None, None);
+        let mut schedule =
+            place_agents_in_row(&mut board, &[[1000, 1000], [1000, 1000], [0, 0]], 2);
+
+        // Agent 2, holding nothing from the start, dies at the end of step 3 and leaves the board
+        step_board(&mut board, &mut schedule, 3);
+        assert!(board.agents.get(&2).is_some());
+        step_board(&mut board, &mut schedule, 1);
+        assert!(board.agents.get(&2).is_none());
+        let on_grid = |board: &Board, id| {
+            (0..6)
+                .flat_map(|x| (0..6).map(move |y| Int2D { x, y }))
+                .filter_map(|pos| board.agent_grid.get_objects(&pos))
+                .any(|traders| traders.contains(&Trader::dummy(id)))
+        };
+        assert!(on_grid(&board, 0));
+        assert!(!on_grid(&board, 2));
+        assert_eq!(board.agent_histories[&2].end(), Some(4));
+
+        // It leaves the schedule in the next step, while the other agents and learning go on
+        // past its terminal transition
+        step_board(&mut board, &mut schedule, 6);
+        assert_eq!(schedule.get_all_events().len(), 2);
+        assert_eq!(board.agents.len(), 2);
+        assert_eq!(board.agent_histories[&0].len(), 10);
+        assert_eq!(board.agent_histories[&2].len(), 4);
+    }
+
+    #[test]
+    fn test_reproduction() {
+        // Agents holding more than 500 of every resource spawn an offspring with half of each
+        let mut config = test_config();
+        config.agent.REPRODUCTION = ReproductionConfig::Surplus {
+            THRESHOLD: 500,
+            THRESHOLDS: BTreeMap::new(),
+            SHARE: 0.5,
+            NOISE: 0.1,
+        };
+        config.rl.SARSA_N = 2;
+        config.rl.MULTI_POLICY = true;
+        let mut board = board_from_config(config, None, None);
+        let mut schedule = place_agents_in_row(&mut board, &[[1000, 1000], [100, 100]], 4);
+        // Mark the table of the parent, which is not updated before step SARSA_N + 1
+        for q in board.model.get_table_by_id_mut(0).values_mut() {
+            *q = 123.0;
+        }
+
+        // Agent 0 ends the first step with 995 of each, so gives 497 to its offspring 2, placed
+        // next to it with a noisy copy of its Q-table
+        step_board(&mut board, &mut schedule, 1);
+        assert_eq!(board.population, vec![3]);
+        assert_eq!(schedule.get_all_events().len(), 3);
+        let parent = *board.get_agent_by_id(&0).forager();
+        let child = *board.get_agent_by_id(&2).forager();
+        assert_eq!(parent.inventory(), [498, 498]);
+        assert_eq!(child.inventory(), [497, 497]);
+        assert_eq!(
+            (child.pos.x - parent.pos.x).abs() + (child.pos.y - parent.pos.y).abs(),
+            1
+        );
+        let (parent_tbl, child_tbl) = (
+            board.model.get_table_by_id(0),
+            board.model.get_table_by_id(2),
+        );
+        assert_eq!(parent_tbl.len(), child_tbl.len());
+        assert_ne!(parent_tbl, child_tbl);
+        for (key, q) in child_tbl {
+            assert_eq!(parent_tbl[key], 123.0);
+            assert!((q - 123.0).abs() < 1.0);
+        }
+        assert_eq!(board.agent_histories[&2].start, 1);
+        assert!(board.agent_histories[&2].is_empty());
+
+        // The offspring steps from the next step, and learns from its own trajectory
+        step_board(&mut board, &mut schedule, 5);
+        assert_eq!(board.population, vec![3; 6]);
+        assert_eq!(board.agent_histories[&0].len(), 6);
+        assert_eq!(board.agent_histories[&2].len(), 5);
+    }
+
+    #[test]
+    fn test_boards_with_different_configs() {
+        // Two boards with different consumption rates run side by side
+        let mut config = test_config();
+        config.resources[0].CONSUME_RATE = 1;
+        let mut boards = [
+            board_from_config(test_config(), None, None),
+            board_from_config(config, None, None),
+        ];
+        let mut schedules = [new_schedule(), new_schedule()];
+        for (board, schedule) in boards.iter_mut().zip(schedules.iter_mut()) {
+            board.init_with_test_agents(schedule);
+        }
+        for (board, schedule) in boards.iter_mut().zip(schedules.iter_mut()) {
+            schedule.step(board);
+        }
+        assert_eq!(*get_inventories(&boards[0]).get(&2).unwrap(), (-5, -5));
+        assert_eq!(*get_inventories(&boards[1]).get(&2).unwrap(), (-1, -5));
+    }
 }
@@ -1,55 +1,134 @@
-use crate::config::core_config;
-use rand::{
-    distributions::{Distribution, Standard},
-    Rng,
-};
+use crate::config::{Config, ResourceConfig, TerrainConfig};
+use rand::{distributions::Distribution, Rng};
 use serde::{Deserialize, Serialize};
-use strum_macros::EnumIter;
 
 use super::board::Patch;
 
 #[derive(Clone, Copy, Debug)]
 pub enum EnvItem {
-    Land,
-    Bush,
+    /// Terrain type given by its index in the configured terrain types.
+    Terrain(usize),
     Resource(Resource),
 }
 
-impl Distribution<EnvItem> for Standard {
+impl EnvItem {
+    /// Gets the terrain type of the item, if not a resource.
+    pub fn terrain<'a>(&self, terrain: &'a [TerrainConfig]) -> Option<&'a TerrainConfig> {
+        match self {
+            EnvItem::Terrain(index) => terrain.get(*index),
+            EnvItem::Resource(_) => None,
+        }
+    }
+
+    /// Whether agents can move onto a patch with the item. Resources are always passable.
+    pub fn passable(&self, terrain: &[TerrainConfig]) -> bool {
+        self.terrain(terrain).is_none_or(|t| t.PASSABLE)
+    }
+
+    /// Multiple of the consumption rates for moving onto a patch with the item.
+    pub fn movement_cost(&self, terrain: &[TerrainConfig]) -> u32 {
+        self.terrain(terrain).map_or(1, |t| t.MOVEMENT_COST)
+    }
+
+    pub fn texture(&self, resources: &[ResourceConfig], terrain: &[TerrainConfig]) -> String {
+        match self {
+            EnvItem::Resource(resource) => resource.texture(resources),
+            EnvItem::Terrain(_) => self
+                .terrain(terrain)
+                .map(|t| t.TEXTURE.to_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Distribution of terrain types with probabilities given by the terrain config.
+pub struct TerrainDistribution {
+    probs: Vec<f32>,
+}
+
+impl From<&[TerrainConfig]> for TerrainDistribution {
+    fn from(terrain: &[TerrainConfig]) -> Self {
+        TerrainDistribution {
+            probs: terrain.iter().map(|t| t.PROB).collect(),
+        }
+    }
+}
+
+impl Distribution<EnvItem> for TerrainDistribution {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> EnvItem {
         let pick: f32 = rng.gen();
-        if pick < core_config().world.FOOD_ABUNDANCE {
-            EnvItem::Resource(Resource::Food)
-        } else if pick < core_config().world.FOOD_ABUNDANCE + core_config().world.WATER_ABUNDANCE {
-            EnvItem::Resource(Resource::Water)
-        } else if rng.gen::<f32>() < core_config().world.LAND_PROP {
-            EnvItem::Land
-        } else {
-            EnvItem::Bush
+        let mut cumulative = 0.0;
+        for (index, prob) in self.probs.iter().enumerate() {
+            cumulative += prob;
+            if pick < cumulative {
+                return EnvItem::Terrain(index);
+            }
         }
+        // Probabilities may sum to slightly less than one due to rounding
+        EnvItem::Terrain(self.probs.len().saturating_sub(1))
     }
 }
 
-#[derive(
-    Debug, Clone, Copy, Hash, PartialEq, Eq, EnumIter, PartialOrd, Ord, Serialize, Deserialize,
-)]
-pub enum Resource {
-    Food,
-    Water,
+/// Distribution of environment items with proportions given by the config.
+pub struct EnvItemDistribution {
+    abundances: Vec<f32>,
+    terrain: TerrainDistribution,
 }
 
-impl Resource {
-    pub fn texture(&self) -> String {
-        match self {
-            Resource::Food => "fruit".to_string(),
-            Resource::Water => "water".to_string(),
+impl From<&Config> for EnvItemDistribution {
+    fn from(config: &Config) -> Self {
+        EnvItemDistribution {
+            abundances: config.resources.iter().map(|r| r.ABUNDANCE).collect(),
+            terrain: TerrainDistribution::from(config.terrain.as_slice()),
         }
     }
+}
 
-    pub fn to_patch(self, id: u32) -> Patch {
-        Patch {
-            id,
-            env_item: EnvItem::Resource(self),
+impl Distribution<EnvItem> for EnvItemDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> EnvItem {
+        let pick: f32 = rng.gen();
+        let mut cumulative = 0.0;
+        for (index, abundance) in self.abundances.iter().enumerate() {
+            cumulative += abundance;
+            if pick < cumulative {
+                return EnvItem::Resource(Resource(index));
+            }
         }
+        self.terrain.sample(rng)
+    }
+}
+
+/// Largest number of configured resources, bounding the inventories of agents.
+pub const MAX_RESOURCES: usize = 8;
+
+/// Resource given by its index in the configured resources.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Resource(pub usize);
+
+impl Resource {
+    /// Gets every configured resource, in order.
+    pub fn all(resources: &[ResourceConfig]) -> impl Iterator<Item = Resource> {
+        (0..resources.len()).map(Resource)
+    }
+
+    /// Gets the configured resource with the given name, if any.
+    pub fn named(resources: &[ResourceConfig], name: &str) -> Option<Resource> {
+        resources.iter().position(|r| r.NAME == name).map(Resource)
+    }
+
+    pub fn config<'a>(&self, resources: &'a [ResourceConfig]) -> &'a ResourceConfig {
+        &resources[self.0]
+    }
+
+    pub fn name<'a>(&self, resources: &'a [ResourceConfig]) -> &'a str {
+        &self.config(resources).NAME
+    }
+
+    pub fn texture(&self, resources: &[ResourceConfig]) -> String {
+        self.config(resources).TEXTURE.to_owned()
+    }
+
+    pub fn to_patch(self, id: u32) -> Patch {
+        Patch::new(id, EnvItem::Resource(self))
     }
 }
@@ -1,20 +1,18 @@
 use super::action::Action;
-use super::agent_state::{AgentState, DiscrRep};
+use super::agent_state::AgentState;
 use super::board::Board;
-use super::environment::Resource;
-use super::history::SAR;
+use super::environment::{Resource, MAX_RESOURCES};
 use super::inventory::Inventory;
 use super::policy::Policy;
-use super::reward::Reward;
-use super::routing::{get_resource_locations, get_trader_locations, Position, Router};
-use super::trader::Trader;
-use crate::config::core_config;
-use crate::model::board::Patch;
+use super::routing::{Position, Router};
+use crate::config::{MortalityConfig, ReproductionConfig, ResourceConfig};
+use crate::event_log::EventKind;
 use crate::model::environment::EnvItem;
 use krabmaga::engine::state::State;
 use krabmaga::engine::{agent::Agent, location::Int2D};
 use rand::{
     distributions::{Distribution, Standard},
+    rngs::StdRng,
     Rng,
 };
 use std::hash::{Hash, Hasher};
@@ -23,11 +21,17 @@ use std::hash::{Hash, Hasher};
 pub struct Forager {
     id: u32,
     pub pos: Int2D,
-    food: i32,
-    water: i32,
+    /// Inventory of each resource, indexed by `Resource`. Fixed in size so foragers can be
+    /// stored in grids.
+    inventory: [i32; MAX_RESOURCES],
+    max_inventory: [i32; MAX_RESOURCES],
+    n_resources: usize,
+    /// Number of consecutive steps ending with an inventory below the threshold of the
+    /// mortality rule.
+    starving_steps: u32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 /// Direction of movement.
 pub enum Direction {
     North,
@@ -37,6 +41,22 @@ pub enum Direction {
     // Stationary,
 }
 
+impl Direction {
+    /// Gets the location one step in the direction from a location.
+    pub fn step(&self, pos: &Int2D) -> Int2D {
+        let (dx, dy) = match self {
+            Direction::North => (0, 1),
+            Direction::East => (1, 0),
+            Direction::South => (0, -1),
+            Direction::West => (-1, 0),
+        };
+        Int2D {
+            x: pos.x + dx,
+            y: pos.y + dy,
+        }
+    }
+}
+
 impl Distribution<Direction> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Direction {
         match rng.gen_range(0..=3) {
@@ -52,19 +72,12 @@ impl Distribution<Direction> for Standard {
 impl Inventory for Forager {
     /// Returns the amount of a given resource in the inventory.
     fn count(&self, resource: &Resource) -> i32 {
-        match resource {
-            Resource::Food => self.food,
-            Resource::Water => self.water,
-        }
+        self.inventory[resource.0]
     }
 
     fn acquire(&mut self, resource: &Resource, quantity: i32) {
-        match resource {
-            Resource::Food => self.food += quantity,
-            Resource::Water => self.water += quantity,
-        }
-        self.food = self.food.min(core_config().agent.FOOD_MAX_INVENTORY);
-        self.water = self.water.min(core_config().agent.WATER_MAX_INVENTORY);
+        let count = &mut self.inventory[resource.0];
+        *count = (*count + quantity).min(self.max_inventory[resource.0]);
     }
 
     // fn consume(&mut self, resource: &Resource, quantity: i32) {
@@ -73,19 +86,35 @@ impl Inventory for Forager {
 }
 
 impl Policy for Forager {
-    fn chose_action(&self, state: &mut dyn State, agent_state: &AgentState) -> Action {
-        let state = state.as_any_mut().downcast_mut::<Board>().unwrap();
+    fn chose_action(
+        &self,
+        state: &dyn State,
+        agent_state: &AgentState,
+        rng: &mut StdRng,
+    ) -> Action {
+        let state = state.as_any().downcast_ref::<Board>().unwrap();
         state
-            .model
-            .sample_action_by_id(self.id, &agent_state.representation(), &mut state.rng)
-        // if agent_state.food < agent_state.water {
-        //     Action::ToFood
-        // } else {
-        //     Action::ToWater
-        // }
+            .policy
+            .select_action(self.id, agent_state, &state.model, &state.config, rng)
     }
 }
 
+/// Step taken by an agent, buffered on the board until committed at the end of the step.
+///
+/// Agents only read the board during their steps, apart from buffering them, so that steps give
+/// the same results in any order or on any thread. The board commits the buffered steps in the
+/// random order of the step, drawn before the agents step.
+#[derive(Clone)]
+pub struct PendingStep {
+    /// The agent after moving and consuming resources.
+    pub forager: Forager,
+    /// State observed by the agent at the start of the step.
+    pub agent_state: AgentState,
+    pub action: Action,
+    /// Location of the agent at the start of the step.
+    pub from: Int2D,
+}
+
 impl Agent for Forager {
     fn step(&mut self, state: &mut dyn State) {
         // now downcasting to a mutable reference
@@ -94,83 +123,48 @@ impl Agent for Forager {
         // observe current agent state
         let agent_state = self.agent_state(board);
 
-        // select action from policy
-        let action = self.chose_action(board, &agent_state);
+        // select action from policy, drawing from the agent's own random stream
+        let mut rng = board.agent_rng(self.id);
+        let action = self.chose_action(board, &agent_state, &mut rng);
 
         // route agent based on action
+        let from = self.pos;
         let route = match action {
-            Action::ToFood => self.try_move_towards_resource(&Resource::Food, board, None),
-            Action::ToWater => self.try_move_towards_resource(&Resource::Water, board, None),
-            Action::ToAgent => self.try_move_towards_agent(board, None),
+            Action::ToResource(resource) => {
+                self.try_move_towards_resource(&resource, board, None, &mut rng)
+            }
+            Action::ToAgent => self.try_move_towards_agent(board, None, &mut rng),
             _ => None,
         };
 
         // TODO: consider moving to a new update_position method:
-        if let Some(dir) = route {
-            match dir {
-                Direction::North => self.pos.y += 1,
-                Direction::East => self.pos.x += 1,
-                Direction::South => self.pos.y -= 1,
-                Direction::West => self.pos.x -= 1,
+        let mut movement_cost = 1;
+        if let Some(pos) = route.and_then(|dir| board.neighbour(&self.pos, dir)) {
+            // Only move onto passable terrain
+            let item = board.patch(&pos).map(|patch| patch.env_item);
+            if pos != self.pos && board.can_enter(&pos) {
+                self.pos = pos;
+                movement_cost = item.map_or(1, |item| item.movement_cost(&board.config.terrain));
             }
-            // Clamp positions to be 1 <= pos < dim
-            self.pos.x = self.pos.x.clamp(1, (board.dim.0 - 1).into());
-            self.pos.y = self.pos.y.clamp(1, (board.dim.1 - 1).into());
         }
 
-        // resources depleted automatically after taking an action (even if Action::Stationary)
-        self.consume(&Resource::Food, core_config().agent.FOOD_CONSUME_RATE);
-        self.consume(&Resource::Water, core_config().agent.WATER_CONSUME_RATE);
-
-        // if now on a resource, gather the resource
-        // Note: get_objects() checks the "read" resource grid, currently resources are fixed once
-        // initialised and do not update during the simulation. If resources change during a step,
-        // ensure the get_objects() returns updated resources as required.
-        if let Some(patches) = board.resource_grid.get_objects(&self.pos) {
-            patches.iter().for_each(|patch| {
-                if let Patch {
-                    id: _,
-                    env_item: EnvItem::Resource(resource),
-                } = patch
-                {
-                    match resource {
-                        Resource::Food => {
-                            self.acquire(&Resource::Food, core_config().agent.FOOD_ACQUIRE_RATE)
-                        }
-                        Resource::Water => {
-                            self.acquire(&Resource::Water, core_config().agent.WATER_ACQUIRE_RATE)
-                        }
-                    }
-                }
-            })
+        // resources depleted automatically after taking an action (even if Action::Stationary),
+        // scaled by the movement cost of any terrain moved onto
+        for (resource, config) in board.config.resources.iter().enumerate() {
+            self.consume(&Resource(resource), config.CONSUME_RATE * movement_cost);
         }
 
-        // Update agent stored in agent_grid, will not be readable until lazy_update after board update
-        board
-            .agent_grid
-            .set_object_location(Trader::new(*self), &self.pos);
-
-        // push (s_n, a_n, r_n+1) to history
-        board
-            .agent_histories
-            .get_mut(&self.id())
-            .expect("HashMap initialised for all agents")
-            .push(SAR::new(
+        // Buffer the step for the board to harvest resources, update the agent and push
+        // (s_n, a_n, r_n+1) to its history at the end of the step
+        board.pending_steps.insert(
+            self.id,
+            PendingStep {
+                forager: *self,
                 agent_state,
                 action,
-                Reward::from_inv_count_linear(self.food, self.water),
-            ));
-
-        // if self.id == 0 {
-        //     println!(
-        //         "agent: {:?}, food: {:?}, water: {:?}, act: {:?}, pos: {}",
-        //         self.id,
-        //         self.food,
-        //         self.water,
-        //         action,
-        //         self.get_position()
-        //     );
-        // }
+                from,
+            },
+        );
     }
 }
 
@@ -200,15 +194,20 @@ impl Hash for Forager {
 }
 
 impl Forager {
-    pub fn new(id: u32, pos: Int2D, food: i32, water: i32) -> Self {
+    /// Constructs a forager with an initial inventory of each resource, indexed by `Resource`.
+    pub fn new(id: u32, pos: Int2D, init: &[i32], resources: &[ResourceConfig]) -> Self {
         let mut forager = Self {
             id,
             pos,
-            food: 0,
-            water: 0,
+            inventory: [0; MAX_RESOURCES],
+            max_inventory: [0; MAX_RESOURCES],
+            n_resources: resources.len(),
+            starving_steps: 0,
         };
-        forager.acquire(&Resource::Food, food);
-        forager.acquire(&Resource::Water, water);
+        for (resource, config) in resources.iter().enumerate() {
+            forager.max_inventory[resource] = config.MAX_INVENTORY;
+            forager.acquire(&Resource(resource), init[resource]);
+        }
         forager
     }
 
@@ -216,17 +215,115 @@ impl Forager {
         self.id
     }
 
-    pub fn agent_state(&self, state: &mut dyn krabmaga::engine::state::State) -> AgentState {
-        let min_steps_to_food = self.min_steps_to(get_resource_locations(&Resource::Food, state));
-        let min_steps_to_water = self.min_steps_to(get_resource_locations(&Resource::Water, state));
+    /// Gets the inventory of each resource, indexed by `Resource`.
+    pub fn inventory(&self) -> &[i32] {
+        &self.inventory[..self.n_resources]
+    }
+
+    /// Gets the amount of a resource that can be acquired before the inventory is full.
+    fn room(&self, resource: &Resource) -> i32 {
+        (self.max_inventory[resource.0] - self.count(resource)).max(0)
+    }
+
+    /// Counts the step towards the death of the agent under a mortality rule, given its
+    /// inventory at the end of the step, returning whether the agent dies.
+    pub fn starve(&mut self, mortality: &MortalityConfig) -> bool {
+        match *mortality {
+            MortalityConfig::Immortal => false,
+            MortalityConfig::Starvation { THRESHOLD, STEPS } => {
+                if self.inventory().iter().any(|&count| count < THRESHOLD) {
+                    self.starving_steps += 1;
+                } else {
+                    self.starving_steps = 0;
+                }
+                self.starving_steps >= STEPS
+            }
+        }
+    }
+
+    /// Spawns an offspring with the given id at the location of the agent under a reproduction
+    /// rule, if the inventory of the agent at the end of the step is above the thresholds of
+    /// every resource, giving it a share of the inventory.
+    pub fn reproduce(
+        &mut self,
+        id: u32,
+        reproduction: &ReproductionConfig,
+        resources: &[ResourceConfig],
+    ) -> Option<Forager> {
+        let ReproductionConfig::Surplus {
+            THRESHOLD,
+            THRESHOLDS,
+            SHARE,
+            ..
+        } = reproduction
+        else {
+            return None;
+        };
+        let surplus = resources.iter().enumerate().all(|(resource, config)| {
+            self.count(&Resource(resource)) > *THRESHOLDS.get(&config.NAME).unwrap_or(THRESHOLD)
+        });
+        if !surplus {
+            return None;
+        }
+        let shares: Vec<i32> = self
+            .inventory()
+            .iter()
+            .map(|&count| (SHARE * count.max(0) as f32) as i32)
+            .collect();
+        for (resource, &share) in shares.iter().enumerate() {
+            self.acquire(&Resource(resource), -share);
+        }
+        Some(Forager::new(id, self.pos, &shares, resources))
+    }
+
+    /// Gathers the resource of the patch the agent is on, if any, taking from its stock if
+    /// finite. A harvested patch is written to the "write" resource grid, so that agents
+    /// harvesting later in the step see the depleted stock through `current_patch()`.
+    pub fn harvest(&mut self, board: &mut Board) {
+        let Some(mut patch) = board.current_patch(&self.pos) else {
+            return;
+        };
+        if let EnvItem::Resource(resource) = patch.env_item {
+            let count = self.count(&resource);
+            let mut amount = board.acquire_rate(&resource, &patch);
+            if let Some(stock) = patch.stock.as_mut() {
+                // Only take what the agent can hold
+                amount = stock.harvest(amount.min(self.room(&resource)));
+                board.resource_grid.set_object_location(patch, &self.pos);
+            }
+            self.acquire(&resource, amount);
+            board.event_log.log(
+                board.step,
+                EventKind::Acquisition {
+                    agent: self.id,
+                    resource: resource.name(&board.config.resources).to_string(),
+                    amount: self.count(&resource) - count,
+                },
+            );
+        }
+    }
+
+    pub fn agent_state(&self, state: &dyn krabmaga::engine::state::State) -> AgentState {
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        let min_steps_to = |resource: &Resource| {
+            board
+                .resource_distances
+                .get(resource)
+                .expect("HashMap initialised for all resource types")
+                .get(&self.pos)
+        };
+        let min_steps_to_resources = Resource::all(&board.config.resources)
+            .map(|resource| min_steps_to(&resource))
+            .collect();
 
-        let min_steps_to_trader = self.min_steps_to(get_trader_locations(state));
+        let min_steps_to_trader = board
+            .trader_distances
+            .nearest_other(&self.pos)
+            .map(|(_, steps)| steps);
 
         AgentState {
-            food: self.food,
-            water: self.water,
-            min_steps_to_food,
-            min_steps_to_water,
+            inventory: self.inventory().to_vec(),
+            min_steps_to_resources,
             min_steps_to_trader,
             // TODO: placeholder waiting for routing work
             // last_action: state
@@ -245,8 +342,10 @@ impl Forager {
                 x: Default::default(),
                 y: Default::default(),
             },
-            food: 0,
-            water: 0,
+            inventory: [0; MAX_RESOURCES],
+            max_inventory: [0; MAX_RESOURCES],
+            n_resources: 0,
+            starving_steps: 0,
         }
     }
 }
@@ -0,0 +1,153 @@
+//! Map files giving the terrain or resource of every patch, and agent spawn points, as a grid
+//! of symbols with a legend.
+use super::board::{ClammsInt2D, MapError};
+use krabmaga::engine::location::Int2D;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Contents of the patches marked by a symbol of a grid map.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct LegendEntry {
+    /// Name of the terrain type of a patch without a resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terrain: Option<String>,
+    /// Name of the resource of the patch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// Amount of the resource acquired per step on the patch, in place of the acquire rate in
+    /// the agent config.
+    #[serde(default, rename = "yield", skip_serializing_if = "Option::is_none")]
+    pub resource_yield: Option<i32>,
+    /// Whether agents can be spawned on the patch.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub spawn: bool,
+    /// Name of the group of spawn points the patch belongs to, if a spawn point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+/// Map of the board as rows of symbols, from the north edge (`y = HEIGHT - 1`) to the south edge
+/// (`y = 0`), with a legend giving the contents of the patches marked by each symbol.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GridMap {
+    pub legend: BTreeMap<char, LegendEntry>,
+    pub grid: Vec<String>,
+}
+
+impl GridMap {
+    /// Reads a grid map from JSON.
+    pub fn read(input: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(input)
+    }
+
+    /// Loads a grid map from a JSON file.
+    pub fn load(path: &Path) -> Result<Self, MapError> {
+        let input =
+            std::fs::read_to_string(path).map_err(|err| MapError::Io(path.to_path_buf(), err))?;
+        GridMap::read(&input).map_err(|err| MapError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Gets the location and symbol of every patch in the grid.
+    pub fn symbols(&self) -> impl Iterator<Item = (Int2D, char)> + '_ {
+        let height = self.grid.len();
+        self.grid
+            .iter()
+            .enumerate()
+            .flat_map(move |(row, symbols)| {
+                symbols.chars().enumerate().map(move |(x, symbol)| {
+                    let pos = Int2D {
+                        x: x as i32,
+                        y: (height - 1 - row) as i32,
+                    };
+                    (pos, symbol)
+                })
+            })
+    }
+
+    /// Gets the location and legend entry of every patch in the grid with a symbol in the legend.
+    pub fn patches(&self) -> impl Iterator<Item = (Int2D, &LegendEntry)> + '_ {
+        self.symbols()
+            .filter_map(|(pos, symbol)| self.legend.get(&symbol).map(|entry| (pos, entry)))
+    }
+
+    /// Gets the locations of each resource on the map, by resource name.
+    pub fn resource_locations(&self) -> BTreeMap<String, Vec<Int2D>> {
+        let mut resource_locations: BTreeMap<String, Vec<Int2D>> = BTreeMap::new();
+        for (pos, entry) in self.patches() {
+            if let Some(resource) = &entry.resource {
+                resource_locations
+                    .entry(resource.to_owned())
+                    .or_default()
+                    .push(pos);
+            }
+        }
+        resource_locations
+    }
+
+    /// Gets the locations of the resources, as embedded in provenance.
+    pub fn clamms_resource_locations(&self) -> BTreeMap<String, Vec<ClammsInt2D>> {
+        self.resource_locations()
+            .into_iter()
+            .map(|(resource, locs)| (resource, locs.into_iter().map(Into::into).collect()))
+            .collect()
+    }
+
+    /// Gets the spawn points of agents.
+    pub fn spawn_points(&self) -> Vec<Int2D> {
+        self.patches()
+            .filter(|(_, entry)| entry.spawn)
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    /// Gets the spawn points in each named group.
+    pub fn spawn_groups(&self) -> BTreeMap<String, Vec<Int2D>> {
+        let mut spawn_groups: BTreeMap<String, Vec<Int2D>> = BTreeMap::new();
+        for (pos, entry) in self.patches() {
+            if let (true, Some(group)) = (entry.spawn, &entry.group) {
+                spawn_groups.entry(group.to_owned()).or_default().push(pos);
+            }
+        }
+        spawn_groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_GRID_MAP: &str = r##"{
+        "legend": {
+            ".": {"terrain": "land"},
+            "#": {"terrain": "bush"},
+            "@": {"terrain": "land", "spawn": true},
+            "f": {"resource": "Food"},
+            "F": {"resource": "Food", "yield": 20},
+            "w": {"resource": "Water", "spawn": true}
+        },
+        "grid": [
+            "..#",
+            "f@w",
+            "F.."
+        ]
+    }"##;
+
+    #[test]
+    fn test_grid_map() {
+        let grid_map = GridMap::read(TEST_GRID_MAP).unwrap();
+        let resource_locations = grid_map.resource_locations();
+        assert!(resource_locations["Food"] == vec![Int2D { x: 0, y: 1 }, Int2D { x: 0, y: 0 }]);
+        assert!(resource_locations["Water"] == vec![Int2D { x: 2, y: 1 }]);
+        assert!(grid_map.spawn_points() == vec![Int2D { x: 1, y: 1 }, Int2D { x: 2, y: 1 }]);
+        let (_, entry) = grid_map
+            .patches()
+            .find(|(pos, _)| *pos == Int2D { x: 0, y: 0 })
+            .unwrap();
+        assert_eq!(entry.resource_yield, Some(20));
+
+        // Round trips through JSON
+        let serial = serde_json::to_string(&grid_map).unwrap();
+        assert_eq!(GridMap::read(&serial).unwrap(), grid_map);
+    }
+}
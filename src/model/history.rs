@@ -1,19 +1,18 @@
-use super::{
-    action::Action,
-    agent_state::{AgentState, AgentStateItems, DiscrRep, InvLevel},
-    q_table::QKey,
-    reward::Reward,
-};
+use super::{agent_state::DiscrRep, q_table::QKey, reward::Reward};
+use crate::config::Config;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 pub struct History<T, S, L, A>
 where
     T: DiscrRep<S, L> + Clone,
     A: Clone,
 {
     pub trajectory: Vec<SAR<T, S, L, A>>,
+    /// Step at which the trajectory starts, later than zero for agents born during the run.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub start: usize,
     agent_state_items: PhantomData<S>,
     agent_state_item_levels: PhantomData<L>,
 }
@@ -27,6 +26,9 @@ where
     pub state: T,
     pub action: A,
     pub reward: Reward,
+    /// Whether the transition is terminal, ending the trajectory with the death of the agent.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub terminal: bool,
     agent_state_items: PhantomData<S>,
     agent_state_item_levels: PhantomData<L>,
 }
@@ -37,8 +39,13 @@ where
     A: Clone,
 {
     pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+    /// Constructs an empty history of an agent first stepping at the given step.
+    pub fn starting_at(start: usize) -> Self {
         Self {
             trajectory: Vec::new(),
+            start,
             agent_state_items: PhantomData,
             agent_state_item_levels: PhantomData,
         }
@@ -60,6 +67,20 @@ where
     pub fn len(&self) -> usize {
         self.trajectory.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.trajectory.is_empty()
+    }
+    /// Gets the length of the trajectory if it has ended with a terminal transition.
+    pub fn end(&self) -> Option<usize> {
+        self.trajectory
+            .last()
+            .filter(|sar| sar.terminal)
+            .map(|_| self.trajectory.len())
+    }
+}
+
+fn is_zero(start: &usize) -> bool {
+    *start == 0
 }
 
 impl<T, S, L, A> SAR<T, S, L, A>
@@ -72,33 +93,41 @@ where
             state,
             action,
             reward,
+            terminal: false,
             agent_state_items: PhantomData,
             agent_state_item_levels: PhantomData,
         }
     }
 
-    pub fn representation(&self) -> QKey<S, L, A> {
-        QKey(self.state.representation(), self.action.clone())
+    /// Sets whether the transition is terminal.
+    pub fn with_terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    pub fn representation(&self, config: &Config) -> QKey<S, L, A> {
+        QKey(self.state.representation(config), self.action.clone())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::action::Action;
+    use crate::model::agent_state::{AgentState, AgentStateItems, InvLevel};
 
     fn get_test_history() -> History<AgentState, AgentStateItems, InvLevel, Action> {
         History {
             trajectory: vec![SAR::new(
                 AgentState {
-                    food: 0,
-                    water: 0,
-                    min_steps_to_food: None,
-                    min_steps_to_water: None,
+                    inventory: vec![0, 0],
+                    min_steps_to_resources: vec![None, None],
                     min_steps_to_trader: None, // last_action: None,
                 },
                 Action::Stationary,
                 Reward { val: -1 },
             )],
+            start: 0,
             agent_state_items: PhantomData,
             agent_state_item_levels: PhantomData,
         }
@@ -109,10 +138,8 @@ mod tests {
         let mut history = get_test_history();
         let sar = SAR::new(
             AgentState {
-                food: 0,
-                water: 0,
-                min_steps_to_food: None,
-                min_steps_to_water: None,
+                inventory: vec![0, 0],
+                min_steps_to_resources: vec![None, None],
                 min_steps_to_trader: None, // last_action: None,
             },
             Action::Stationary,
@@ -120,10 +147,8 @@ mod tests {
         );
         let sar2 = SAR::new(
             AgentState {
-                food: 0,
-                water: 0,
-                min_steps_to_food: None,
-                min_steps_to_water: None,
+                inventory: vec![0, 0],
+                min_steps_to_resources: vec![None, None],
                 min_steps_to_trader: None, // last_action: None,
             },
             Action::Stationary,
@@ -136,6 +161,11 @@ mod tests {
         // assert!(matches!(history.trajectory.last().unwrap(), sar)));
         assert_eq!(history.trajectory.last().unwrap(), &sar);
         assert_ne!(history.trajectory.last().unwrap(), &sar2);
+
+        // A terminal transition ends the trajectory
+        assert_eq!(history.end(), None);
+        history.push(sar2.with_terminal(true));
+        assert_eq!(history.end(), Some(3));
     }
 
     // #[test]
@@ -0,0 +1,418 @@
+//! Procedural generators of resource maps.
+use super::environment::Resource;
+use crate::config::{Config, MapGeneratorConfig, ResourceConfig, WorldConfig};
+use krabmaga::engine::location::Int2D;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+/// Generates the locations of the configured resources on a board with the dimensions of the
+/// world config.
+pub trait MapGenerator {
+    fn generate(
+        &self,
+        world: &WorldConfig,
+        resources: &[ResourceConfig],
+        rng: &mut StdRng,
+    ) -> BTreeMap<Resource, Vec<Int2D>>;
+}
+
+/// Gets the generator selected in the world config, or `None` if resources are scattered
+/// independently over patches as the board is initialised.
+pub fn map_generator(config: &MapGeneratorConfig) -> Option<Box<dyn MapGenerator>> {
+    match *config {
+        MapGeneratorConfig::Scatter => None,
+        MapGeneratorConfig::River {
+            RIVER_WIDTH,
+            MEANDER,
+            FOOD_BLOCK,
+            ref RIVER_RESOURCE,
+            ref BLOCK_RESOURCE,
+        } => Some(Box::new(River {
+            width: RIVER_WIDTH,
+            meander: MEANDER,
+            food_block: FOOD_BLOCK,
+            river_resource: RIVER_RESOURCE.to_owned(),
+            block_resource: BLOCK_RESOURCE.to_owned(),
+        })),
+        MapGeneratorConfig::Clusters { N_CLUSTERS, SPREAD } => Some(Box::new(Clusters {
+            n_clusters: N_CLUSTERS,
+            spread: SPREAD,
+        })),
+        MapGeneratorConfig::Islands { FILL, ITERATIONS } => Some(Box::new(Islands {
+            fill: FILL,
+            iterations: ITERATIONS,
+        })),
+        MapGeneratorConfig::Noise { SCALE } => Some(Box::new(Noise { scale: SCALE })),
+    }
+}
+
+/// Generates resource locations by resource name with the generator selected in the world
+/// config, seeded from `RANDOM_SEED`. Returns `None` if resources are scattered as the board is
+/// initialised.
+pub fn generate_resource_locations(config: &Config) -> Option<BTreeMap<String, Vec<Int2D>>> {
+    let world = &config.world;
+    let generator = map_generator(&world.MAP_GENERATOR)?;
+    let mut rng = StdRng::seed_from_u64(world.RANDOM_SEED);
+    let resource_locations = generator.generate(world, &config.resources, &mut rng);
+    Some(
+        resource_locations
+            .into_iter()
+            .map(|(resource, locs)| (resource.name(&config.resources).to_string(), locs))
+            .collect(),
+    )
+}
+
+/// Resources placed on the patches of a board, with at most one resource per patch.
+struct Placement {
+    width: i32,
+    height: i32,
+    patches: BTreeMap<(i32, i32), Resource>,
+}
+
+impl Placement {
+    fn new(world: &WorldConfig) -> Self {
+        Placement {
+            width: world.WIDTH.into(),
+            height: world.HEIGHT.into(),
+            patches: BTreeMap::new(),
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y)
+    }
+
+    /// Places a resource on a patch if on the board and free, returning whether it was placed.
+    fn place(&mut self, x: i32, y: i32, resource: Resource) -> bool {
+        if !self.contains(x, y) || self.patches.contains_key(&(x, y)) {
+            return false;
+        }
+        self.patches.insert((x, y), resource);
+        true
+    }
+
+    /// Number of patches a resource with the given abundance covers.
+    fn n_patches(&self, abundance: f32) -> usize {
+        (abundance * (self.width * self.height) as f32).round() as usize
+    }
+
+    fn into_resource_locations(
+        self,
+        resources: &[ResourceConfig],
+    ) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut resource_locations: BTreeMap<Resource, Vec<Int2D>> = Resource::all(resources)
+            .map(|resource| (resource, Vec::new()))
+            .collect();
+        for ((x, y), resource) in self.patches {
+            resource_locations
+                .get_mut(&resource)
+                .expect("initialised for all resources")
+                .push(Int2D { x, y });
+        }
+        resource_locations
+    }
+}
+
+/// A river of one resource meandering from south to north through the middle of the board, and
+/// a square block of another resource in the south-west corner. Resources not configured are
+/// left out.
+pub struct River {
+    pub width: u16,
+    pub meander: u16,
+    pub food_block: u16,
+    pub river_resource: String,
+    pub block_resource: String,
+}
+
+impl MapGenerator for River {
+    fn generate(
+        &self,
+        world: &WorldConfig,
+        resources: &[ResourceConfig],
+        rng: &mut StdRng,
+    ) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut placement = Placement::new(world);
+        if let Some(resource) = Resource::named(resources, &self.block_resource) {
+            for x in 1..=i32::from(self.food_block) {
+                for y in 1..=i32::from(self.food_block) {
+                    placement.place(x, y, resource);
+                }
+            }
+        }
+        let river_resource = Resource::named(resources, &self.river_resource);
+
+        // Keep the river on the board as it meanders
+        let width = i32::from(self.width).min(placement.width);
+        let meander = i32::from(self.meander);
+        let start = (placement.width - width) / 2;
+        let (min_offset, max_offset) = (-start, placement.width - width - start);
+        let mut offset = 0;
+        for y in 0..placement.height {
+            offset = (offset + rng.gen_range(-meander..=meander)).clamp(min_offset, max_offset);
+            for x in (start + offset)..(start + offset + width) {
+                if let Some(resource) = river_resource {
+                    placement.place(x, y, resource);
+                }
+            }
+        }
+        placement.into_resource_locations(resources)
+    }
+}
+
+/// Resources in Gaussian clusters around random centres, with numbers of patches given by their
+/// abundances.
+pub struct Clusters {
+    pub n_clusters: u32,
+    pub spread: f32,
+}
+
+/// Maximum number of draws per patch placed, beyond which clusters are left incomplete.
+const MAX_DRAWS_PER_PATCH: usize = 100;
+
+impl MapGenerator for Clusters {
+    fn generate(
+        &self,
+        world: &WorldConfig,
+        resources: &[ResourceConfig],
+        rng: &mut StdRng,
+    ) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut placement = Placement::new(world);
+        for resource in Resource::all(resources) {
+            let centres: Vec<(f32, f32)> = (0..self.n_clusters)
+                .map(|_| {
+                    (
+                        rng.gen_range(0.0..placement.width as f32),
+                        rng.gen_range(0.0..placement.height as f32),
+                    )
+                })
+                .collect();
+            if centres.is_empty() {
+                continue;
+            }
+            let n_patches = placement.n_patches(resource.config(resources).ABUNDANCE);
+            let mut placed = 0;
+            for _ in 0..n_patches * MAX_DRAWS_PER_PATCH {
+                if placed == n_patches {
+                    break;
+                }
+                let (cx, cy) = centres[rng.gen_range(0..centres.len())];
+                // Box-Muller transform of two uniform samples to a standard normal sample
+                let r = (-2.0 * (1.0 - rng.gen::<f32>()).ln()).sqrt();
+                let theta = 2.0 * PI * rng.gen::<f32>();
+                let x = (cx + self.spread * r * theta.cos()).floor() as i32;
+                let y = (cy + self.spread * r * theta.sin()).floor() as i32;
+                if placement.place(x, y, resource) {
+                    placed += 1;
+                }
+            }
+        }
+        placement.into_resource_locations(resources)
+    }
+}
+
+/// Islands of each resource grown by a cellular automaton from randomly filled patches.
+pub struct Islands {
+    pub fill: f32,
+    pub iterations: u32,
+}
+
+impl MapGenerator for Islands {
+    fn generate(
+        &self,
+        world: &WorldConfig,
+        resources: &[ResourceConfig],
+        rng: &mut StdRng,
+    ) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut placement = Placement::new(world);
+        let (width, height) = (placement.width, placement.height);
+        for resource in Resource::all(resources) {
+            let mut alive: Vec<Vec<bool>> = (0..width)
+                .map(|_| (0..height).map(|_| rng.gen::<f32>() < self.fill).collect())
+                .collect();
+            // A patch is alive in the next iteration if at least five patches of its 3x3
+            // neighbourhood (including itself) are alive, with patches off the board dead.
+            for _ in 0..self.iterations {
+                alive = (0..width)
+                    .map(|x| {
+                        (0..height)
+                            .map(|y| {
+                                let neighbours = (x - 1..=x + 1)
+                                    .flat_map(|nx| (y - 1..=y + 1).map(move |ny| (nx, ny)))
+                                    .filter(|&(nx, ny)| {
+                                        placement.contains(nx, ny)
+                                            && alive[nx as usize][ny as usize]
+                                    })
+                                    .count();
+                                neighbours >= 5
+                            })
+                            .collect()
+                    })
+                    .collect();
+            }
+            for x in 0..width {
+                for y in 0..height {
+                    if alive[x as usize][y as usize] {
+                        placement.place(x, y, resource);
+                    }
+                }
+            }
+        }
+        placement.into_resource_locations(resources)
+    }
+}
+
+/// Resources where value noise fields are highest, with numbers of patches given by their
+/// abundances.
+pub struct Noise {
+    pub scale: u16,
+}
+
+impl Noise {
+    /// Samples a value noise field over the board, interpolating random values on a lattice with
+    /// the given spacing.
+    fn field(&self, width: i32, height: i32, rng: &mut StdRng) -> Vec<((i32, i32), f32)> {
+        let scale = i32::from(self.scale.max(1));
+        let lattice: Vec<Vec<f32>> = (0..=width / scale + 1)
+            .map(|_| (0..=height / scale + 1).map(|_| rng.gen()).collect())
+            .collect();
+        let smoothstep = |t: f32| t * t * (3.0 - 2.0 * t);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let (i, j) = ((x / scale) as usize, (y / scale) as usize);
+                let tx = smoothstep((x % scale) as f32 / scale as f32);
+                let ty = smoothstep((y % scale) as f32 / scale as f32);
+                let value = lerp(
+                    lerp(lattice[i][j], lattice[i + 1][j], tx),
+                    lerp(lattice[i][j + 1], lattice[i + 1][j + 1], tx),
+                    ty,
+                );
+                ((x, y), value)
+            })
+            .collect()
+    }
+}
+
+impl MapGenerator for Noise {
+    fn generate(
+        &self,
+        world: &WorldConfig,
+        resources: &[ResourceConfig],
+        rng: &mut StdRng,
+    ) -> BTreeMap<Resource, Vec<Int2D>> {
+        let mut placement = Placement::new(world);
+        for resource in Resource::all(resources) {
+            let mut field = self.field(placement.width, placement.height, rng);
+            field.retain(|(patch, _)| !placement.patches.contains_key(patch));
+            field.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            let n_patches = placement.n_patches(resource.config(resources).ABUNDANCE);
+            for ((x, y), _) in field.into_iter().take(n_patches) {
+                placement.place(x, y, resource);
+            }
+        }
+        placement.into_resource_locations(resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_config;
+
+    /// Gets a config for a 20x20 board with the given generator.
+    fn config(generator: MapGeneratorConfig) -> Config {
+        let mut config = test_config();
+        config.world.WIDTH = 20;
+        config.world.HEIGHT = 20;
+        config.resources[0].ABUNDANCE = 0.1;
+        config.resources[1].ABUNDANCE = 0.15;
+        config.world.MAP_GENERATOR = generator;
+        config
+    }
+
+    #[test]
+    fn test_generators() {
+        let generators = [
+            MapGeneratorConfig::River {
+                RIVER_WIDTH: 5,
+                MEANDER: 2,
+                FOOD_BLOCK: 6,
+                RIVER_RESOURCE: "Water".to_string(),
+                BLOCK_RESOURCE: "Food".to_string(),
+            },
+            MapGeneratorConfig::Clusters {
+                N_CLUSTERS: 3,
+                SPREAD: 2.0,
+            },
+            MapGeneratorConfig::Islands {
+                FILL: 0.45,
+                ITERATIONS: 4,
+            },
+            MapGeneratorConfig::Noise { SCALE: 5 },
+        ];
+        for generator in generators {
+            let config = config(generator);
+            let resource_locations = generate_resource_locations(&config).unwrap();
+            let mut patches: Vec<(i32, i32)> = resource_locations
+                .values()
+                .flatten()
+                .map(|loc| (loc.x, loc.y))
+                .collect();
+            assert!(patches
+                .iter()
+                .all(|&(x, y)| (0..20).contains(&x) && (0..20).contains(&y)));
+            let n_patches = patches.len();
+            patches.sort();
+            patches.dedup();
+            assert_eq!(patches.len(), n_patches);
+            assert!(resource_locations.values().all(|v| !v.is_empty()));
+
+            // Same map for the same seed, and a different map for a different seed
+            let same = generate_resource_locations(&config).unwrap();
+            assert!(same == resource_locations);
+            let mut other_config = config.clone();
+            other_config.world.RANDOM_SEED += 1;
+            assert!(generate_resource_locations(&other_config).unwrap() != resource_locations);
+        }
+        assert!(generate_resource_locations(&config(MapGeneratorConfig::Scatter)).is_none());
+    }
+
+    #[test]
+    fn test_river() {
+        let config = config(MapGeneratorConfig::River {
+            RIVER_WIDTH: 5,
+            MEANDER: 2,
+            FOOD_BLOCK: 6,
+            RIVER_RESOURCE: "Water".to_string(),
+            BLOCK_RESOURCE: "Food".to_string(),
+        });
+        let resource_locations = generate_resource_locations(&config).unwrap();
+        // Five patches of water in every row, less any in the food block
+        assert_eq!(resource_locations["Food"].len(), 36);
+        for y in 7..20 {
+            let n_water = resource_locations["Water"]
+                .iter()
+                .filter(|loc| loc.y == y)
+                .count();
+            assert_eq!(n_water, 5);
+        }
+    }
+
+    #[test]
+    fn test_abundances() {
+        for generator in [
+            MapGeneratorConfig::Clusters {
+                N_CLUSTERS: 3,
+                SPREAD: 2.0,
+            },
+            MapGeneratorConfig::Noise { SCALE: 5 },
+        ] {
+            let resource_locations = generate_resource_locations(&config(generator)).unwrap();
+            assert_eq!(resource_locations["Food"].len(), 40);
+            assert_eq!(resource_locations["Water"].len(), 60);
+        }
+    }
+}
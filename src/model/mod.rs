@@ -1,26 +1,58 @@
-use std::sync::Once;
-
-use crate::config::CLAMMS_CONFIG;
-
 pub mod action;
 pub mod agent_api;
+pub mod agent_registry;
 pub mod agent_state;
 pub mod board;
 pub mod environment;
 pub mod forager;
+pub mod grid_map;
 pub mod history;
 pub mod inventory;
+pub mod map_generator;
+pub mod placement;
 pub mod policy;
 pub mod q_table;
 pub mod reward;
 pub mod routing;
 pub mod serde_utils;
+pub mod stock;
 pub mod tabular_rl;
+pub mod timeline;
 pub mod trader;
 
-static INIT: Once = Once::new();
-pub fn init() {
-    INIT.call_once(|| {
-        std::env::set_var(CLAMMS_CONFIG, std::env::var("CLAMMS_CONFIG_TEST").unwrap());
-    });
+/// Loads the test config from the file at `CLAMMS_CONFIG_TEST`.
+#[cfg(test)]
+pub fn test_config() -> crate::config::Config {
+    let path = std::env::var("CLAMMS_CONFIG_TEST").unwrap();
+    crate::config::load_config_with_env(std::path::Path::new(&path), []).unwrap()
+}
+
+/// Temporary directory unique to one test run, removed when dropped.
+#[cfg(test)]
+pub struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    /// Creates an empty directory named after `name`, the process id and the current time.
+    pub fn new(name: &str) -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path =
+            std::env::temp_dir().join(format!("clamms_test_{name}_{}_{nanos}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
 }
@@ -0,0 +1,196 @@
+//! Placement of agents on the board at the start of a run.
+use super::environment::Resource;
+use super::routing::PathDistances;
+use crate::config::{PlacementConfig, ResourceConfig, Topology};
+use krabmaga::engine::location::Int2D;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{BTreeMap, HashSet};
+use std::f32::consts::PI;
+
+/// Locations of a board available to place agents on.
+pub struct Sites<'a> {
+    pub dim: (u16, u16),
+    pub topology: Topology,
+    /// Locations agents can enter.
+    pub open: &'a [Int2D],
+    /// Spawn points of the grid map, if any.
+    pub spawn_points: &'a [Int2D],
+    /// Spawn points of the grid map in each named group.
+    pub spawn_groups: &'a BTreeMap<String, Vec<Int2D>>,
+    /// Path distances to the nearest patch of each resource.
+    pub resource_distances: &'a BTreeMap<Resource, PathDistances>,
+    /// Configured resources, named by placements.
+    pub resources: &'a [ResourceConfig],
+}
+
+impl Sites<'_> {
+    /// Chooses a location agents can enter uniformly at random, or any location on the board if
+    /// there is none.
+    fn random(&self, rng: &mut StdRng) -> Int2D {
+        self.open.choose(rng).copied().unwrap_or_else(|| Int2D {
+            x: rng.gen_range(0..i32::from(self.dim.0)),
+            y: rng.gen_range(0..i32::from(self.dim.1)),
+        })
+    }
+
+    /// Chooses uniformly at random from candidate locations, or from the open locations if there
+    /// are no candidates.
+    fn choose(&self, candidates: &[Int2D], rng: &mut StdRng) -> Int2D {
+        match candidates.choose(rng) {
+            Some(pos) => *pos,
+            None => self.random(rng),
+        }
+    }
+
+    /// Gets a location on the board from a location possibly beyond its edges, wrapping around a
+    /// torus.
+    fn on_board(&self, x: i32, y: i32) -> Option<Int2D> {
+        let (width, height) = (i32::from(self.dim.0), i32::from(self.dim.1));
+        match self.topology {
+            Topology::Bounded => {
+                ((0..width).contains(&x) && (0..height).contains(&y)).then_some(Int2D { x, y })
+            }
+            Topology::Torus => Some(Int2D {
+                x: x.rem_euclid(width),
+                y: y.rem_euclid(height),
+            }),
+        }
+    }
+}
+
+/// Maximum number of draws per agent placed in a cluster, beyond which the agent is placed at the
+/// centre of the cluster.
+const MAX_DRAWS_PER_AGENT: usize = 100;
+
+/// Places a number of agents on the board with a placement strategy, returning their locations.
+pub fn place_agents(
+    placement: &PlacementConfig,
+    n_agents: usize,
+    sites: &Sites,
+    rng: &mut StdRng,
+) -> Vec<Int2D> {
+    match placement {
+        PlacementConfig::Random => {
+            let candidates = if sites.spawn_points.is_empty() {
+                sites.open
+            } else {
+                sites.spawn_points
+            };
+            (0..n_agents)
+                .map(|_| sites.choose(candidates, rng))
+                .collect()
+        }
+        PlacementConfig::SpawnPoints { GROUP } => {
+            let candidates = match GROUP {
+                Some(group) => sites.spawn_groups.get(group).map_or(&[][..], Vec::as_slice),
+                None => sites.spawn_points,
+            };
+            (0..n_agents)
+                .map(|_| sites.choose(candidates, rng))
+                .collect()
+        }
+        PlacementConfig::Clusters { N_CLUSTERS, SPREAD } => {
+            let open: HashSet<Int2D> = sites.open.iter().copied().collect();
+            let centres: Vec<Int2D> = (0..*N_CLUSTERS).map(|_| sites.random(rng)).collect();
+            (0..n_agents)
+                .map(|_| {
+                    let Some(centre) = centres.choose(rng).copied() else {
+                        return sites.random(rng);
+                    };
+                    for _ in 0..MAX_DRAWS_PER_AGENT {
+                        // Box-Muller transform of two uniform samples to a standard normal sample
+                        let r = (-2.0 * (1.0 - rng.gen::<f32>()).ln()).sqrt();
+                        let theta = 2.0 * PI * rng.gen::<f32>();
+                        let x = centre.x + (SPREAD * r * theta.cos()).round() as i32;
+                        let y = centre.y + (SPREAD * r * theta.sin()).round() as i32;
+                        if let Some(pos) = sites.on_board(x, y) {
+                            if open.contains(&pos) {
+                                return pos;
+                            }
+                        }
+                    }
+                    centre
+                })
+                .collect()
+        }
+        PlacementConfig::NearResource { RESOURCE, RADIUS } => {
+            let distances = Resource::named(sites.resources, RESOURCE)
+                .and_then(|resource| sites.resource_distances.get(&resource));
+            let candidates: Vec<Int2D> = match distances {
+                Some(distances) => sites
+                    .open
+                    .iter()
+                    .filter(|pos| distances.get(pos).is_some_and(|steps| steps <= *RADIUS))
+                    .copied()
+                    .collect(),
+                None => Vec::new(),
+            };
+            (0..n_agents)
+                .map(|_| sites.choose(&candidates, rng))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_place_agents() {
+        // Open board apart from the west column, with food in the north-east corner
+        let dim = (8, 5);
+        let open: Vec<Int2D> = (1..8)
+            .flat_map(|x| (0..5).map(move |y| Int2D { x, y }))
+            .collect();
+        let food = Int2D { x: 7, y: 4 };
+        let resource_distances = BTreeMap::from([(
+            Resource(0),
+            PathDistances::from_sources(dim, Topology::Bounded, &[food], |pos| open.contains(pos)),
+        )]);
+        let spawn_points = vec![Int2D { x: 1, y: 1 }, Int2D { x: 6, y: 1 }];
+        let spawn_groups = BTreeMap::from([("east".to_string(), vec![Int2D { x: 6, y: 1 }])]);
+        let resources = crate::model::test_config().resources;
+        let sites = Sites {
+            dim,
+            topology: Topology::Bounded,
+            open: &open,
+            spawn_points: &spawn_points,
+            spawn_groups: &spawn_groups,
+            resource_distances: &resource_distances,
+            resources: &resources,
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut place = |placement| place_agents(&placement, 50, &sites, &mut rng);
+
+        assert!(place(PlacementConfig::Random)
+            .iter()
+            .all(|pos| spawn_points.contains(pos)));
+        let east = place(PlacementConfig::SpawnPoints {
+            GROUP: Some("east".to_string()),
+        });
+        assert!(east.iter().all(|pos| *pos == Int2D { x: 6, y: 1 }));
+        let clustered = place(PlacementConfig::Clusters {
+            N_CLUSTERS: 1,
+            SPREAD: 1.0,
+        });
+        assert_eq!(clustered.len(), 50);
+        assert!(clustered.iter().all(|pos| open.contains(pos)));
+        let near_food = place(PlacementConfig::NearResource {
+            RESOURCE: "Food".to_string(),
+            RADIUS: 2,
+        });
+        assert!(near_food
+            .iter()
+            .all(|pos| Topology::Bounded.step_distance(pos, &food, dim) <= 2));
+
+        // Falls back to the open locations without any eligible locations
+        let unknown = place(PlacementConfig::SpawnPoints {
+            GROUP: Some("west".to_string()),
+        });
+        assert!(unknown.iter().all(|pos| open.contains(pos)));
+    }
+}
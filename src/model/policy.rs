@@ -1,7 +1,69 @@
-use krabmaga::engine::state::State;
-
+use super::agent_state::{AgentStateItems, DiscrRep, InvLevel};
+use super::tabular_rl::SARSAModel;
 use super::{action::Action, agent_state::AgentState};
+use crate::config::Config;
+use krabmaga::engine::state::State;
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
 
 pub trait Policy {
-    fn chose_action(&self, state: &mut dyn State, agent_state: &AgentState) -> Action;
+    /// Chooses an action given the observed state, drawing from the agent's own random stream.
+    fn chose_action(&self, state: &dyn State, agent_state: &AgentState, rng: &mut StdRng)
+        -> Action;
+}
+
+/// Rule for selecting the action of an agent from its observed state, used by the board for
+/// every agent.
+pub trait ActionPolicy: Send + Sync {
+    fn select_action(
+        &self,
+        id: u32,
+        agent_state: &AgentState,
+        model: &SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+        config: &Config,
+        rng: &mut StdRng,
+    ) -> Action;
+}
+
+/// Epsilon-greedy policy over the Q-values of the model, exploring with probability `EPSILON`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EpsilonGreedy;
+
+impl ActionPolicy for EpsilonGreedy {
+    fn select_action(
+        &self,
+        id: u32,
+        agent_state: &AgentState,
+        model: &SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+        config: &Config,
+        rng: &mut StdRng,
+    ) -> Action {
+        model.sample_action_by_id(
+            id,
+            &agent_state.representation(config),
+            &Action::all(&config.resources),
+            config.rl.EPSILON,
+            rng,
+        )
+    }
+}
+
+/// Policy selecting actions uniformly at random, ignoring the model.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UniformRandom;
+
+impl ActionPolicy for UniformRandom {
+    fn select_action(
+        &self,
+        _id: u32,
+        _agent_state: &AgentState,
+        _model: &SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+        config: &Config,
+        rng: &mut StdRng,
+    ) -> Action {
+        Action::all(&config.resources)
+            .into_iter()
+            .choose(rng)
+            .expect("there is at least one action")
+    }
 }
@@ -1,26 +1,17 @@
 use super::serde_utils;
-use crate::config::core_config;
 use itertools::Itertools;
 use krabmaga::HashMap;
 use rand::{rngs::StdRng, Rng};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use strum::IntoEnumIterator;
-use tuple_conv::RepeatedTuple;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct QTable<S, L, A>
 where
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + IntoEnumIterator
-        + Serialize
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
     // To serialize with a struct as key, custom serialization with a `serde_utils` module can be [used](https://stackoverflow.com/questions/51276896/how-do-i-use-serde-to-serialize-a-hashmap-with-structs-as-keys-to-json)
     #[serde(with = "serde_utils")]
@@ -40,15 +31,14 @@ impl<S, L, A> QTable<S, L, A>
 where
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + Serialize
-        + IntoEnumIterator
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
-    pub fn new(state_items: Vec<S>, state_levels: Vec<L>, actions: Vec<A>) -> Self {
+    pub fn new(
+        state_items: Vec<S>,
+        state_levels: Vec<L>,
+        actions: Vec<A>,
+        init_q_value: f32,
+    ) -> Self {
         let mut q_tbl = HashMap::new();
         let mut combs_for_all_state_items = Vec::new();
         for s in state_items {
@@ -74,16 +64,12 @@ where
         //     .into_iter()
         //     .collect_vec();
 
-        let q = combs
-            .into_iter()
-            .cartesian_product(actions)
-            .into_iter()
-            .collect_vec();
+        let q = combs.into_iter().cartesian_product(actions).collect_vec();
 
         for el in q {
             // println!("{:?}", el);
             let q_key = QKey(el.0, el.1);
-            q_tbl.insert(q_key, core_config().rl.INIT_Q_VALUES);
+            q_tbl.insert(q_key, init_q_value);
         }
 
         QTable { tab: q_tbl }
@@ -96,14 +82,20 @@ where
         &self.tab
     }
 
-    pub fn sample_action(&self, state: &Vec<(S, L)>, rng: &mut StdRng) -> (A, f32) {
-        let mut optimal_a: A = self.pick_rnd(rng);
+    pub fn sample_action(
+        &self,
+        state: &Vec<(S, L)>,
+        actions: &[A],
+        epsilon: f32,
+        rng: &mut StdRng,
+    ) -> (A, f32) {
+        let mut optimal_a: A = self.pick_rnd(actions, rng);
         let mut q_optimal = self
             .get_tab()
             .get(&QKey(state.to_owned(), optimal_a.clone()))
             .unwrap();
 
-        for a in A::iter() {
+        for a in actions.iter().cloned() {
             let q_a = self
                 .get_tab()
                 .get(&QKey(state.to_owned(), optimal_a.clone()))
@@ -118,26 +110,19 @@ where
             }
         }
         let r: f32 = rng.gen();
-        if r < core_config().rl.EPSILON {
-            optimal_a = self.pick_rnd(rng);
+        if r < epsilon {
+            optimal_a = self.pick_rnd(actions, rng);
         }
         (optimal_a, *q_optimal)
     }
-    fn pick_rnd(&self, rng: &mut StdRng) -> A {
+
+    /// Picks uniformly at random from the actions other than the last, which is taken to be
+    /// staying stationary.
+    fn pick_rnd(&self, actions: &[A], rng: &mut StdRng) -> A {
         let r: f32 = rng.gen();
-        let mut a_iter = A::iter();
-        let a: A;
-        if r < 0.3 {
-            a = a_iter.next().expect("at least one action in enum");
-        } else if r < 0.6 {
-            a_iter.next();
-            a = a_iter.next().unwrap();
-        } else {
-            a_iter.next();
-            a_iter.next();
-            a = a_iter.next().unwrap();
-        }
-        a
+        let n_moves = actions.len().saturating_sub(1).max(1);
+        let index = ((r * n_moves as f32) as usize).min(n_moves - 1);
+        actions.get(index).expect("at least one action").clone()
     }
 }
 
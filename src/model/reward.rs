@@ -11,9 +11,7 @@ impl Reward {
     }
     /// Recieve a negative reward if resource counts are 0 or below negative reward is linearly
     /// proportional to count
-    pub fn from_inv_count_linear(food_count: i32, water_count: i32) -> Self {
-        let food_reward = 0.min(food_count);
-        let water_reward = 0.min(water_count);
-        Reward::new(food_reward + water_reward)
+    pub fn from_inv_count_linear(counts: &[i32]) -> Self {
+        Reward::new(counts.iter().map(|count| 0.min(*count)).sum())
     }
 }
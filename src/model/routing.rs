@@ -1,70 +1,47 @@
-use std::borrow::Borrow;
-
 use super::agent_api::AgentAPI;
-use super::board::{Board, Patch};
+use super::board::Board;
 use super::environment::Resource;
-use super::trader::Trader;
+use crate::config::Topology;
 use crate::model::forager::Direction;
-use krabmaga::cfg_if::cfg_if;
-use krabmaga::engine::fields::dense_object_grid_2d::DenseGrid2D;
 use krabmaga::engine::{location::Int2D, state::State};
 use rand::distributions::{Bernoulli, Distribution};
 use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use std::collections::VecDeque;
 // use krabmaga::utils;
 
 pub trait Router: Position {
-    /// Gets an appropriate direction of movement towards a specified resource.
+    /// Gets an appropriate direction of movement towards the nearest patch of a specified
+    /// resource, reading the path distances to resources maintained by the board.
     fn try_move_towards_resource(
         &self,
         resource: &Resource,
-        state: &mut dyn State,
+        state: &dyn State,
         horizon: Option<u32>,
+        rng: &mut StdRng,
     ) -> Option<Direction> {
-        self.try_move_towards(&self.find_nearest_resource(resource, state, horizon), state)
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        let distances = board
+            .resource_distances
+            .get(resource)
+            .expect("HashMap initialised for all resource types");
+        move_along_within(&self.get_position(), distances, horizon, rng)
     }
 
-    /// Gets an appropriate direction of movement towards the nearest agent.
+    /// Gets an appropriate direction of movement towards the nearest other agent.
     fn try_move_towards_agent(
         &self,
-        state: &mut dyn State,
+        state: &dyn State,
         horizon: Option<u32>,
+        rng: &mut StdRng,
     ) -> Option<Direction> {
-        self.try_move_towards(&self.find_nearest_trader(state, horizon), state)
-    }
-
-    fn try_move_towards(&self, target: &Option<Int2D>, state: &mut dyn State) -> Option<Direction> {
-        // Downcast to get access to rng
-        let state = state.as_any_mut().downcast_mut::<Board>().unwrap();
-        match target {
-            None => state.rng.gen(),
-            Some(pos) => {
-                if pos.eq(&self.get_position()) {
-                    return None;
-                }
-                move_towards(&self.get_position(), &pos, &mut state.rng)
-            }
-        }
-    }
-
-    /// Finds the nearest from a vector of coordinates.
-    fn find_nearest(&self, targets: &Vec<Int2D>, horizon: Option<u32>) -> Option<Int2D> {
-        let pos = &self.get_position();
-        if targets.is_empty() {
-            return None;
-        }
-        let nearest = targets
-            .iter()
-            .min_by_key(|x| step_distance(x, pos))
-            .expect("Non-empty targets vector");
-        if let Some(h) = horizon {
-            if step_distance(nearest, pos) <= h {
-                return Some(nearest.to_owned());
-            } else {
-                return None;
-            }
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        let pos = self.get_position();
+        match nearest_within(board.trader_distances.nearest_other(&pos), horizon) {
+            None => rng.gen(),
+            Some(_) => move_along_to_other(&pos, &board.trader_distances, rng),
         }
-        Some(nearest.to_owned())
     }
 
     /// Finds the coordinates of the nearest specified resource.
@@ -74,11 +51,20 @@ pub trait Router: Position {
         state: &dyn State,
         horizon: Option<u32>, // horizon: Option<f32>,
     ) -> Option<Int2D> {
-        self.find_nearest(&get_resource_locations(resource, state), horizon)
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        let distances = board
+            .resource_distances
+            .get(resource)
+            .expect("HashMap initialised for all resource types");
+        nearest_within(distances.nearest(&self.get_position()), horizon)
     }
 
     fn find_nearest_trader(&self, state: &mut dyn State, horizon: Option<u32>) -> Option<Int2D> {
-        self.find_nearest(&get_trader_locations(state), horizon)
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        nearest_within(
+            board.trader_distances.nearest_other(&self.get_position()),
+            horizon,
+        )
     }
 }
 
@@ -114,8 +100,8 @@ pub fn get_resource_locations(resource: &Resource, state: &dyn State) -> Vec<Int
         .to_owned()
 }
 
-pub fn get_trader_locations(state: &mut dyn State) -> Vec<Int2D> {
-    let board = state.as_any_mut().downcast_mut::<Board>().unwrap();
+pub fn get_trader_locations(state: &dyn State) -> Vec<Int2D> {
+    let board = state.as_any().downcast_ref::<Board>().unwrap();
     board
         .get_agents()
         .iter()
@@ -125,142 +111,471 @@ pub fn get_trader_locations(state: &mut dyn State) -> Vec<Int2D> {
 
 pub trait Position {
     fn get_position(&self) -> Int2D;
-    fn min_steps_to(&self, targets: Vec<Int2D>) -> Option<u32> {
-        targets
+}
+
+/// Numbers of steps along shortest paths from every location to the nearest of a set of sources,
+/// moving north, east, south or west onto locations that can be entered, with the nearest source.
+/// The two nearest sources at distinct locations are kept, so that a source can find the nearest
+/// of the others.
+#[derive(Clone, PartialEq)]
+pub struct PathDistances {
+    dim: (u16, u16),
+    topology: Topology,
+    sources: Vec<Int2D>,
+    /// Whether each location can be entered.
+    enterable: Vec<bool>,
+    /// Number of sources at each location.
+    counts: Vec<u32>,
+    /// Numbers of steps to the two nearest sources at distinct locations from each location,
+    /// nearest first, and the indices of the sources.
+    nearest: Vec<[Option<(u32, usize)>; 2]>,
+}
+
+impl PathDistances {
+    /// Computes the path distances to sources on a board of the given dimensions and topology by
+    /// multi-source breadth first search, given whether each location can be entered. Of equally
+    /// near sources, the first is nearest.
+    pub fn from_sources<F: Fn(&Int2D) -> bool>(
+        dim: (u16, u16),
+        topology: Topology,
+        sources: &[Int2D],
+        can_enter: F,
+    ) -> Self {
+        let (width, height) = (i32::from(dim.0), i32::from(dim.1));
+        let enterable = (0..width)
+            .flat_map(|x| (0..height).map(move |y| Int2D { x, y }))
+            .map(|pos| can_enter(&pos))
+            .collect();
+        let mut distances = PathDistances {
+            dim,
+            topology,
+            sources: sources.to_vec(),
+            enterable,
+            counts: vec![0; usize::from(dim.0) * usize::from(dim.1)],
+            nearest: vec![[None; 2]; usize::from(dim.0) * usize::from(dim.1)],
+        };
+        let mut queue = VecDeque::new();
+        for (i, source) in sources.iter().enumerate() {
+            if let Some(index) = distances.index(source) {
+                distances.counts[index] += 1;
+                if distances.counts[index] == 1 {
+                    distances.nearest[index][0] = Some((0, i));
+                    queue.push_back((index, 0, i));
+                }
+            }
+        }
+        // Each location is one step further than the nearest enterable neighbour, so only
+        // enterable locations pass on their distances. Locations are visited in order of
+        // distance, so the sources of neighbours are final when passed on, unless displaced by
+        // an equally near source that comes first.
+        while let Some((index, steps, source)) = queue.pop_front() {
+            if !distances.enterable[index]
+                || !distances.nearest[index].contains(&Some((steps, source)))
+            {
+                continue;
+            }
+            let pos = Int2D {
+                x: index as i32 / height,
+                y: index as i32 % height,
+            };
+            for dir in DIRECTIONS {
+                let Some(next) = distances
+                    .neighbour(&pos, dir)
+                    .and_then(|next| distances.index(&next))
+                else {
+                    continue;
+                };
+                if distances.offer(next, steps + 1, source) {
+                    queue.push_back((next, steps + 1, source));
+                }
+            }
+        }
+        distances
+    }
+
+    /// Offers a source to a location, keeping the two nearest sources, of equally near sources
+    /// the first. Returns whether the source is kept.
+    fn offer(&mut self, index: usize, steps: u32, source: usize) -> bool {
+        let nearest = &mut self.nearest[index];
+        if nearest.iter().flatten().any(|&(_, kept)| kept == source) {
+            return false;
+        }
+        let nearer = |kept: Option<(u32, usize)>| kept.is_none_or(|kept| (steps, source) < kept);
+        if !nearer(nearest[1]) {
+            return false;
+        }
+        nearest[1] = Some((steps, source));
+        if nearer(nearest[0]) {
+            nearest.swap(0, 1);
+        }
+        true
+    }
+
+    fn index(&self, pos: &Int2D) -> Option<usize> {
+        let (width, height) = (i32::from(self.dim.0), i32::from(self.dim.1));
+        ((0..width).contains(&pos.x) && (0..height).contains(&pos.y))
+            .then(|| (pos.x * height + pos.y) as usize)
+    }
+
+    /// Gets the location one step in a direction from a location, if on the board.
+    pub fn neighbour(&self, pos: &Int2D, dir: Direction) -> Option<Int2D> {
+        self.topology.neighbour(pos, dir, self.dim)
+    }
+
+    /// Whether a location can be entered.
+    pub fn can_enter(&self, pos: &Int2D) -> bool {
+        self.index(pos).is_some_and(|index| self.enterable[index])
+    }
+
+    /// Gets the number of steps from a location to the nearest source, or `None` if no source
+    /// can be reached.
+    pub fn get(&self, pos: &Int2D) -> Option<u32> {
+        self.nearest(pos).map(|(_, steps)| steps)
+    }
+
+    /// Gets the nearest source to a location and the number of steps to it, or `None` if no
+    /// source can be reached.
+    pub fn nearest(&self, pos: &Int2D) -> Option<(Int2D, u32)> {
+        self.index(pos)
+            .and_then(|index| self.nearest[index][0])
+            .map(|(steps, source)| (self.sources[source], steps))
+    }
+
+    /// Gets the nearest source to a location other than one source at the location itself, as
+    /// for a source looking for the others, and the number of steps to it, or `None` if no other
+    /// source can be reached.
+    pub fn nearest_other(&self, pos: &Int2D) -> Option<(Int2D, u32)> {
+        let index = self.index(pos)?;
+        if self.counts[index] > 1 {
+            return Some((*pos, 0));
+        }
+        self.nearest_excluding(pos, pos)
+    }
+
+    /// Gets the nearest source to a location that is not at the excluded location, and the
+    /// number of steps to it.
+    fn nearest_excluding(&self, pos: &Int2D, excluded: &Int2D) -> Option<(Int2D, u32)> {
+        self.nearest[self.index(pos)?]
             .iter()
-            .map(|x| step_distance(x, &self.get_position()))
-            .min()
+            .flatten()
+            .map(|&(steps, source)| (self.sources[source], steps))
+            .find(|(source, _)| source != excluded)
+    }
+}
+
+/// Gets the nearest source within the horizon, if any, given the nearest source and the number of
+/// steps to it.
+fn nearest_within(nearest: Option<(Int2D, u32)>, horizon: Option<u32>) -> Option<Int2D> {
+    let (nearest, steps) = nearest?;
+    if horizon.is_some_and(|h| steps > h) {
+        return None;
+    }
+    Some(nearest)
+}
+
+/// Decides a direction to move nearer to the nearest source within the horizon, or a random
+/// direction if there is none.
+fn move_along_within(
+    pos: &Int2D,
+    distances: &PathDistances,
+    horizon: Option<u32>,
+    rng: &mut StdRng,
+) -> Option<Direction> {
+    match nearest_within(distances.nearest(pos), horizon) {
+        None => rng.gen(),
+        Some(_) => move_along(pos, distances, rng),
     }
 }
 
+/// Directions of movement, horizontal before vertical.
+pub(crate) const DIRECTIONS: [Direction; 4] = [
+    Direction::East,
+    Direction::West,
+    Direction::North,
+    Direction::South,
+];
+
 pub fn coin_flip(rng: &mut StdRng) -> bool {
     let d = Bernoulli::new(0.5).unwrap();
     d.sample(rng)
 }
 
-/// Computes the number of steps to move from a to b.
-pub fn step_distance(a: &Int2D, b: &Int2D) -> u32 {
-    ((a.x - b.x).abs() + (a.y - b.y).abs()).try_into().unwrap()
+impl Topology {
+    /// Gets the location one step in a direction from a location on a board of the given
+    /// dimensions, or `None` if beyond the edge of a bounded board.
+    pub fn neighbour(&self, pos: &Int2D, dir: Direction, dim: (u16, u16)) -> Option<Int2D> {
+        let next = dir.step(pos);
+        let (width, height) = (i32::from(dim.0), i32::from(dim.1));
+        match self {
+            Topology::Bounded => {
+                ((0..width).contains(&next.x) && (0..height).contains(&next.y)).then_some(next)
+            }
+            Topology::Torus => Some(Int2D {
+                x: next.x.rem_euclid(width),
+                y: next.y.rem_euclid(height),
+            }),
+        }
+    }
+
+    /// Gets the absolute displacements between a and b on a board of the given dimensions, the
+    /// shorter way round a torus.
+    fn displacement(&self, a: &Int2D, b: &Int2D, dim: (u16, u16)) -> (i32, i32) {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        match self {
+            Topology::Bounded => (dx, dy),
+            Topology::Torus => (dx.min(i32::from(dim.0) - dx), dy.min(i32::from(dim.1) - dy)),
+        }
+    }
+
+    /// Computes the number of steps to move from a to b, ignoring obstacles.
+    pub fn step_distance(&self, a: &Int2D, b: &Int2D, dim: (u16, u16)) -> u32 {
+        let (dx, dy) = self.displacement(a, b, dim);
+        (dx + dy).try_into().unwrap()
+    }
+
+    /// Computes the straight line distance from a to b.
+    pub fn sight_distance(&self, a: &Int2D, b: &Int2D, dim: (u16, u16)) -> f32 {
+        let (dx, dy) = self.displacement(a, b, dim);
+        f32::sqrt((dx.pow(2) + dy.pow(2)) as f32)
+    }
 }
 
-/// Computes the straight line distance from a to b.
-fn sight_distance(a: &Int2D, b: &Int2D) -> f32 {
-    f32::sqrt(((a.x - b.x).pow(2) + (a.y - b.y).pow(2)) as f32)
+/// Decides a direction to move one step nearer to the nearest source, given the path distances to
+/// the sources, or `None` if at a source or no step is nearer. Where several steps are equally
+/// near, one is chosen at random (by a coin flip between a horizontal and a vertical step).
+pub fn move_along(pos: &Int2D, distances: &PathDistances, rng: &mut StdRng) -> Option<Direction> {
+    move_by(pos, distances, |pos| distances.get(pos), rng)
 }
 
-/// Decides an appropriate direction to move towards a target.
-pub fn move_towards(pos: &Int2D, target: &Int2D, rng: &mut StdRng) -> Option<Direction> {
-    if pos.eq(target) {
+/// Decides a direction for a source to move one step nearer to the nearest other source, as
+/// [`move_along`] does to the nearest source.
+pub fn move_along_to_other(
+    pos: &Int2D,
+    distances: &PathDistances,
+    rng: &mut StdRng,
+) -> Option<Direction> {
+    if distances
+        .nearest_other(pos)
+        .is_some_and(|(_, steps)| steps == 0)
+    {
         return None;
     }
-    if pos.x < target.x {
-        if pos.y == target.y {
-            return Some(Direction::East);
-        }
-        if pos.y < target.y {
-            // flip coin for East or North
-            if coin_flip(rng) {
-                return Some(Direction::East);
-            } else {
-                return Some(Direction::North);
-            }
-        } else {
-            // flip coin for East or South
-            if coin_flip(rng) {
-                return Some(Direction::East);
-            } else {
-                return Some(Direction::South);
-            }
-        }
+    let steps_to_other = |next: &Int2D| {
+        distances
+            .nearest_excluding(next, pos)
+            .map(|(_, steps)| steps)
+    };
+    move_by(pos, distances, steps_to_other, rng)
+}
+
+/// Decides a direction to move one step nearer to a source, given the number of steps to it from
+/// each location.
+fn move_by<F: Fn(&Int2D) -> Option<u32>>(
+    pos: &Int2D,
+    distances: &PathDistances,
+    steps_from: F,
+    rng: &mut StdRng,
+) -> Option<Direction> {
+    let current = steps_from(pos);
+    if current == Some(0) {
+        return None;
     }
-    if pos.x > target.x {
-        if pos.y == target.y {
-            return Some(Direction::West);
-        }
-        if pos.y < target.y {
-            // flip coin for West or North
-            if coin_flip(rng) {
-                return Some(Direction::West);
-            } else {
-                return Some(Direction::North);
-            }
-        } else {
-            // flip coin for West or South
-            if coin_flip(rng) {
-                return Some(Direction::West);
-            } else {
-                return Some(Direction::South);
-            }
-        }
+    let steps: Vec<(Direction, u32)> = DIRECTIONS
+        .into_iter()
+        .filter_map(|dir| Some((dir, distances.neighbour(pos, dir)?)))
+        .filter(|(_, next)| distances.can_enter(next))
+        .filter_map(|(dir, next)| steps_from(&next).map(|steps| (dir, steps)))
+        .collect();
+    let best = steps.iter().map(|(_, steps)| *steps).min()?;
+    if current.is_some_and(|current| best >= current) {
+        return None;
     }
-    if pos.y < target.y {
-        return Some(Direction::North);
+    let candidates: Vec<Direction> = steps
+        .into_iter()
+        .filter(|(_, steps)| *steps == best)
+        .map(|(dir, _)| dir)
+        .collect();
+    match candidates[..] {
+        [dir] => Some(dir),
+        [first, second] => Some(if coin_flip(rng) { first } else { second }),
+        _ => candidates.choose(rng).copied(),
     }
-    Some(Direction::South)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        config::core_config,
-        model::{forager::Forager, trader::Trader},
-    };
-
     use super::*;
-    use krabmaga::engine::fields::dense_object_grid_2d::DenseGrid2D;
     use rand::SeedableRng;
 
     #[test]
     fn test_sight_dist() {
+        let (a, b) = (Int2D { x: 0, y: 0 }, Int2D { x: 4, y: 3 });
+        assert_eq!(Topology::Bounded.sight_distance(&a, &b, (10, 5)), 5.);
         assert_eq!(
-            sight_distance(&Int2D { x: 0, y: 0 }, &Int2D { x: 4, y: 3 }),
-            5.
-        )
+            Topology::Torus.sight_distance(&a, &b, (10, 5)),
+            f32::sqrt(20.)
+        );
+    }
+
+    #[test]
+    fn test_topology() {
+        // Non-square board
+        let dim = (7, 3);
+        let corner = Int2D { x: 0, y: 2 };
+        assert!(Topology::Bounded
+            .neighbour(&corner, Direction::West, dim)
+            .is_none());
+        assert!(Topology::Bounded
+            .neighbour(&corner, Direction::North, dim)
+            .is_none());
+        assert!(
+            Topology::Bounded.neighbour(&corner, Direction::South, dim)
+                == Some(Int2D { x: 0, y: 1 })
+        );
+        assert!(
+            Topology::Torus.neighbour(&corner, Direction::West, dim) == Some(Int2D { x: 6, y: 2 })
+        );
+        assert!(
+            Topology::Torus.neighbour(&corner, Direction::North, dim) == Some(Int2D { x: 0, y: 0 })
+        );
+
+        let far = Int2D { x: 6, y: 0 };
+        assert_eq!(Topology::Bounded.step_distance(&corner, &far, dim), 8);
+        assert_eq!(Topology::Torus.step_distance(&corner, &far, dim), 2);
+
+        // Paths wrap around a torus, with every location on the board reachable
+        let bounded = PathDistances::from_sources(dim, Topology::Bounded, &[corner], |_| true);
+        let torus = PathDistances::from_sources(dim, Topology::Torus, &[corner], |_| true);
+        assert_eq!(bounded.get(&far), Some(8));
+        assert_eq!(torus.get(&far), Some(2));
+        let mut rng = StdRng::seed_from_u64(0);
+        let dir = move_along(&far, &torus, &mut rng);
+        assert!(dir == Some(Direction::East) || dir == Some(Direction::South));
+        for x in 0..7 {
+            for y in 0..3 {
+                let pos = Int2D { x, y };
+                assert_eq!(
+                    bounded.get(&pos),
+                    Some(Topology::Bounded.step_distance(&pos, &corner, dim))
+                );
+                assert_eq!(
+                    torus.get(&pos),
+                    Some(Topology::Torus.step_distance(&pos, &corner, dim))
+                );
+            }
+        }
     }
 
     #[test]
     fn test_move_towards() {
         let mut rng = StdRng::from_entropy();
         let target = Int2D { x: 10, y: 10 };
+        // Open board with no obstacles
+        let distances =
+            PathDistances::from_sources((25, 25), Topology::Bounded, &[target], |_| true);
 
         let pos = Int2D { x: 10, y: 10 };
-        assert_eq!(move_towards(&pos, &target, &mut rng), None);
+        assert_eq!(move_along(&pos, &distances, &mut rng), None);
 
         let pos = Int2D { x: 1, y: 10 };
-        assert_eq!(move_towards(&pos, &target, &mut rng), Some(Direction::East));
+        assert_eq!(
+            move_along(&pos, &distances, &mut rng),
+            Some(Direction::East)
+        );
 
         let pos = Int2D { x: 11, y: 10 };
-        assert_eq!(move_towards(&pos, &target, &mut rng), Some(Direction::West));
+        assert_eq!(
+            move_along(&pos, &distances, &mut rng),
+            Some(Direction::West)
+        );
 
         let pos = Int2D { x: 10, y: 5 };
         assert_eq!(
-            move_towards(&pos, &target, &mut rng),
+            move_along(&pos, &distances, &mut rng),
             Some(Direction::North)
         );
 
         let pos = Int2D { x: 10, y: 12 };
         assert_eq!(
-            move_towards(&pos, &target, &mut rng),
+            move_along(&pos, &distances, &mut rng),
             Some(Direction::South)
         );
 
         let pos = Int2D { x: 4, y: 8 };
-        let result = move_towards(&pos, &target, &mut rng);
+        let result = move_along(&pos, &distances, &mut rng);
         assert!(result == Some(Direction::North) || result == Some(Direction::East));
 
         let pos = Int2D { x: 4, y: 20 };
-        let result = move_towards(&pos, &target, &mut rng);
+        let result = move_along(&pos, &distances, &mut rng);
         assert!(result == Some(Direction::South) || result == Some(Direction::East));
 
         let pos = Int2D { x: 14, y: 8 };
-        let result = move_towards(&pos, &target, &mut rng);
+        let result = move_along(&pos, &distances, &mut rng);
         assert!(result == Some(Direction::North) || result == Some(Direction::West));
 
         let pos = Int2D { x: 11, y: 18 };
-        let result = move_towards(&pos, &target, &mut rng);
+        let result = move_along(&pos, &distances, &mut rng);
         assert!(result == Some(Direction::South) || result == Some(Direction::West));
     }
 
+    #[test]
+    fn test_path_distances() {
+        // A wall at x = 5 with a single gap at y = 8
+        let dim = (10, 10);
+        let can_enter = |pos: &Int2D| pos.x != 5 || pos.y == 8;
+        let target = Int2D { x: 7, y: 2 };
+        let distances = PathDistances::from_sources(dim, Topology::Bounded, &[target], can_enter);
+        let pos = Int2D { x: 3, y: 2 };
+        assert_eq!(Topology::Bounded.step_distance(&pos, &target, dim), 4);
+        assert_eq!(distances.get(&pos), Some(16));
+
+        // The nearest source is the one with the shortest path around the wall
+        let near = Int2D { x: 6, y: 2 };
+        let far = Int2D { x: 0, y: 2 };
+        let to_sources =
+            PathDistances::from_sources(dim, Topology::Bounded, &[near, far], can_enter);
+        assert!(to_sources.nearest(&pos) == Some((far, 3)));
+        assert!(to_sources.nearest(&target) == Some((near, 1)));
+
+        // Of equally near sources, the first is nearest
+        let (west, east) = (Int2D { x: 1, y: 2 }, Int2D { x: 3, y: 4 });
+        let to_sources =
+            PathDistances::from_sources(dim, Topology::Bounded, &[west, east], can_enter);
+        assert!(to_sources.nearest(&pos) == Some((west, 2)));
+        let to_sources =
+            PathDistances::from_sources(dim, Topology::Bounded, &[east, west], can_enter);
+        assert!(to_sources.nearest(&pos) == Some((east, 2)));
+
+        // Sources find the nearest of the others, or one sharing their location
+        let shared = Int2D { x: 1, y: 2 };
+        let to_sources =
+            PathDistances::from_sources(dim, Topology::Bounded, &[west, east, shared], can_enter);
+        assert!(to_sources.nearest_other(&east) == Some((west, 4)));
+        assert!(to_sources.nearest_other(&west) == Some((west, 0)));
+        let to_sources =
+            PathDistances::from_sources(dim, Topology::Bounded, &[west, east], can_enter);
+        assert!(to_sources.nearest_other(&west) == Some((east, 4)));
+        assert!(to_sources.nearest_other(&pos) == Some((west, 2)));
+        let mut rng = StdRng::seed_from_u64(0);
+        let dir = move_along_to_other(&west, &to_sources, &mut rng);
+        assert!(dir == Some(Direction::East) || dir == Some(Direction::North));
+
+        // Moves north towards the gap rather than east into the wall
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            move_along(&pos, &distances, &mut rng),
+            Some(Direction::North)
+        );
+
+        // Walled off targets cannot be reached
+        let distances =
+            PathDistances::from_sources(dim, Topology::Bounded, &[target], |pos: &Int2D| {
+                pos.x != 5
+            });
+        assert_eq!(distances.get(&pos), None);
+        assert_eq!(move_along(&pos, &distances, &mut rng), None);
+    }
+
     // #[test]
     // fn test_get_traders() {
     //     let dim: (u16, u16) = (10, 10);
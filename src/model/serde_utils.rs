@@ -20,5 +20,5 @@ where
     V: Deserialize<'de>,
 {
     let container: Vec<_> = serde::Deserialize::deserialize(des)?;
-    Ok(T::from_iter(container.into_iter()))
+    Ok(T::from_iter(container))
 }
@@ -0,0 +1,120 @@
+//! Finite stocks of resources on patches, depleted by harvesting and regrowing each step.
+use crate::config::RegrowthConfig;
+use rand::Rng;
+
+/// Stock of the resource on a patch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stock {
+    pub level: f32,
+    pub capacity: f32,
+}
+
+impl Stock {
+    /// Constructs a full stock of the given capacity.
+    pub fn full(capacity: i32) -> Self {
+        Stock {
+            level: capacity as f32,
+            capacity: capacity as f32,
+        }
+    }
+
+    /// Constructs the initial stock of a resource patch under the regrowth model, or `None` if
+    /// resources are never depleted.
+    pub fn initial<R: Rng + ?Sized>(regrowth: &RegrowthConfig, rng: &mut R) -> Option<Self> {
+        match *regrowth {
+            RegrowthConfig::Unlimited => None,
+            RegrowthConfig::Constant { CAPACITY, .. }
+            | RegrowthConfig::Logistic { CAPACITY, .. } => Some(Stock::full(CAPACITY)),
+            RegrowthConfig::Sugarscape { CAPACITY, .. } => {
+                Some(Stock::full(rng.gen_range(1..=CAPACITY)))
+            }
+        }
+    }
+
+    /// Whether the stock has less than a whole unit left to harvest.
+    pub fn is_depleted(&self) -> bool {
+        self.level < 1.0
+    }
+
+    /// Takes whole units, up to the given amount, from the stock, returning the amount taken.
+    pub fn harvest(&mut self, amount: i32) -> i32 {
+        let taken = amount.clamp(0, self.level.floor() as i32);
+        self.level -= taken as f32;
+        taken
+    }
+
+    /// Regrows the stock for one step under the regrowth model, up to its capacity.
+    pub fn regrow(&mut self, regrowth: &RegrowthConfig) {
+        let growth = match *regrowth {
+            RegrowthConfig::Unlimited => 0.0,
+            RegrowthConfig::Constant { RATE, .. } | RegrowthConfig::Sugarscape { RATE, .. } => RATE,
+            RegrowthConfig::Logistic { RATE, .. } => {
+                let level = self.level.max(1.0);
+                RATE * level * (1.0 - level / self.capacity)
+            }
+        };
+        self.level = (self.level + growth.max(0.0)).min(self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_regrowth() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(Stock::initial(&RegrowthConfig::Unlimited, &mut rng).is_none());
+
+        // Harvesting takes no more than the stock
+        let constant = RegrowthConfig::Constant {
+            CAPACITY: 10,
+            RATE: 0.5,
+        };
+        let mut stock = Stock::initial(&constant, &mut rng).unwrap();
+        assert_eq!(stock.harvest(4), 4);
+        assert_eq!(stock.harvest(20), 6);
+        assert!(stock.is_depleted());
+        stock.regrow(&constant);
+        assert!(stock.is_depleted());
+        stock.regrow(&constant);
+        assert!(!stock.is_depleted());
+        assert_eq!(stock.harvest(4), 1);
+        for _ in 0..30 {
+            stock.regrow(&constant);
+        }
+        assert_eq!(stock, Stock::full(10));
+
+        // Logistic growth is slow when nearly depleted or full, and fastest at half capacity
+        let logistic = RegrowthConfig::Logistic {
+            CAPACITY: 100,
+            RATE: 0.5,
+        };
+        let mut stock = Stock::full(100);
+        stock.harvest(100);
+        let mut levels = vec![stock.level];
+        for _ in 0..40 {
+            stock.regrow(&logistic);
+            levels.push(stock.level);
+        }
+        let growth: Vec<f32> = levels.windows(2).map(|w| w[1] - w[0]).collect();
+        let fastest = (0..growth.len())
+            .max_by(|&i, &j| growth[i].total_cmp(&growth[j]))
+            .unwrap();
+        assert!(growth[0] < 1.0);
+        assert!((levels[fastest] - 50.0).abs() < 15.0);
+        assert!(levels[40] > 99.0);
+
+        // Sugarscape capacities vary between patches
+        let sugarscape = RegrowthConfig::Sugarscape {
+            CAPACITY: 4,
+            RATE: 1.0,
+        };
+        let capacities: Vec<f32> = (0..50)
+            .map(|_| Stock::initial(&sugarscape, &mut rng).unwrap().capacity)
+            .collect();
+        assert!((1..=4).all(|capacity| capacities.contains(&(capacity as f32))));
+    }
+}
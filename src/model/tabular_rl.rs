@@ -4,9 +4,11 @@ use super::{
     q_table::{QKey, QTable},
     serde_utils,
 };
-use crate::config::core_config;
+use crate::config::Config;
+use crate::event_log::{EventKind, EventLog};
+use crate::provenance::Provenance;
 use krabmaga::HashMap;
-use rand::rngs::StdRng;
+use rand::{rngs::StdRng, Rng};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -14,7 +16,8 @@ use std::fmt::Debug;
 use std::fs::File;
 use std::io::Write;
 use std::marker::PhantomData;
-use strum::IntoEnumIterator;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 
 #[derive(Debug)]
 pub struct SARSAModel<T, S, L, A>
@@ -22,13 +25,7 @@ where
     T: DiscrRep<S, L> + Clone,
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + Serialize
-        + IntoEnumIterator
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
     /// Q tables indexed by agent ID.
     pub q_tbls: HashMap<u32, QTable<S, L, A>>,
@@ -43,13 +40,7 @@ where
     T: DiscrRep<S, L> + Clone,
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + Serialize
-        + IntoEnumIterator
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
     // Vec< Vec<dim=num levels for each resource> dim=num different resources>
     pub fn new(
@@ -58,12 +49,20 @@ where
         state_levels: Vec<L>,
         actions: Vec<A>,
         multi_policy: bool,
+        init_q_value: f32,
     ) -> Self {
         let mut q_tbls = HashMap::new();
-        for id in agent_ids {
+        // Without multiple policies, every agent shares the table of policy 0
+        let policy_ids = if multi_policy { agent_ids } else { vec![0] };
+        for id in policy_ids {
             q_tbls.insert(
                 id,
-                QTable::new(state_items.clone(), state_levels.clone(), actions.clone()),
+                QTable::new(
+                    state_items.clone(),
+                    state_levels.clone(),
+                    actions.clone(),
+                    init_q_value,
+                ),
             );
         }
         SARSAModel {
@@ -82,43 +81,96 @@ where
         }
     }
 
-    pub fn step(&mut self, t: i32, agent_hist: &BTreeMap<u32, History<T, S, L, A>>) {
-        let tau_: i32 = t - core_config().rl.SARSA_N as i32 - 1;
+    pub fn step(
+        &mut self,
+        t: i32,
+        agent_hist: &BTreeMap<u32, History<T, S, L, A>>,
+        config: &Config,
+        event_log: &mut EventLog,
+    ) {
+        let tau_: i32 = t - config.rl.SARSA_N as i32 - 1;
 
         // do update
         if tau_ >= 0 {
             // update all agents in turn
             for (id, hist) in agent_hist.iter() {
-                let tab = self.get_table_by_id_mut(*id);
                 let traj = &hist.trajectory;
 
-                let tau = tau_ as usize;
-                let n = core_config().rl.SARSA_N as usize;
+                // index of tau in the trajectory, skipping agents born after tau
+                let Some(tau) = (tau_ as usize).checked_sub(hist.start) else {
+                    continue;
+                };
+                let n = config.rl.SARSA_N as usize;
                 let mut g: f32 = 0.0;
 
+                // a trajectory ending with a terminal transition at time T (the death of the
+                // agent) has no updates for tau >= T, and no rewards or bootstrap beyond T
+                let end = hist.end();
+                if end.is_some_and(|end| tau >= end) {
+                    continue;
+                }
+
                 // sum n rewards (discounted back)
-                for i in (tau + 1)..=(tau + n) {
+                for i in (tau + 1)..=end.map_or(tau + n, |end| end.min(tau + n)) {
                     // assuming index (s0,a0,r1),(s1,a1,r2)...
                     // book assumes (s0,a0),(s1,a1,r1)...
                     let r_i = traj[i - 1].reward.val;
-                    g += core_config().rl.GAMMA.powf((i - tau - 1) as f32) * r_i as f32;
+                    g += config.rl.GAMMA.powf((i - tau - 1) as f32) * r_i as f32;
                 }
 
-                // bootstrap using q(n+1)
-                let q_btstrap = tab
-                    .get(&traj[tau + n].representation())
-                    .expect("all possible state-actions will be in the QTable");
-                g += core_config().rl.GAMMA.powf(n as f32) * q_btstrap;
+                let tab = self.get_table_by_id_mut(*id);
+
+                // bootstrap using q(n+1), unless the terminal transition is within n steps
+                if end.is_none_or(|end| tau + n < end) {
+                    let q_btstrap = tab
+                        .get(&traj[tau + n].representation(config))
+                        .expect("all possible state-actions will be in the QTable");
+                    g += config.rl.GAMMA.powf(n as f32) * q_btstrap;
+                }
 
                 // update q for (s_tau,a_tau)
                 let mut q_tau = *tab
-                    .get(&traj[tau].representation())
+                    .get(&traj[tau].representation(config))
                     .expect("all possible state-actions will be in the QTable");
-                q_tau += core_config().rl.ALPHA * (g - q_tau);
-                let old_q = tab.insert(traj[tau].representation(), q_tau);
-                // println!("{:?} -> {:?}", old_q, q_tau)
+                q_tau += config.rl.ALPHA * (g - q_tau);
+                let old_q = tab.insert(traj[tau].representation(config), q_tau);
+                event_log.log(
+                    t as u64,
+                    EventKind::LearningUpdate {
+                        agent: *id,
+                        tau: tau_ as usize,
+                        old_q: old_q.expect("all possible state-actions will be in the QTable"),
+                        new_q: q_tau,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Gives an agent born during the run a copy of the Q-table of its parent, with Gaussian
+    /// noise of standard deviation `noise` added to each value. Without multiple policies the
+    /// offspring shares the single table.
+    pub fn inherit(&mut self, parent: u32, child: u32, noise: f32, rng: &mut StdRng) {
+        if !self.multi_policy {
+            return;
+        }
+        let mut q_tbl = self
+            .q_tbls
+            .get(&parent)
+            .expect("qtable was initialised for all agent id's")
+            .clone();
+        if noise > 0.0 {
+            // Draw the noise in a fixed order of the keys, as the table is unordered
+            let tab = q_tbl.get_tab_mut();
+            let mut keys = tab.keys().cloned().collect::<Vec<_>>();
+            keys.sort_by_cached_key(|key| format!("{key:?}"));
+            for key in keys {
+                let r = (-2.0 * (1.0 - rng.gen::<f32>()).ln()).sqrt();
+                let theta = 2.0 * std::f32::consts::PI * rng.gen::<f32>();
+                *tab.get_mut(&key).expect("key of the table") += noise * r * theta.cos();
             }
         }
+        self.q_tbls.insert(child, q_tbl);
     }
 
     pub fn get_table_by_id_mut(&mut self, id: u32) -> &mut HashMap<QKey<S, L, A>, f32> {
@@ -135,105 +187,147 @@ where
             .get_tab()
     }
 
-    pub fn sample_action_by_id(&self, id: u32, state: &Vec<(S, L)>, rng: &mut StdRng) -> A {
-        let (a, q_optimal) = self
+    pub fn sample_action_by_id(
+        &self,
+        id: u32,
+        state: &Vec<(S, L)>,
+        actions: &[A],
+        epsilon: f32,
+        rng: &mut StdRng,
+    ) -> A {
+        let (a, _q_optimal) = self
             .q_tbls
             .get(&self.policy_id(id))
             .expect("qtable was initialised for all agent id's")
-            .sample_action(state, rng);
-        if id == 0 {
-            // println!("{}", q_optimal)
-        }
+            .sample_action(state, actions, epsilon, rng);
         a
     }
 
-    pub fn save(mut self) {
-        let mut total_itr = core_config().world.N_STEPS;
-        if core_config().rl.LOAD_MODEL {
+    /// Gets a checkpoint of the model at the end of the run with the given provenance.
+    pub fn checkpoint(mut self, provenance: &Provenance) -> SARSACheckpoint<S, L, A> {
+        let config = &provenance.config;
+        let mut total_itr = config.world.N_STEPS;
+        if config.rl.LOAD_MODEL {
             total_itr += self.checkpoint_itr.expect("set when model loaded");
         }
-        let mut f = File::create(format!(
-            "multiP_{}__agents_{}__trading_{}__totalItr_{}.json",
-            if core_config().rl.MULTI_POLICY { 1 } else { 0 },
-            core_config().world.N_AGENTS,
-            if core_config().world.HAS_TRADING {
-                1
-            } else {
-                0
-            },
-            total_itr
-        ))
-        .unwrap();
 
         let mut q_tbls;
-        if core_config().rl.MULTI_POLICY {
+        if config.rl.MULTI_POLICY {
             q_tbls = self.q_tbls;
         } else {
             q_tbls = HashMap::new();
             q_tbls.insert(0, self.q_tbls.remove(&0).unwrap());
         }
 
-        writeln!(
-            f,
-            "{}",
-            serde_json::to_string_pretty(&SARSACheckpoint {
-                total_itr: total_itr,
-                num_agents: core_config().world.N_AGENTS,
-                multi_policy: core_config().rl.MULTI_POLICY,
-                q_tbls,
-            })
-            .unwrap()
-        )
-        .unwrap();
+        // With multiple policies every agent has a table, including any born during the run
+        let num_agents = if config.rl.MULTI_POLICY {
+            q_tbls.len() as u32
+        } else {
+            config.world.N_AGENTS
+        };
+        SARSACheckpoint {
+            total_itr,
+            num_agents,
+            multi_policy: config.rl.MULTI_POLICY,
+            q_tbls,
+            provenance: Some(provenance.to_owned()),
+        }
+    }
+
+    /// Saves the model to a checkpoint file in the output directory, returning its path.
+    pub fn save(self, provenance: &Provenance) -> Result<PathBuf, CheckpointError> {
+        self.checkpoint(provenance).save(&provenance.config)
     }
 
-    pub fn load(checkpoint_file: &str) -> Self {
-        let path = std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap())
-            .join(checkpoint_file);
-        let checkpoint = SARSACheckpoint::parse(std::fs::read_to_string(path).unwrap());
+    /// Loads a model from a checkpoint file.
+    pub fn load(checkpoint_file: &Path) -> Result<Self, CheckpointError> {
+        let serial = std::fs::read_to_string(checkpoint_file)
+            .map_err(|err| CheckpointError::Read(checkpoint_file.to_path_buf(), err))?;
+        let checkpoint = SARSACheckpoint::parse(serial)
+            .map_err(|err| CheckpointError::Parse(checkpoint_file.to_path_buf(), err))?;
+        let expected = if checkpoint.multi_policy {
+            checkpoint.num_agents as usize
+        } else {
+            1
+        };
+        if checkpoint.q_tbls.len() != expected {
+            return Err(CheckpointError::TableCount(
+                checkpoint_file.to_path_buf(),
+                checkpoint.q_tbls.len(),
+                expected,
+            ));
+        }
 
-        SARSAModel {
+        Ok(SARSAModel {
             q_tbls: checkpoint.q_tbls,
             multi_policy: checkpoint.multi_policy,
             agent_state_type: PhantomData,
             checkpoint_itr: Some(checkpoint.total_itr),
-        }
+        })
     }
 }
 
+/// An error relating to loading or saving a model checkpoint.
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    /// Checkpoint file could not be read.
+    #[error("Unable to read the model checkpoint file {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    /// Checkpoint file could not be written.
+    #[error("Unable to write the model checkpoint file {0}: {1}")]
+    Write(PathBuf, std::io::Error),
+    /// Checkpoint could not be (de)serialized.
+    #[error("Unable to parse the model checkpoint file {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    /// Checkpoint has a number of Q-tables inconsistent with its number of agents.
+    #[error("The model checkpoint file {0} has {1} Q-tables, expected {2}")]
+    TableCount(PathBuf, usize, usize),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SARSACheckpoint<S, L, A>
 where
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + Serialize
-        + IntoEnumIterator
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
     total_itr: i32,
     multi_policy: bool,
-    num_agents: u8,
+    num_agents: u32,
     #[serde(with = "serde_utils")]
-    q_tbls: HashMap<u32, QTable<S, L, A>>,
+    pub q_tbls: HashMap<u32, QTable<S, L, A>>,
+    /// Provenance of the run producing the checkpoint. Absent from older checkpoints.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
 }
 
 impl<S, L, A> SARSACheckpoint<S, L, A>
 where
     S: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
     L: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
-    A: std::cmp::Eq
-        + std::hash::Hash
-        + Clone
-        + Debug
-        + Serialize
-        + IntoEnumIterator
-        + DeserializeOwned,
+    A: std::cmp::Eq + std::hash::Hash + Clone + Debug + Serialize + DeserializeOwned,
 {
-    pub fn parse(serial: String) -> SARSACheckpoint<S, L, A> {
-        serde_json::from_str::<SARSACheckpoint<S, L, A>>(&serial).unwrap()
+    pub fn parse(serial: String) -> Result<SARSACheckpoint<S, L, A>, serde_json::Error> {
+        serde_json::from_str::<SARSACheckpoint<S, L, A>>(&serial)
+    }
+
+    /// Saves the checkpoint to a file in the output directory, returning its path.
+    pub fn save(&self, config: &Config) -> Result<PathBuf, CheckpointError> {
+        let output_dir = Path::new(&config.simulation.OUTPUT_DIR);
+        let path = output_dir.join(format!(
+            "multiP_{}__agents_{}__trading_{}__totalItr_{}.json",
+            if self.multi_policy { 1 } else { 0 },
+            self.num_agents,
+            if config.world.HAS_TRADING { 1 } else { 0 },
+            self.total_itr
+        ));
+
+        let serial = serde_json::to_string_pretty(self)
+            .map_err(|err| CheckpointError::Parse(path.to_owned(), err))?;
+        std::fs::create_dir_all(output_dir)
+            .and_then(|_| File::create(&path))
+            .and_then(|mut f| writeln!(f, "{}", serial))
+            .map_err(|err| CheckpointError::Write(path.to_owned(), err))?;
+        Ok(path)
     }
 }
@@ -0,0 +1,141 @@
+//! Changes to the patches of the board made by environmental regimes on the timeline.
+use super::board::Patch;
+use super::environment::{EnvItem, Resource};
+use crate::config::{RegimeChange, Region, ResourceConfig, TerrainConfig};
+use krabmaga::engine::location::Int2D;
+use std::collections::BTreeSet;
+
+/// Gets the terrain item left behind by a resource that disappears or moves, given the name of a
+/// terrain type (by default the first terrain type).
+fn vacated_item(terrain: &[TerrainConfig], name: Option<&String>) -> EnvItem {
+    let index = name
+        .and_then(|name| terrain.iter().position(|t| t.NAME == *name))
+        .unwrap_or_default();
+    EnvItem::Terrain(index)
+}
+
+/// Whether a patch has the resource and is within the region, if any.
+fn selected(
+    pos: &Int2D,
+    patch: &Patch,
+    resource: Option<Resource>,
+    region: Option<&Region>,
+) -> bool {
+    matches!(patch.env_item, EnvItem::Resource(r) if Some(r) == resource)
+        && region.is_none_or(|region| region.contains(pos.x, pos.y))
+}
+
+/// Applies the change made by a regime to the patches of a board of the given dimensions, given
+/// with their locations by column then row. Regimes naming resources that are not configured
+/// select no patches.
+pub fn apply_change(
+    change: &RegimeChange,
+    patches: &mut [(Int2D, Patch)],
+    dim: (u16, u16),
+    resources: &[ResourceConfig],
+    terrain: &[TerrainConfig],
+) {
+    match change {
+        RegimeChange::Disappear {
+            RESOURCE,
+            REGION,
+            TERRAIN,
+        } => {
+            let resource = Resource::named(resources, RESOURCE);
+            let vacated = vacated_item(terrain, TERRAIN.as_ref());
+            for (pos, patch) in patches.iter_mut() {
+                if selected(pos, patch, resource, REGION.as_ref()) {
+                    *patch = Patch::new(patch.id, vacated);
+                }
+            }
+        }
+        // Applied to acquisition by the board
+        RegimeChange::AcquireRate { .. } => {}
+        RegimeChange::Shift {
+            RESOURCE,
+            DX,
+            DY,
+            REGION,
+            TERRAIN,
+        } => {
+            let resource = Resource::named(resources, RESOURCE);
+            let (width, height) = (i32::from(dim.0), i32::from(dim.1));
+            let index = |pos: &Int2D| (pos.x * height + pos.y) as usize;
+            let shifted = |pos: &Int2D| Int2D {
+                x: (pos.x + DX).rem_euclid(width),
+                y: (pos.y + DY).rem_euclid(height),
+            };
+            let moving: Vec<(Int2D, Patch)> = patches
+                .iter()
+                .filter(|(pos, patch)| selected(pos, patch, resource, REGION.as_ref()))
+                .copied()
+                .collect();
+            let destinations: BTreeSet<usize> =
+                moving.iter().map(|(pos, _)| index(&shifted(pos))).collect();
+            let sources: BTreeSet<usize> = moving.iter().map(|(pos, _)| index(pos)).collect();
+            // Patches overwritten by moving patches give their ids to the vacated patches, so
+            // that ids stay unique
+            let overwritten_ids: Vec<u32> = destinations
+                .difference(&sources)
+                .map(|&i| patches[i].1.id)
+                .collect();
+            let vacated = vacated_item(terrain, TERRAIN.as_ref());
+            let vacated_indices: Vec<usize> = sources.difference(&destinations).copied().collect();
+            for (pos, patch) in moving {
+                patches[index(&shifted(&pos))].1 = patch;
+            }
+            for (i, id) in vacated_indices.into_iter().zip(overwritten_ids) {
+                patches[i].1 = Patch::new(id, vacated);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_config;
+
+    #[test]
+    fn test_shift() {
+        // Food in the three westmost columns of the bottom row moves two patches west, wrapping
+        // around the board
+        let (width, height) = (5, 2);
+        let mut patches: Vec<(Int2D, Patch)> = (0..width)
+            .flat_map(|x| (0..height).map(move |y| Int2D { x, y }))
+            .enumerate()
+            .map(|(id, pos)| {
+                let item = if pos.y == 0 && pos.x < 3 {
+                    EnvItem::Resource(Resource(0))
+                } else {
+                    EnvItem::Resource(Resource(1))
+                };
+                (pos, Patch::new(id as u32, item))
+            })
+            .collect();
+        let change = RegimeChange::Shift {
+            RESOURCE: "Food".to_string(),
+            DX: -2,
+            DY: 0,
+            REGION: None,
+            TERRAIN: None,
+        };
+        let config = test_config();
+        apply_change(
+            &change,
+            &mut patches,
+            (width as u16, height as u16),
+            &config.resources,
+            &config.terrain,
+        );
+        let row: Vec<String> = (0..width)
+            .map(|x| match patches[(x * height) as usize].1.env_item {
+                EnvItem::Resource(resource) => resource.name(&config.resources).to_string(),
+                EnvItem::Terrain(index) => format!("Terrain({index})"),
+            })
+            .collect();
+        assert_eq!(row, ["Food", "Terrain(0)", "Terrain(0)", "Food", "Food"]);
+        let ids: BTreeSet<u32> = patches.iter().map(|(_, patch)| patch.id).collect();
+        assert_eq!(ids.len(), patches.len());
+    }
+}
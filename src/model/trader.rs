@@ -1,17 +1,18 @@
-use super::agent_api::AgentAPI;
-use super::routing::step_distance;
 use krabmaga::engine::{agent::Agent, location::Int2D};
-use rand::seq::SliceRandom;
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
 // use std::error::Error;
 use super::{
+    agent_api::AgentAPI,
     environment::Resource,
     forager::Forager,
     inventory::Inventory,
     routing::{Position, Router},
 };
-use crate::{config::core_config, model::board::Board};
+use crate::{config::Config, event_log::EventKind, model::board::Board};
+use krabmaga::HashMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(Clone, Copy)]
 pub struct Trader {
@@ -22,12 +23,11 @@ impl Display for Trader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "ID: {}; Loc: ({}, {}); Food: {} Water: {}",
+            "ID: {}; Loc: ({}, {}); Inventory: {:?}",
             self.id(),
             self.forager.pos.x,
             self.forager.pos.y,
-            self.forager.count(&Resource::Food),
-            self.forager.count(&Resource::Water)
+            self.forager.inventory()
         )
     }
 }
@@ -53,8 +53,10 @@ impl Trader {
     }
 }
 
-#[derive(Debug)]
-pub struct Offer(i32, i32);
+/// Lots of each resource, indexed by `Resource`, exchanged by an offer: negative for the lots
+/// offered and positive for the lots demanded.
+#[derive(Debug, PartialEq)]
+pub struct Offer(Vec<i32>);
 
 // #[derive(Error, Debug)]
 // pub enum OfferError {
@@ -64,62 +66,66 @@ pub struct Offer(i32, i32);
 // }
 
 impl Offer {
-    pub fn new(lots_food: i32, lots_water: i32) -> Self {
-        if lots_food > 0 && lots_water > 0 {
+    pub fn new(lots: Vec<i32>) -> Self {
+        // At most one resource is offered and at most one demanded
+        if lots.iter().filter(|&&l| l > 0).count() > 1 {
             panic!();
         }
-        if lots_food < 0 && lots_water < 0 {
+        if lots.iter().filter(|&&l| l < 0).count() > 1 {
             panic!();
         }
-        Offer(lots_food, lots_water)
+        Offer(lots)
     }
 
-    fn food_delta(&self) -> i32 {
-        self.0
+    /// Trivial offer of no lots of any of a number of resources.
+    pub fn trivial(n_resources: usize) -> Self {
+        Offer(vec![0; n_resources])
     }
 
-    fn water_delta(&self) -> i32 {
-        self.1
+    fn delta(&self, resource: &Resource) -> i32 {
+        self.0[resource.0]
     }
 
     /// Number of lots offered. Will always be non-positive.
     fn offered_lots(&self) -> i32 {
-        std::cmp::min(self.0, self.1)
+        self.0.iter().copied().min().unwrap_or_default().min(0)
     }
 
     /// Number of lots demanded. Will always be non-negative
     fn demanded_lots(&self) -> i32 {
-        std::cmp::max(self.0, self.1)
+        self.0.iter().copied().max().unwrap_or_default().max(0)
     }
 
     pub fn is_trivial(&self) -> bool {
-        self.0 == 0 && self.1 == 0
+        self.0.iter().all(|&l| l == 0)
     }
 
     /// Adjust this offer by one lot offered & demanded.
-    fn adjust_by_one(&mut self, food_is_offered: bool) {
-        if food_is_offered {
-            self.0 -= 1;
-            self.1 += 1;
-        } else {
-            self.0 += 1;
-            self.1 -= 1;
-        }
+    fn adjust_by_one(&mut self, offered: &Resource, demanded: &Resource) {
+        self.0[offered.0] -= 1;
+        self.0[demanded.0] += 1;
     }
 
     /// Determines whether this offer is matched by another offer.
     pub fn matched(&self, offer: &Offer) -> bool {
-        std::cmp::max(self.0 + offer.0, self.1 + offer.1) <= 0
+        self.0.iter().zip(&offer.0).all(|(a, b)| a + b <= 0)
     }
 
     pub fn invert(&self) -> Offer {
-        Offer(self.1, self.0)
+        let mut lots = vec![0; self.0.len()];
+        let offered = self.0.iter().position(|&l| l < 0);
+        let demanded = self.0.iter().position(|&l| l > 0);
+        if let (Some(offered), Some(demanded)) = (offered, demanded) {
+            lots[offered] = self.0[demanded];
+            lots[demanded] = self.0[offered];
+        }
+        Offer(lots)
     }
 }
 
 pub trait Trade {
     /// Gets this trader's offer.
-    fn offer(&self) -> Offer;
+    fn offer(&self, config: &Config) -> Offer;
     /// Decides whether this trader is prepared to raise the given current offer.
     fn will_raise_offer(
         &self,
@@ -128,42 +134,41 @@ pub trait Trade {
         other_count: i32,
         offered_lot_size: u32,
         other_lot_size: u32,
+        max_trade_lots: u32,
     ) -> bool;
     /// Applies their offer during trading.
-    fn apply_offer(&mut self);
+    fn apply_offer(&mut self, config: &Config);
 }
 
 impl Trade for Trader {
     /// Makes an offer, given the agent's current inventory.
-    fn offer(&self) -> Offer {
-        let mut current_offer = Offer::new(0, 0);
+    fn offer(&self, config: &Config) -> Offer {
+        let mut current_offer = Offer::trivial(config.resources.len());
 
         // Offer the resource with maximum supply and demand the one with minimum supply.
-        // Find the maximum trade of food such that the food inventory will
-        // be greater than the water inventory even after the trade.
-        let count_food = self.count(&Resource::Food);
-        let count_water = self.count(&Resource::Water);
-        while self.will_raise_offer(
-            &current_offer,
-            count_food,
-            count_water,
-            core_config().agent.FOOD_LOT_SIZE,
-            core_config().agent.WATER_LOT_SIZE,
-        ) {
-            current_offer.adjust_by_one(true);
-        }
-        if !current_offer.is_trivial() {
+        // Find the maximum trade of the offered resource such that its inventory will be greater
+        // than that of the demanded resource even after the trade.
+        let resources: Vec<Resource> = Resource::all(&config.resources).collect();
+        let by_count = |r: &&Resource| self.count(r);
+        let Some(offered) = resources.iter().rev().max_by_key(by_count) else {
             return current_offer;
-        }
-
+        };
+        let Some(demanded) = resources
+            .iter()
+            .filter(|r| *r != offered)
+            .min_by_key(by_count)
+        else {
+            return current_offer;
+        };
         while self.will_raise_offer(
             &current_offer,
-            count_water,
-            count_food,
-            core_config().agent.WATER_LOT_SIZE,
-            core_config().agent.FOOD_LOT_SIZE,
+            self.count(offered),
+            self.count(demanded),
+            offered.config(&config.resources).LOT_SIZE,
+            demanded.config(&config.resources).LOT_SIZE,
+            config.agent.MAX_TRADE_LOTS,
         ) {
-            current_offer.adjust_by_one(false);
+            current_offer.adjust_by_one(offered, demanded);
         }
         current_offer
     }
@@ -176,9 +181,10 @@ impl Trade for Trader {
         demanded_count: i32,
         offered_lot_size: u32,
         demanded_lot_size: u32,
+        max_trade_lots: u32,
     ) -> bool {
         let offered_lots = current_offer.offered_lots();
-        if offered_lots.abs() as u32 >= core_config().agent.MAX_TRADE_LOTS {
+        if offered_lots.unsigned_abs() >= max_trade_lots {
             return false;
         }
         // Naively, the offer is max when the inventory of the offered resource would remain larger than that of
@@ -190,68 +196,114 @@ impl Trade for Trader {
             > demanded_count + ((demanded_lots + 1) * (demanded_lot_size as i32))
     }
 
-    fn apply_offer(&mut self) {
-        let offer = self.offer();
-        // Settle food inventory.
-        self.acquire(&Resource::Food, offer.food_delta());
-        // Settle water inventory.
-        self.acquire(&Resource::Water, offer.water_delta());
+    fn apply_offer(&mut self, config: &Config) {
+        let offer = self.offer(config);
+        // Settle the inventory of each resource.
+        for resource in Resource::all(&config.resources) {
+            self.acquire(&resource, offer.delta(&resource));
+        }
+    }
+}
+
+/// Matches trades between agents at the start of a step, the first phase of trading: agents
+/// with non-trivial offers are taken in the random order of the step, each trading with the
+/// first agent in that order close enough in the snapshot of the board whose offer matches
+/// theirs. Matched agents apply their offers during their own steps, the second phase.
+pub fn resolve_trades(board: &mut Board) {
+    if board.step == 0 || !board.has_trading {
+        return;
+    }
+    let config = Arc::clone(&board.config);
+    let rank: HashMap<u32, usize> = board
+        .step_order
+        .iter()
+        .enumerate()
+        .map(|(rank, &id)| (id, rank))
+        .collect();
+    for id in board.step_order.clone() {
+        // If already traded, continue
+        if board.traded.contains_key(&id) {
+            continue;
+        }
+        let trader = board.get_agent_by_id(&id);
+        let offer = trader.offer(&config);
+        if offer.is_trivial() {
+            // Offer trivial, set to None
+            board.traded.insert(id, None);
+            continue;
+        }
+        // Agents close enough to trade in the snapshot captured at start of current board step,
+        // in the random order of the step
+        let mut counterparty_ids =
+            config
+                .trade
+                .MAX_TRADE_DISTANCE
+                .checked_sub(1)
+                .map_or_else(Vec::new, |steps| {
+                    board.agents.within(
+                        &trader.get_position(),
+                        steps,
+                        config.world.TOPOLOGY,
+                        board.dim,
+                    )
+                });
+        counterparty_ids.sort_by_key(|id| rank.get(id).copied());
+        for counterparty_id in counterparty_ids {
+            // If self or already traded, continue
+            if counterparty_id == id || board.traded.contains_key(&counterparty_id) {
+                continue;
+            }
+            let counterparty = board.get_agent_by_id(&counterparty_id);
+            // If offer is matched, perform trade
+            if counterparty.offer(&config).matched(&offer) {
+                board.event_log.log(
+                    board.step,
+                    EventKind::Trade {
+                        agent: id,
+                        counterparty: counterparty_id,
+                        lots: Resource::all(&config.resources)
+                            .map(|r| (r.name(&config.resources).to_string(), offer.delta(&r)))
+                            .collect::<BTreeMap<_, _>>(),
+                    },
+                );
+                // Add trade to lookup of which agents have traded
+                board.traded.insert(id, Some(counterparty_id));
+                board.traded.insert(counterparty_id, Some(id));
+
+                // Break - trade has occurred with only single trade currently implemented
+                break;
+            }
+        }
+        // If no trade possible and not traded, set to None
+        board.traded.entry(id).or_insert(None);
     }
 }
 
 impl Agent for Trader {
     fn step(&mut self, state: &mut dyn krabmaga::engine::state::State) {
         let board = state.as_any_mut().downcast_mut::<Board>().unwrap();
-        // Borrow traders snapshot captured at start of current board step in before_step
-        let traders = &board.current_traders;
-        if (board.step > 0) & board.has_trading {
-            // Execute trade if available.
-            if !self.offer().is_trivial() {
-                if !board.traded.contains_key(&self.id()) {
-                    let offer = self.offer();
-                    for counterparty in traders {
-                        let counterparty_id = counterparty.id();
-                        // If already traded, continue
-                        if board.traded.contains_key(&counterparty_id) {
-                            continue;
-                        }
-                        // If not self AND offer is matched AND agents are close enough, perform trade
-                        if counterparty_id != self.id()
-                            && counterparty.offer().matched(&offer)
-                            && (step_distance(&self.forager.pos, &counterparty.forager.pos)
-                                < core_config().trade.MAX_TRADE_DISTANCE)
-                        {
-                            // Print trade when vverbose
-                            if core_config().simulation.VERBOSITY > 1 {
-                                println!("Trade between: {} and {}", self, counterparty);
-                            }
-                            // Add trade to lookup of which agents have traded
-                            board.traded.insert(self.id(), Some(counterparty_id));
-                            board.traded.insert(counterparty.id(), Some(self.id()));
-
-                            // Apply offer to inventory, counterparty will do corresponding call
-                            // during their update
-                            self.apply_offer();
-
-                            // Break - trade has occurred with only single trade currently implemented
-                            break;
-                        }
-                    }
-                    // If no trade possible and not traded, set to None
-                    board.traded.entry(self.id()).or_insert(None);
-                } else if let Some(&Some(_)) = board.traded.get(&self.id()) {
-                    // Apply offer previously initiated by a counterparty during their agent step
-                    self.apply_offer();
-                }
-            } else {
-                // Offer trivial, set to None
-                board.traded.insert(self.id(), None);
-            }
+        // Start from the agent as committed to the board at the end of the last step, unless it
+        // has died
+        let Some(trader) = board.agents.get(&self.id()) else {
+            return;
+        };
+        *self = *trader;
+
+        // Apply offer of any trade matched at the start of the step, as does the counterparty
+        // during their agent step
+        if let Some(Some(_)) = board.traded.get(&self.id()) {
+            self.apply_offer(&board.config);
         }
 
         // Trade has occurred before agent choosee next action
         self.forager.step(state)
     }
+
+    /// Removes agents that have died from the schedule.
+    fn is_stopped(&mut self, state: &mut dyn krabmaga::engine::state::State) -> bool {
+        let board = state.as_any().downcast_ref::<Board>().unwrap();
+        board.agents.get(&self.id()).is_none()
+    }
 }
 
 // impl Policy for Trader {
@@ -321,16 +373,22 @@ mod tests {
     #[test]
     fn test_matched() {
         // This is an offer of *at most* 2 lots of food for *at least* 3 lots of water.
-        let offer = Offer::new(-2, 3);
-
-        assert!(offer.matched(&Offer::new(2, -3)));
-        assert!(offer.matched(&Offer::new(2, -5)));
-        assert!(offer.matched(&Offer::new(1, -3)));
-        assert!(offer.matched(&Offer::new(1, -4)));
-        assert!(offer.matched(&Offer::new(0, -3)));
-
-        assert!(!offer.matched(&Offer::new(3, -3)));
-        assert!(!offer.matched(&Offer::new(2, -2)));
-        assert!(!offer.matched(&Offer::new(2, -1)));
+        let offer = Offer::new(vec![-2, 3]);
+
+        assert!(offer.matched(&Offer::new(vec![2, -3])));
+        assert!(offer.matched(&Offer::new(vec![2, -5])));
+        assert!(offer.matched(&Offer::new(vec![1, -3])));
+        assert!(offer.matched(&Offer::new(vec![1, -4])));
+        assert!(offer.matched(&Offer::new(vec![0, -3])));
+        assert_eq!(offer.invert(), Offer::new(vec![3, -2]));
+
+        assert!(!offer.matched(&Offer::new(vec![3, -3])));
+        assert!(!offer.matched(&Offer::new(vec![2, -2])));
+        assert!(!offer.matched(&Offer::new(vec![2, -1])));
+
+        // With a third resource, offers of other resources are not matched
+        let offer = Offer::new(vec![-2, 3, 0]);
+        assert!(offer.matched(&Offer::new(vec![2, -3, 0])));
+        assert!(!offer.matched(&Offer::new(vec![0, -3, 2])));
     }
 }
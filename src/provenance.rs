@@ -0,0 +1,292 @@
+//! Provenance of simulation outputs and model checkpoints, sufficient to rerun the simulation
+//! that produced them.
+use crate::config::{Config, ConfigError};
+use crate::model::board::{ClammsInt2D, MapError};
+use crate::model::grid_map::GridMap;
+use chrono::{DateTime, Utc};
+use krabmaga::engine::location::Int2D;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Key of the provenance record in output and checkpoint files.
+pub const PROVENANCE_KEY: &str = "provenance";
+
+/// An error relating to recording or reading provenance.
+#[derive(Error, Debug)]
+pub enum ProvenanceError {
+    /// Map file could not be read.
+    #[error(transparent)]
+    Map(#[from] MapError),
+    /// File recorded in the provenance could not be read.
+    #[error("Unable to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// File could not be parsed as JSON.
+    #[error("Unable to parse {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    /// File has no provenance record.
+    #[error("{0} has no provenance record")]
+    Missing(PathBuf),
+    /// Timeline file could not be parsed.
+    #[error("Unable to parse the timeline file {0}: {1}")]
+    Timeline(PathBuf, ConfigError),
+    /// File recorded in the provenance has changed since the run.
+    #[error("{0} has changed since the run (SHA-256 {1} expected)")]
+    Changed(PathBuf, String),
+}
+
+/// SHA-256 digest of the contents of a file used by a run.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FileDigest {
+    pub file: String,
+    pub sha256: String,
+}
+
+impl FileDigest {
+    fn new(file: &str, contents: &[u8]) -> Self {
+        FileDigest {
+            file: file.to_string(),
+            sha256: format!("{:x}", Sha256::digest(contents)),
+        }
+    }
+
+    /// Checks the file is unchanged.
+    pub fn verify(&self) -> Result<(), ProvenanceError> {
+        let path = Path::new(&self.file);
+        let contents = std::fs::read(path).map_err(|err| ProvenanceError::Io(path.into(), err))?;
+        if FileDigest::new(&self.file, &contents) == *self {
+            Ok(())
+        } else {
+            Err(ProvenanceError::Changed(
+                path.to_path_buf(),
+                self.sha256.to_owned(),
+            ))
+        }
+    }
+}
+
+/// Resource locations map used by a run, with its contents embedded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapProvenance {
+    /// Map file, if the map was loaded from one.
+    pub file: Option<String>,
+    /// SHA-256 digest of the map file, or of the JSON serialization of a map not loaded from a
+    /// file.
+    pub sha256: String,
+    pub resource_locations: BTreeMap<String, Vec<ClammsInt2D>>,
+    /// Grid map, if the map was loaded from a grid map file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<GridMap>,
+}
+
+impl MapProvenance {
+    /// Records a map not loaded from a file.
+    pub fn from_resource_locations(resource_locations: &BTreeMap<String, Vec<Int2D>>) -> Self {
+        let resource_locations: BTreeMap<String, Vec<ClammsInt2D>> = resource_locations
+            .iter()
+            .map(|(resource, locs)| {
+                let locs = locs.iter().copied().map(Into::into).collect();
+                (resource.to_owned(), locs)
+            })
+            .collect();
+        let serial =
+            serde_json::to_string(&resource_locations).expect("resource locations serialize");
+        MapProvenance {
+            file: None,
+            sha256: FileDigest::new("", serial.as_bytes()).sha256,
+            resource_locations,
+            grid: None,
+        }
+    }
+}
+
+/// Everything needed to rerun a simulation: the resolved config, the map, the model checkpoint
+/// started from, the crate version and the start time. The regimes of any timeline file are
+/// merged into the timeline of the resolved config.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Provenance {
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    pub config: Config,
+    /// Map given by `MAP_FILE` or `RESOURCE_LOCATIONS_FILE`, or generated by `MAP_GENERATOR`, if
+    /// any.
+    pub map: Option<MapProvenance>,
+    /// Checkpoint given by `MODEL_CHECKPOINT_FILE`, if the model was loaded.
+    pub checkpoint: Option<FileDigest>,
+    /// Timeline file given by `TIMELINE_FILE`, if any. Absent from older provenance records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<FileDigest>,
+}
+
+impl Provenance {
+    /// Records the provenance of a run starting now with the given config, reading the map,
+    /// checkpoint and timeline files it refers to.
+    pub fn record(config: &Config) -> Result<Self, ProvenanceError> {
+        let read = |file: &str| {
+            let path = Path::new(file);
+            std::fs::read_to_string(path).map_err(|err| MapError::Io(path.to_path_buf(), err))
+        };
+        let parse_error = |file: &str, err| MapError::Parse(Path::new(file).to_path_buf(), err);
+        let map = match (
+            &config.world.MAP_FILE,
+            &config.world.RESOURCE_LOCATIONS_FILE,
+        ) {
+            (Some(file), _) => {
+                let input = read(file)?;
+                let grid = GridMap::read(&input).map_err(|err| parse_error(file, err))?;
+                Some(MapProvenance {
+                    file: Some(file.to_owned()),
+                    sha256: FileDigest::new(file, input.as_bytes()).sha256,
+                    resource_locations: grid.clamms_resource_locations(),
+                    grid: Some(grid),
+                })
+            }
+            (None, Some(file)) => {
+                let input = read(file)?;
+                Some(MapProvenance {
+                    file: Some(file.to_owned()),
+                    sha256: FileDigest::new(file, input.as_bytes()).sha256,
+                    resource_locations: serde_json::from_str(&input)
+                        .map_err(|err| parse_error(file, err))?,
+                    grid: None,
+                })
+            }
+            (None, None) => None,
+        };
+        let checkpoint = match (&config.rl.MODEL_CHECKPOINT_FILE, config.rl.LOAD_MODEL) {
+            (Some(file), true) => {
+                let path = Path::new(file);
+                let contents =
+                    std::fs::read(path).map_err(|err| ProvenanceError::Io(path.into(), err))?;
+                Some(FileDigest::new(file, &contents))
+            }
+            _ => None,
+        };
+        let mut config = config.clone();
+        let timeline_file = config.world.TIMELINE_FILE.clone().unwrap_or_default();
+        let timeline = config
+            .resolve_timeline()
+            .map_err(|err| match err {
+                ConfigError::Io(path, err) => ProvenanceError::Io(path, err),
+                err => ProvenanceError::Timeline((&timeline_file).into(), err),
+            })?
+            .map(|(_, input)| FileDigest::new(&timeline_file, input.as_bytes()));
+        Ok(Provenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: Utc::now(),
+            config,
+            map,
+            checkpoint,
+            timeline,
+        })
+    }
+
+    /// Reads the provenance recorded in an output or checkpoint file.
+    pub fn open(path: &Path) -> Result<Self, ProvenanceError> {
+        let input =
+            std::fs::read_to_string(path).map_err(|err| ProvenanceError::Io(path.into(), err))?;
+        let mut value: serde_json::Value = serde_json::from_str(&input)
+            .map_err(|err| ProvenanceError::Parse(path.to_path_buf(), err))?;
+        match value.get_mut(PROVENANCE_KEY).map(serde_json::Value::take) {
+            None | Some(serde_json::Value::Null) => {
+                Err(ProvenanceError::Missing(path.to_path_buf()))
+            }
+            Some(provenance) => serde_json::from_value(provenance)
+                .map_err(|err| ProvenanceError::Parse(path.to_path_buf(), err)),
+        }
+    }
+
+    /// Gets the provenance of a rerun of this run starting now, with the same config, map and
+    /// checkpoint.
+    pub fn rerun(&self) -> Self {
+        Provenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: Utc::now(),
+            ..self.clone()
+        }
+    }
+
+    /// Gets the embedded grid map, if a grid map was used.
+    pub fn grid_map(&self) -> Option<&GridMap> {
+        self.map.as_ref().and_then(|map| map.grid.as_ref())
+    }
+
+    /// Gets the embedded resource locations, if a map was used.
+    pub fn resource_locations(&self) -> Option<BTreeMap<String, Vec<Int2D>>> {
+        self.map.as_ref().map(|map| {
+            map.resource_locations
+                .iter()
+                .map(|(resource, locs)| {
+                    (
+                        resource.to_owned(),
+                        locs.iter().copied().map(Int2D::from).collect(),
+                    )
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{test_config, TestDir};
+
+    #[test]
+    fn test_record() {
+        let mut config = test_config();
+        let provenance = Provenance::record(&config).unwrap();
+        assert_eq!(provenance.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.config, config);
+        assert!(provenance.map.is_none());
+        assert!(provenance.checkpoint.is_none());
+
+        let dir = TestDir::new("provenance");
+        let map_file = dir.path().join("map.json");
+        std::fs::write(&map_file, r#"{"Food": [{"x": 1, "y": 2}], "Water": []}"#).unwrap();
+        config.world.RESOURCE_LOCATIONS_FILE = Some(map_file.to_string_lossy().into_owned());
+        let provenance = Provenance::record(&config).unwrap();
+        let map = provenance.map.as_ref().unwrap();
+        assert_eq!(map.sha256.len(), 64);
+        let digest = FileDigest::new(
+            &map_file.to_string_lossy(),
+            &std::fs::read(&map_file).unwrap(),
+        );
+        assert_eq!(digest.sha256, map.sha256);
+        assert!(digest.verify().is_ok());
+        assert!(provenance.resource_locations().unwrap()["Food"] == vec![Int2D { x: 1, y: 2 }]);
+
+        // Provenance round trips through an output file
+        let output_file = dir.path().join("output.json");
+        std::fs::write(
+            &output_file,
+            serde_json::json!({ PROVENANCE_KEY: provenance, "agent_histories": {} }).to_string(),
+        )
+        .unwrap();
+        assert_eq!(Provenance::open(&output_file).unwrap(), provenance);
+
+        // Regimes in a timeline file are merged into the recorded config
+        let timeline_file = dir.path().join("timeline.toml");
+        std::fs::write(
+            &timeline_file,
+            "[[timeline]]\nNAME = \"famine\"\nSTART = 3\nTYPE = \"disappear\"\nRESOURCE = \"Food\"\n",
+        )
+        .unwrap();
+        config.world.TIMELINE_FILE = Some(timeline_file.to_string_lossy().into_owned());
+        let provenance = Provenance::record(&config).unwrap();
+        assert_eq!(provenance.config.world.TIMELINE_FILE, None);
+        assert_eq!(provenance.config.timeline.len(), 1);
+        assert_eq!(provenance.config.timeline[0].NAME, "famine");
+        assert!(provenance.timeline.unwrap().verify().is_ok());
+
+        std::fs::write(&map_file, r#"{"Food": [], "Water": []}"#).unwrap();
+        assert!(matches!(digest.verify(), Err(ProvenanceError::Changed(..))));
+        std::fs::write(&output_file, "{}").unwrap();
+        assert!(matches!(
+            Provenance::open(&output_file),
+            Err(ProvenanceError::Missing(_))
+        ));
+    }
+}
@@ -0,0 +1,686 @@
+//! Building, stepping and running simulations, and writing their outputs.
+use crate::config::Config;
+use crate::event_log::EventLog;
+use crate::model::{
+    action::Action,
+    agent_api::AgentAPI,
+    agent_state::{AgentState, AgentStateItems, InvLevel},
+    board::{new_schedule, Board, ClammsInt2D, MapError},
+    history::History,
+    map_generator::generate_resource_locations,
+    policy::{ActionPolicy, EpsilonGreedy},
+    tabular_rl::{CheckpointError, SARSACheckpoint, SARSAModel},
+};
+use crate::provenance::{MapProvenance, Provenance, ProvenanceError};
+use crate::validation::{validate_with_map, ValidationError};
+use krabmaga::engine::location::Int2D;
+use krabmaga::engine::{schedule::Schedule, state::State};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+/// File in the output directory that agent histories are written to by default.
+pub const HISTORIES_FILE: &str = "output.json";
+
+/// File in the output directory that the board layout is written to as a grid map.
+pub const LAYOUT_FILE: &str = "layout.json";
+
+/// File in the output directory that the resource locations of the board are written to.
+pub const LAYOUT_RESOURCE_LOCATIONS_FILE: &str = "layout_resource_locations.json";
+
+/// An error relating to setting up or running a simulation.
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    /// Resource locations could not be loaded.
+    #[error(transparent)]
+    Map(#[from] MapError),
+    /// Model checkpoint could not be loaded or saved.
+    #[error(transparent)]
+    Checkpoint(#[from] CheckpointError),
+    /// Provenance could not be recorded or read.
+    #[error(transparent)]
+    Provenance(#[from] ProvenanceError),
+    /// Config failed validation.
+    #[error(transparent)]
+    Invalid(#[from] ValidationError),
+    /// Outputs could not be written.
+    #[error("Unable to write the output file {0}: {1}")]
+    Output(PathBuf, std::io::Error),
+}
+
+/// Writes a value to a JSON file, creating its directory if needed.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), SimulationError> {
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut f = File::create(path)?;
+        writeln!(f, "{}", serde_json::to_string_pretty(value)?)
+    };
+    write().map_err(|err| SimulationError::Output(path.to_owned(), err))
+}
+
+pub type AgentHistories = BTreeMap<u32, History<AgentState, AgentStateItems, InvLevel, Action>>;
+
+/// Contents of the output file: the agent histories, the population at the end of each step and
+/// the provenance of the run.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SimulationOutput<H = AgentHistories> {
+    pub provenance: Provenance,
+    pub agent_histories: H,
+    /// Number of living agents at the end of each step. Absent from older output files.
+    #[serde(default)]
+    pub population: Vec<u32>,
+}
+
+/// Loads the model from a checkpoint if `LOAD_MODEL` is set, otherwise constructs a new model.
+pub fn load_or_new_model(
+    config: &Config,
+) -> Result<SARSAModel<AgentState, AgentStateItems, InvLevel, Action>, CheckpointError> {
+    if config.rl.LOAD_MODEL {
+        SARSAModel::load(Path::new(
+            config
+                .rl
+                .MODEL_CHECKPOINT_FILE
+                .as_ref()
+                .expect("path to model checkpoint file needed to load model"),
+        ))
+    } else {
+        Ok(SARSAModel::new(
+            (0..config.world.N_AGENTS).collect(),
+            AgentStateItems::all(&config.resources),
+            InvLevel::iter().collect::<Vec<InvLevel>>(),
+            Action::all(&config.resources),
+            config.rl.MULTI_POLICY,
+            config.rl.INIT_Q_VALUES,
+        ))
+    }
+}
+
+/// Builder for a `Simulation`. The map, model and policy default to those given by the config:
+/// the map in `MAP_FILE` or `RESOURCE_LOCATIONS_FILE` (or a map from `MAP_GENERATOR`), the model
+/// loaded from `MODEL_CHECKPOINT_FILE` if `LOAD_MODEL` is set (or a new model) and an
+/// epsilon-greedy policy.
+pub struct SimulationBuilder {
+    config: Config,
+    provenance: Option<Provenance>,
+    resource_locations: Option<BTreeMap<String, Vec<Int2D>>>,
+    model: Option<SARSAModel<AgentState, AgentStateItems, InvLevel, Action>>,
+    policy: Box<dyn ActionPolicy>,
+}
+
+impl SimulationBuilder {
+    /// Constructs a builder for a simulation with the given config.
+    pub fn new(config: Config) -> Self {
+        SimulationBuilder {
+            config,
+            provenance: None,
+            resource_locations: None,
+            model: None,
+            policy: Box::new(EpsilonGreedy),
+        }
+    }
+
+    /// Constructs a builder rerunning the simulation with the given provenance, using its
+    /// config and embedded map.
+    pub fn from_provenance(provenance: Provenance) -> Self {
+        SimulationBuilder {
+            provenance: Some(provenance.clone()),
+            ..SimulationBuilder::new(provenance.config)
+        }
+    }
+
+    /// Uses the given resource locations in place of the map in the config.
+    pub fn resource_locations(mut self, resource_locations: BTreeMap<String, Vec<Int2D>>) -> Self {
+        self.resource_locations = Some(resource_locations);
+        self
+    }
+
+    /// Uses the given model in place of the model given by the config. The model is not
+    /// recorded in the provenance of the run.
+    pub fn model(
+        mut self,
+        model: SARSAModel<AgentState, AgentStateItems, InvLevel, Action>,
+    ) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Uses the given policy to select the actions of agents.
+    pub fn policy(mut self, policy: impl ActionPolicy + 'static) -> Self {
+        self.policy = Box::new(policy);
+        self
+    }
+
+    /// Builds the board without initialising agents and resources, returning it with the
+    /// provenance of the run.
+    pub fn build_board(self) -> Result<(Board, Provenance), SimulationError> {
+        let mut provenance = match self.provenance {
+            Some(provenance) => provenance,
+            None => Provenance::record(&self.config)?,
+        };
+        if let Some(resource_locations) = &self.resource_locations {
+            provenance.map = Some(MapProvenance::from_resource_locations(resource_locations));
+        } else if provenance.map.is_none() {
+            // Record any generated map so that it is embedded in the outputs
+            provenance.map = generate_resource_locations(&provenance.config)
+                .as_ref()
+                .map(MapProvenance::from_resource_locations);
+        }
+        let resource_locations = provenance.resource_locations();
+        let grid_map = provenance.grid_map().cloned();
+        validate_with_map(
+            &provenance.config,
+            resource_locations.as_ref(),
+            grid_map.as_ref(),
+        )?;
+
+        let config = Arc::new(provenance.config.clone());
+        let model = match self.model {
+            Some(model) => model,
+            None => load_or_new_model(&config)?,
+        };
+        let mut board = match (grid_map, resource_locations) {
+            (Some(grid_map), _) => {
+                Board::new_with_seed_grid_map(Arc::clone(&config), grid_map, model)
+            }
+            (None, Some(resource_locations)) => Board::new_with_seed_resource_locations(
+                Arc::clone(&config),
+                resource_locations,
+                model,
+            ),
+            (None, None) => Board::new_with_seed(Arc::clone(&config), model),
+        };
+        board.policy = self.policy;
+        if let Some(path) = event_log_path(&config) {
+            board.event_log = EventLog::new(&config.log)
+                .with_file(&path)
+                .map_err(|err| SimulationError::Output(path.to_owned(), err))?;
+        }
+        Ok((board, provenance))
+    }
+
+    /// Builds the simulation, initialising agents and resources.
+    pub fn build(self) -> Result<Simulation, SimulationError> {
+        let (mut board, provenance) = self.build_board()?;
+        let mut schedule: Schedule = new_schedule();
+        board.init(&mut schedule);
+        Ok(Simulation {
+            board,
+            schedule,
+            provenance,
+            steps: 0,
+        })
+    }
+}
+
+/// Path of the event log file, if any, in the output directory.
+fn event_log_path(config: &Config) -> Option<PathBuf> {
+    config
+        .log
+        .FILE
+        .as_ref()
+        .map(|file| Path::new(&config.simulation.OUTPUT_DIR).join(file))
+}
+
+/// Observation of an agent: its position and observed state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub position: ClammsInt2D,
+    pub state: AgentState,
+}
+
+/// A simulation that can be stepped, observed and written to output files.
+pub struct Simulation {
+    board: Board,
+    schedule: Schedule,
+    provenance: Provenance,
+    steps: u64,
+}
+
+impl Simulation {
+    /// Constructs a builder for a simulation with the given config.
+    pub fn builder(config: Config) -> SimulationBuilder {
+        SimulationBuilder::new(config)
+    }
+
+    /// Advances the simulation by one step, unless every agent has died.
+    pub fn step(&mut self) {
+        if self.is_extinct() {
+            return;
+        }
+        self.schedule.step(&mut self.board);
+        self.steps += 1;
+    }
+
+    /// Advances the simulation by `n` steps.
+    pub fn run(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// Advances the simulation to `N_STEPS` steps.
+    pub fn run_to_end(&mut self) {
+        let n_steps = u64::try_from(self.config().world.N_STEPS).unwrap_or_default();
+        self.run(n_steps.saturating_sub(self.steps));
+    }
+
+    /// Whether every agent has died under the mortality rule, after which the simulation does
+    /// not advance.
+    pub fn is_extinct(&self) -> bool {
+        self.steps > 0 && self.board.agents.is_empty()
+    }
+
+    /// Number of steps taken.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.board.config
+    }
+
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    pub fn agent_histories(&self) -> &AgentHistories {
+        &self.board.agent_histories
+    }
+
+    /// Observations of every agent at the end of the last step, keyed by agent ID. Empty before
+    /// the first step.
+    pub fn observations(&self) -> BTreeMap<u32, Observation> {
+        self.board
+            .get_agents()
+            .iter()
+            .map(|trader| {
+                let forager = trader.forager();
+                (
+                    trader.id(),
+                    Observation {
+                        position: forager.pos.into(),
+                        state: forager.agent_state(&self.board),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Number of living agents at the end of each step taken.
+    pub fn population(&self) -> &[u32] {
+        &self.board.population
+    }
+
+    /// Output of the simulation so far: the agent histories, the population at the end of each
+    /// step and the provenance of the run.
+    pub fn output(&self) -> SimulationOutput<&AgentHistories> {
+        SimulationOutput {
+            provenance: self.provenance.to_owned(),
+            agent_histories: self.agent_histories(),
+            population: self.population().to_vec(),
+        }
+    }
+
+    /// Writes the output to the given file (by default `output.json` in the output directory),
+    /// creating its directory if needed.
+    pub fn write_histories(&self, histories_file: Option<&Path>) -> Result<(), SimulationError> {
+        let path = histories_file.map(Path::to_path_buf).unwrap_or_else(|| {
+            Path::new(&self.config().simulation.OUTPUT_DIR).join(HISTORIES_FILE)
+        });
+        write_json(&path, &self.output())
+    }
+
+    /// Writes the realised layout of the board to the output directory, both as a grid map
+    /// (`layout.json`) that can be loaded with `MAP_FILE` and as resource locations
+    /// (`layout_resource_locations.json`) that can be loaded with `RESOURCE_LOCATIONS_FILE`.
+    pub fn write_layout(&self) -> Result<(), SimulationError> {
+        let output_dir = Path::new(&self.config().simulation.OUTPUT_DIR);
+        let resources = &self.config().resources;
+        let resource_locations: BTreeMap<String, Vec<ClammsInt2D>> = self
+            .board
+            .resource_locations
+            .iter()
+            .filter(|(_, locs)| !locs.is_empty())
+            .map(|(resource, locs)| {
+                let locs = locs.iter().copied().map(Into::into).collect();
+                (resource.name(resources).to_string(), locs)
+            })
+            .collect();
+        write_json(&output_dir.join(LAYOUT_FILE), &self.board.layout())?;
+        write_json(
+            &output_dir.join(LAYOUT_RESOURCE_LOCATIONS_FILE),
+            &resource_locations,
+        )
+    }
+
+    /// Flushes the event log file, if any.
+    pub fn flush_event_log(&mut self) -> Result<(), SimulationError> {
+        self.board.event_log.flush().map_err(|err| {
+            SimulationError::Output(event_log_path(self.config()).unwrap_or_default(), err)
+        })
+    }
+
+    /// Writes the outputs of the run: the agent histories to the given file (by default
+    /// `output.json` in the output directory), the event log, the board layout and a model
+    /// checkpoint if `SAVE_MODEL` is set.
+    pub fn write_outputs(mut self, histories_file: Option<&Path>) -> Result<(), SimulationError> {
+        self.flush_event_log()?;
+        self.write_histories(histories_file)?;
+        self.write_layout()?;
+        if self.config().rl.SAVE_MODEL {
+            self.board.model.save(&self.provenance)?;
+        }
+        Ok(())
+    }
+
+    /// Consumes the simulation, returning the board.
+    pub fn into_board(self) -> Board {
+        self.board
+    }
+}
+
+/// Runs a simulation for `N_STEPS` with the map, model and policy given by the config.
+pub fn run(config: Config) -> Result<Simulation, SimulationError> {
+    let mut simulation = Simulation::builder(config).build()?;
+    simulation.run_to_end();
+    Ok(simulation)
+}
+
+/// Outcome of rerunning a recorded simulation.
+#[derive(Debug, PartialEq)]
+pub struct RerunOutcome {
+    /// Whether the rerun reproduced the recorded agent histories, or Q-tables for a checkpoint.
+    pub reproduced: bool,
+    /// Version of the crate that produced the recorded file, if not the version rerunning it.
+    pub recorded_version: Option<String>,
+}
+
+/// Reruns the simulation recorded in an output or checkpoint file, writing its outputs to the
+/// given directory (by default `rerun` alongside the file). Returns whether the rerun reproduced
+/// the recorded results, and the version that recorded them if different.
+pub fn rerun(
+    path: &Path,
+    output_dir: Option<&Path>,
+    histories_file: Option<&Path>,
+) -> Result<RerunOutcome, SimulationError> {
+    let recorded = Provenance::open(path)?;
+    let recorded_version =
+        Some(recorded.version.to_owned()).filter(|version| version != env!("CARGO_PKG_VERSION"));
+    if let Some(checkpoint) = &recorded.checkpoint {
+        checkpoint.verify()?;
+    }
+    let mut provenance = recorded.rerun();
+    provenance.config.simulation.OUTPUT_DIR = output_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| path.with_file_name("rerun"))
+        .to_string_lossy()
+        .into_owned();
+
+    let mut simulation = SimulationBuilder::from_provenance(provenance).build()?;
+    simulation.run_to_end();
+    simulation.flush_event_log()?;
+    simulation.write_histories(histories_file)?;
+
+    let input = std::fs::read_to_string(path)
+        .map_err(|err| ProvenanceError::Io(path.to_path_buf(), err))?;
+    let recorded_histories = serde_json::from_str::<SimulationOutput>(&input)
+        .ok()
+        .map(|output| output.agent_histories);
+    let reproduced_histories = recorded_histories
+        .as_ref()
+        .map(|histories| histories == simulation.agent_histories());
+
+    let provenance = simulation.provenance().to_owned();
+    let checkpoint = simulation.into_board().model.checkpoint(&provenance);
+    if provenance.config.rl.SAVE_MODEL {
+        checkpoint.save(&provenance.config)?;
+    }
+    let reproduced = match reproduced_histories {
+        Some(reproduced) => reproduced,
+        None => {
+            let recorded: SARSACheckpoint<AgentStateItems, InvLevel, Action> =
+                SARSACheckpoint::parse(input)
+                    .map_err(|err| ProvenanceError::Parse(path.to_path_buf(), err))?;
+            recorded.q_tbls == checkpoint.q_tbls
+        }
+    };
+    Ok(RerunOutcome {
+        reproduced,
+        recorded_version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MortalityConfig;
+    use crate::event_log::LogLevel;
+    use crate::model::board::load_resource_locations;
+    use crate::model::grid_map::GridMap;
+    use crate::model::policy::UniformRandom;
+    use crate::model::{test_config, TestDir};
+
+    fn short_config(output_dir: &Path) -> Config {
+        let mut config = test_config();
+        config.world.N_STEPS = 3;
+        config.world.N_AGENTS = 2;
+        config.rl.SARSA_N = 1;
+        config.rl.SAVE_MODEL = true;
+        config.log.CONSOLE_LEVEL = LogLevel::Off;
+        config.simulation.OUTPUT_DIR = output_dir.to_string_lossy().into_owned();
+        config
+    }
+
+    #[test]
+    fn test_write_outputs() {
+        let dir = TestDir::new("outputs");
+        let output_dir = dir.path();
+        let mut config = short_config(output_dir);
+        config.log.FILE = Some("events.jsonl".to_string());
+        let simulation = run(config.clone()).unwrap();
+        let provenance = simulation.provenance().to_owned();
+        simulation.write_outputs(None).unwrap();
+        let events = std::fs::read_to_string(output_dir.join("events.jsonl")).unwrap();
+        assert_eq!(
+            events
+                .lines()
+                .filter(|line| line.contains(r#""category":"step_summary""#))
+                .count(),
+            3
+        );
+        assert!(events.contains(r#""category":"movement""#));
+        let output: SimulationOutput = serde_json::from_str(
+            &std::fs::read_to_string(output_dir.join(HISTORIES_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(output.provenance, provenance);
+        assert_eq!(output.agent_histories.len(), 2);
+
+        // The layout is written both as a grid map and as resource locations
+        let layout = GridMap::load(&output_dir.join(LAYOUT_FILE)).unwrap();
+        let resource_locations =
+            load_resource_locations(&output_dir.join(LAYOUT_RESOURCE_LOCATIONS_FILE)).unwrap();
+        assert!(resource_locations == layout.resource_locations());
+
+        // The saved checkpoint records its provenance and can be loaded to continue the run
+        let checkpoint = output_dir.join("multiP_0__agents_2__trading_1__totalItr_3.json");
+        assert_eq!(Provenance::open(&checkpoint).unwrap(), provenance);
+        config.rl.LOAD_MODEL = true;
+        config.rl.MODEL_CHECKPOINT_FILE = Some(checkpoint.to_string_lossy().into_owned());
+        let model = load_or_new_model(&config).unwrap();
+        assert_eq!(model.checkpoint_itr, Some(3));
+
+        // A checkpoint with a number of tables inconsistent with its number of agents is rejected
+        let mut tampered: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+        tampered["multi_policy"] = true.into();
+        let tampered_file = output_dir.join("tampered.json");
+        std::fs::write(&tampered_file, tampered.to_string()).unwrap();
+        assert!(matches!(
+            SARSAModel::<AgentState, AgentStateItems, InvLevel, Action>::load(&tampered_file),
+            Err(CheckpointError::TableCount(_, 1, 2))
+        ));
+
+        config.rl.MODEL_CHECKPOINT_FILE = Some("does_not_exist.json".to_string());
+        assert!(matches!(
+            run(config),
+            Err(SimulationError::Provenance(ProvenanceError::Io(..)))
+        ));
+    }
+
+    #[test]
+    fn test_many_agents() {
+        // Thousands of agents on a large board, beyond the 255 agents of a u8
+        let mut config = test_config();
+        config.world.WIDTH = 200;
+        config.world.HEIGHT = 200;
+        config.world.N_AGENTS = 5000;
+        config.world.N_STEPS = 10;
+        config.rl.SARSA_N = 2;
+        config.resources[0].ABUNDANCE = 0.1;
+        config.resources[1].ABUNDANCE = 0.1;
+        config.log.CONSOLE_LEVEL = LogLevel::Off;
+        let simulation = run(config).unwrap();
+        assert_eq!(simulation.board().agents.len(), 5000);
+        assert_eq!(simulation.agent_histories().len(), 5000);
+        assert!(simulation
+            .agent_histories()
+            .values()
+            .all(|history| history.trajectory.len() == 10));
+        assert_eq!(simulation.board().get_agent_by_id(&4999).id(), 4999);
+    }
+
+    #[test]
+    fn test_extinction() {
+        // Every agent starves at the end of the second step, after which the run stops early
+        let dir = TestDir::new("extinction");
+        let mut config = short_config(dir.path());
+        config.world.N_STEPS = 10;
+        config.agent.MORTALITY = MortalityConfig::Starvation {
+            THRESHOLD: 1000,
+            STEPS: 2,
+        };
+        let simulation = run(config).unwrap();
+        assert!(simulation.is_extinct());
+        assert_eq!(simulation.steps(), 2);
+        assert!(simulation
+            .agent_histories()
+            .values()
+            .all(|history| history.end() == Some(2)));
+    }
+
+    #[test]
+    fn test_rerun() {
+        let dir = TestDir::new("rerun");
+        let output_dir = dir.path();
+        let mut config = short_config(output_dir);
+        config.world.N_STEPS = 10;
+        config.resources[0].ABUNDANCE = 0.2;
+        config.resources[1].ABUNDANCE = 0.2;
+        let simulation = run(config).unwrap();
+        let provenance = simulation.provenance().to_owned();
+        simulation.write_outputs(None).unwrap();
+
+        let output_file = output_dir.join(HISTORIES_FILE);
+        assert_eq!(
+            rerun(&output_file, None, None).unwrap(),
+            RerunOutcome {
+                reproduced: true,
+                recorded_version: None
+            }
+        );
+        let rerun_output: SimulationOutput = serde_json::from_str(
+            &std::fs::read_to_string(output_dir.join("rerun").join(HISTORIES_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            rerun_output.provenance.config.world,
+            provenance.config.world
+        );
+        let checkpoint = output_dir.join("multiP_0__agents_2__trading_1__totalItr_10.json");
+        assert!(
+            rerun(
+                &checkpoint,
+                Some(&output_dir.join("rerun_checkpoint")),
+                None
+            )
+            .unwrap()
+            .reproduced
+        );
+
+        // A rerun with a different seed does not reproduce the histories, and a file recorded by
+        // another version is reported
+        let mut output: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output_file).unwrap()).unwrap();
+        output["provenance"]["config"]["world"]["RANDOM_SEED"] = 7.into();
+        output["provenance"]["version"] = "0.0.0".into();
+        let altered_file = output_dir.join("altered.json");
+        std::fs::write(&altered_file, output.to_string()).unwrap();
+        assert_eq!(
+            rerun(&altered_file, None, None).unwrap(),
+            RerunOutcome {
+                reproduced: false,
+                recorded_version: Some("0.0.0".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_simulation_builder() {
+        let dir = TestDir::new("builder");
+        let mut config = short_config(dir.path());
+        config.world.N_STEPS = 5;
+        let resource_locations = BTreeMap::from([
+            ("Food".to_string(), vec![Int2D { x: 1, y: 1 }]),
+            ("Water".to_string(), vec![Int2D { x: 4, y: 4 }]),
+        ]);
+        let mut simulation = Simulation::builder(config)
+            .resource_locations(resource_locations)
+            .policy(UniformRandom)
+            .build()
+            .unwrap();
+        assert_eq!(simulation.observations().len(), 0);
+
+        simulation.step();
+        assert_eq!(simulation.steps(), 1);
+        let observations = simulation.observations();
+        assert_eq!(observations.len(), 2);
+        assert!(observations[&0].state.min_steps_to_resources[0].is_some());
+
+        simulation.run(2);
+        assert_eq!(simulation.steps(), 3);
+        simulation.run_to_end();
+        assert_eq!(simulation.steps(), 5);
+        assert!(simulation
+            .agent_histories()
+            .values()
+            .all(|history| history.trajectory.len() == 5));
+        let output = simulation.output();
+        assert_eq!(output.population, vec![2; 5]);
+        assert_eq!(output.provenance.map.as_ref().unwrap().file, None);
+        assert_eq!(
+            output.provenance.resource_locations().unwrap()["Water"].len(),
+            1
+        );
+
+        // Resource locations are validated against the board
+        let resource_locations = BTreeMap::from([("Food".to_string(), vec![Int2D { x: 9, y: 9 }])]);
+        assert!(matches!(
+            Simulation::builder(test_config())
+                .resource_locations(resource_locations)
+                .build(),
+            Err(SimulationError::Invalid(_))
+        ));
+    }
+}
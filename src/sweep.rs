@@ -0,0 +1,341 @@
+//! Parameter sweeps over config values and replicate seeds.
+use crate::config::{set_config_value, Config, ConfigError};
+use crate::simulation::{self, SimulationError};
+use crate::validation::{validate, ValidationError};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// File in the sweep output directory mapping run IDs to parameter values.
+pub const INDEX_FILE: &str = "index.json";
+
+/// An error relating to a parameter sweep.
+#[derive(Error, Debug)]
+pub enum SweepError {
+    /// Sweep file could not be read.
+    #[error("Unable to read the sweep file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Sweep file contents could not be parsed.
+    #[error("Unable to parse the sweep file: {0}")]
+    Parse(#[from] toml::de::Error),
+    /// Sweep parameter not given as a non-empty list of values.
+    #[error("Sweep parameter '{0}' must be a non-empty list of values")]
+    InvalidParameter(String),
+    /// Sweep parameter could not be applied to the config.
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// Config of a run failed validation.
+    #[error("Invalid config for run {0}: {1}")]
+    Invalid(String, ValidationError),
+    /// Sweep output could not be written.
+    #[error("Unable to write the sweep output: {0}")]
+    Output(#[from] std::io::Error),
+    /// Run failed to complete.
+    #[error("Run {0} failed: {1}")]
+    Run(String, SimulationError),
+}
+
+fn default_replicates() -> u64 {
+    1
+}
+
+fn default_output_dir() -> PathBuf {
+    PathBuf::from("sweep")
+}
+
+/// Sweep file contents.
+#[derive(Deserialize, Debug)]
+pub struct SweepConfig {
+    /// Number of replicates of each combination of parameters, with seeds `RANDOM_SEED`,
+    /// `RANDOM_SEED + 1`, ...
+    #[serde(default = "default_replicates")]
+    pub replicates: u64,
+    /// Directory to write one subdirectory per run to, along with the index file. Relative to
+    /// the sweep file.
+    #[serde(default = "default_output_dir")]
+    pub output_dir: PathBuf,
+    /// Lists of values to sweep over, either as tables per section (e.g.
+    /// `[parameters.world]` with `N_AGENTS = [10, 30]`) or dotted keys (e.g.
+    /// `"world.N_AGENTS" = [10, 30]`).
+    #[serde(default)]
+    pub parameters: toml::value::Table,
+}
+
+/// A single run of a sweep.
+#[derive(Serialize, Debug)]
+pub struct SweepRun {
+    pub run_id: String,
+    pub replicate: u64,
+    pub seed: u64,
+    /// Values of the swept parameters, keyed by config key.
+    pub parameters: BTreeMap<String, toml::Value>,
+    #[serde(skip)]
+    pub config: Config,
+}
+
+impl Display for SweepRun {
+    /// Formats the run as its ID and the values of the swept parameters.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} with {}",
+            self.run_id,
+            self.parameters
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .join(", ")
+        )
+    }
+}
+
+impl SweepConfig {
+    /// Opens and parses the sweep file at the given path.
+    pub fn open(path: &Path) -> Result<Self, SweepError> {
+        let toml_str =
+            fs::read_to_string(path).map_err(|err| SweepError::Io(path.to_path_buf(), err))?;
+        let mut sweep: SweepConfig = toml::from_str(&toml_str)?;
+        if let Some(dir) = path.parent() {
+            sweep.output_dir = dir.join(&sweep.output_dir);
+        }
+        Ok(sweep)
+    }
+
+    /// Gets the lists of values to sweep over, keyed by config key.
+    pub fn parameter_values(&self) -> Result<BTreeMap<String, Vec<toml::Value>>, SweepError> {
+        let mut values = BTreeMap::new();
+        flatten_parameters("", &self.parameters, &mut values)?;
+        Ok(values)
+    }
+
+    /// Gets every run of the sweep: the cartesian product of the parameter values, repeated for
+    /// each replicate seed. Each run writes its outputs to its own subdirectory of the output
+    /// directory.
+    pub fn runs(&self, base: &Config) -> Result<Vec<SweepRun>, SweepError> {
+        let parameter_values = self.parameter_values()?;
+        let keys = parameter_values.keys().cloned().collect_vec();
+        let combinations = if keys.is_empty() {
+            vec![Vec::new()]
+        } else {
+            parameter_values
+                .into_values()
+                .multi_cartesian_product()
+                .collect_vec()
+        };
+
+        let n_runs = combinations.len() * self.replicates as usize;
+        let width = (n_runs.max(1) - 1).to_string().len();
+        let mut runs = Vec::with_capacity(n_runs);
+        for combination in combinations {
+            let mut config = base.clone();
+            for (key, value) in keys.iter().zip(combination.iter()) {
+                config = set_config_value(&config, key, value.to_owned())?;
+            }
+            for replicate in 0..self.replicates {
+                let run_id = format!("run_{:0width$}", runs.len());
+                let mut config = config.clone();
+                config.world.RANDOM_SEED += replicate;
+                config.simulation.OUTPUT_DIR =
+                    self.output_dir.join(&run_id).to_string_lossy().into_owned();
+                validate(&config).map_err(|err| SweepError::Invalid(run_id.to_owned(), err))?;
+                runs.push(SweepRun {
+                    run_id,
+                    replicate,
+                    seed: config.world.RANDOM_SEED,
+                    parameters: keys.iter().cloned().zip(combination.clone()).collect(),
+                    config,
+                });
+            }
+        }
+        Ok(runs)
+    }
+}
+
+/// Flattens (possibly nested) tables of parameter values into lists of values keyed by dotted
+/// config keys.
+fn flatten_parameters(
+    prefix: &str,
+    table: &toml::value::Table,
+    values: &mut BTreeMap<String, Vec<toml::Value>>,
+) -> Result<(), SweepError> {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::Table(table) => flatten_parameters(&key, table, values)?,
+            toml::Value::Array(array) if !array.is_empty() => {
+                values.insert(key, array.to_owned());
+            }
+            _ => return Err(SweepError::InvalidParameter(key)),
+        }
+    }
+    Ok(())
+}
+
+/// Runs every run of a sweep, using up to `jobs` threads. Writes the resolved config, agent
+/// histories and any saved model of each run to its own subdirectory of the output directory,
+/// and an index of runs to `index.json`. `on_start` is called with each run as it starts, e.g. to
+/// report progress.
+pub fn run_sweep(
+    sweep: &SweepConfig,
+    base: &Config,
+    jobs: usize,
+    on_start: impl Fn(&SweepRun) + Sync,
+) -> Result<(), SweepError> {
+    let runs = sweep.runs(base)?;
+    fs::create_dir_all(&sweep.output_dir)?;
+    fs::write(
+        sweep.output_dir.join(INDEX_FILE),
+        serde_json::to_string_pretty(&runs).map_err(std::io::Error::from)?,
+    )?;
+
+    let next = AtomicUsize::new(0);
+    let errors = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| {
+                while let Some(run) = runs.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    on_start(run);
+                    if let Err(err) = execute(run) {
+                        errors.lock().unwrap().push(err);
+                    }
+                }
+            });
+        }
+    });
+    match errors.into_inner().unwrap().pop() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Executes a single run, writing its outputs to its own subdirectory.
+fn execute(run: &SweepRun) -> Result<(), SweepError> {
+    let run_dir = Path::new(&run.config.simulation.OUTPUT_DIR);
+    fs::create_dir_all(run_dir)?;
+    fs::write(run_dir.join("config.toml"), run.config.to_string())?;
+    simulation::run(run.config.clone())
+        .and_then(|simulation| simulation.write_outputs(None))
+        .map_err(|err| SweepError::Run(run.run_id.to_owned(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_log::LogLevel;
+    use crate::model::{test_config, TestDir};
+
+    const TEST_SWEEP: &str = r##"
+    replicates = 2
+    output_dir = "sweep_test"
+
+    [parameters]
+    "rl.EPSILON" = [0.01, 0.1]
+
+    [parameters.world]
+    HAS_TRADING = [true, false]
+    N_AGENTS = [3, 5, 7]
+    "##;
+
+    #[test]
+    fn test_runs() {
+        let sweep: SweepConfig = toml::from_str(TEST_SWEEP).unwrap();
+        let runs = sweep.runs(&test_config()).unwrap();
+        assert_eq!(runs.len(), 2 * 2 * 3 * 2);
+        assert_eq!(runs[0].run_id, "run_00");
+        assert_eq!(runs[23].run_id, "run_23");
+
+        // Replicates differ only in seed
+        assert_eq!(runs[0].parameters, runs[1].parameters);
+        assert_eq!(runs[0].seed + 1, runs[1].seed);
+        assert_eq!(runs[1].config.world.RANDOM_SEED, runs[1].seed);
+
+        // Parameter values are applied to the config
+        let run = &runs[23];
+        assert_eq!(run.parameters["rl.EPSILON"], toml::Value::Float(0.1));
+        assert_eq!(run.parameters["world.N_AGENTS"], toml::Value::Integer(7));
+        assert_eq!(run.config.rl.EPSILON, 0.1);
+        assert!(!run.config.world.HAS_TRADING);
+        assert_eq!(run.config.world.N_AGENTS, 7);
+        assert_eq!(
+            Path::new(&run.config.simulation.OUTPUT_DIR),
+            Path::new("sweep_test/run_23")
+        );
+    }
+
+    #[test]
+    fn test_runs_invalid() {
+        let sweep: SweepConfig = toml::from_str("[parameters]\n\"world.N_AGENT\" = [1]").unwrap();
+        assert!(matches!(
+            sweep.runs(&test_config()),
+            Err(SweepError::Config(ConfigError::UnknownKey(_)))
+        ));
+        let sweep: SweepConfig = toml::from_str("[parameters.world]\nN_AGENTS = 1").unwrap();
+        assert!(matches!(
+            sweep.runs(&test_config()),
+            Err(SweepError::InvalidParameter(_))
+        ));
+        let sweep: SweepConfig = toml::from_str("[parameters.world]\nN_AGENTS = [0]").unwrap();
+        assert!(matches!(
+            sweep.runs(&test_config()),
+            Err(SweepError::Invalid(..))
+        ));
+    }
+
+    #[test]
+    fn test_run_sweep() {
+        let dir = TestDir::new("sweep");
+        let output_dir = dir.path().to_path_buf();
+        let sweep = SweepConfig {
+            replicates: 2,
+            output_dir: output_dir.clone(),
+            parameters: toml::from_str("\"world.N_AGENTS\" = [2, 3]").unwrap(),
+        };
+        let mut config = test_config();
+        config.world.N_STEPS = 3;
+        config.rl.SARSA_N = 1;
+        config.log.CONSOLE_LEVEL = LogLevel::Off;
+        config.rl.SAVE_MODEL = true;
+        let started = Mutex::new(Vec::new());
+        run_sweep(&sweep, &config, 2, |run| {
+            started.lock().unwrap().push(run.to_string())
+        })
+        .unwrap();
+        let mut started = started.into_inner().unwrap();
+        started.sort();
+        assert_eq!(started[3], "run_3 with world.N_AGENTS=3");
+
+        let index: Vec<serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(output_dir.join(INDEX_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(index[3]["run_id"], "run_3");
+        assert_eq!(index[3]["seed"], 1);
+        assert_eq!(index[3]["parameters"]["world.N_AGENTS"], 3);
+        for run in index {
+            let run_dir = output_dir.join(run["run_id"].as_str().unwrap());
+            assert!(run_dir.join("config.toml").exists());
+            assert!(run_dir.join("output.json").exists());
+            assert_eq!(
+                fs::read_dir(&run_dir)
+                    .unwrap()
+                    .filter(|entry| entry
+                        .as_ref()
+                        .unwrap()
+                        .file_name()
+                        .to_string_lossy()
+                        .starts_with("multiP_"))
+                    .count(),
+                1
+            );
+        }
+    }
+}
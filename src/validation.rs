@@ -0,0 +1,1059 @@
+//! Semantic validation of the configuration.
+use crate::config::{
+    load_timeline, Config, MapGeneratorConfig, MortalityConfig, PlacementConfig, RegimeChange,
+    RegimeConfig, Region, RegrowthConfig, ReproductionConfig, ResourceConfig, TerrainConfig,
+};
+use crate::model::board::load_resource_locations;
+use crate::model::environment::MAX_RESOURCES;
+use crate::model::grid_map::GridMap;
+use krabmaga::engine::location::Int2D;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use thiserror::Error;
+
+/// A single violation of a constraint on the config.
+#[derive(Error, Debug, PartialEq)]
+pub enum Violation {
+    /// Discretisation thresholds not in strictly increasing order.
+    #[error("{prefix}_CRITICAL_LOW < {prefix}_LOW_MEDIUM < {prefix}_MEDIUM_HIGH is required, found {values:?}")]
+    UnorderedLevels {
+        prefix: &'static str,
+        values: [i64; 3],
+    },
+    /// Discretisation thresholds of a resource not in strictly increasing order.
+    #[error("{prefix}_CRITICAL_LOW < {prefix}_LOW_MEDIUM < {prefix}_MEDIUM_HIGH is required for resource '{resource}', found {values:?}")]
+    UnorderedResourceLevels {
+        resource: String,
+        prefix: &'static str,
+        values: [i64; 3],
+    },
+    /// Value expected to be a probability.
+    #[error("{key} must be between 0 and 1, found {value}")]
+    NotAProbability { key: &'static str, value: f32 },
+    /// More resources than agents can hold.
+    #[error("At most {MAX_RESOURCES} resources can be configured, found {0}")]
+    TooManyResources(usize),
+    /// Resource declared more than once.
+    #[error("Resource '{0}' is declared more than once")]
+    DuplicateResource(String),
+    /// Resource abundance not a probability.
+    #[error("ABUNDANCE of resource '{name}' must be between 0 and 1, found {value}")]
+    ResourceAbundanceNotAProbability { name: String, value: f32 },
+    /// Total resource abundance greater than one.
+    #[error("Resource ABUNDANCE values must sum to at most 1, found {0}")]
+    AbundanceExceedsOne(f32),
+    /// Resource named by a key of the config that is not configured.
+    #[error("{key} names resource '{resource}', which is not configured")]
+    UnknownResource { key: &'static str, resource: String },
+    /// Value expected to be positive.
+    #[error("{key} must be positive, found {value}")]
+    NotPositive { key: &'static str, value: i64 },
+    /// Real value expected to be positive.
+    #[error("{key} must be positive, found {value}")]
+    NotPositiveReal { key: &'static str, value: f32 },
+    /// Real value expected to be zero or positive.
+    #[error("{key} must not be negative, found {value}")]
+    NegativeReal { key: &'static str, value: f32 },
+    /// No terrain types given.
+    #[error("At least one terrain type is required")]
+    NoTerrain,
+    /// Terrain type declared more than once.
+    #[error("Terrain type '{0}' is declared more than once")]
+    DuplicateTerrain(String),
+    /// Terrain probability not a probability.
+    #[error("PROB of terrain type '{name}' must be between 0 and 1, found {value}")]
+    TerrainProbNotAProbability { name: String, value: f32 },
+    /// Terrain movement cost of zero.
+    #[error("MOVEMENT_COST of terrain type '{0}' must be positive")]
+    TerrainMovementCostNotPositive(String),
+    /// Terrain probabilities not summing to one.
+    #[error("Terrain type PROB values must sum to 1, found {0}")]
+    TerrainProbsNotOne(f32),
+    /// Number of steps too small for any SARSA update to take place.
+    #[error("SARSA_N ({sarsa_n}) must be less than N_STEPS ({n_steps})")]
+    SarsaNNotLessThanSteps { sarsa_n: u8, n_steps: i32 },
+    /// Model to be loaded without a checkpoint file.
+    #[error("LOAD_MODEL is true but MODEL_CHECKPOINT_FILE is not given")]
+    MissingCheckpointFile,
+    /// Resource locations file could not be read.
+    #[error("{0}")]
+    UnreadableMap(String),
+    /// Both a grid map and a resource locations file given.
+    #[error("At most one of MAP_FILE and RESOURCE_LOCATIONS_FILE can be given")]
+    MultipleMaps,
+    /// Grid map with a number of rows other than the board height.
+    #[error("Grid map has {found} rows, expected HEIGHT ({height})")]
+    GridMapRows { found: usize, height: u16 },
+    /// Grid map row with a number of symbols other than the board width.
+    #[error("Grid map row {row} has {found} symbols, expected WIDTH ({width})")]
+    GridMapRowLength {
+        row: usize,
+        found: usize,
+        width: u16,
+    },
+    /// Grid map symbol not in the legend.
+    #[error("Grid map symbol '{0}' is not in the legend")]
+    UnknownMapSymbol(char),
+    /// Legend entry without exactly one of a terrain type or resource, with a yield but no
+    /// resource, or with a group but not a spawn point.
+    #[error("Legend entry '{0}' must give one of a terrain or a resource, a yield only with a resource, and a group only with a spawn point")]
+    MalformedLegendEntry(char),
+    /// Legend entry with a terrain type not in the config.
+    #[error("Legend entry '{symbol}' has terrain type '{terrain}', which is not configured")]
+    UnknownTerrain { symbol: char, terrain: String },
+    /// Legend entry with a resource not in the config.
+    #[error("Legend entry '{symbol}' has resource '{resource}', which is not configured")]
+    UnknownLegendResource { symbol: char, resource: String },
+    /// Timeline file could not be read.
+    #[error("{0}")]
+    UnreadableTimeline(String),
+    /// Regime recurring without a duration shorter than its period.
+    #[error("Regime '{regime}' recurs every PERIOD ({period}) steps, so needs a DURATION of at most PERIOD")]
+    RegimeDurationExceedsPeriod { regime: String, period: u64 },
+    /// Regime with a region not within the board.
+    #[error("Regime '{0}' has a REGION not within the board")]
+    RegimeRegionOutsideBoard(String),
+    /// Regime leaving a terrain type not in the config.
+    #[error("Regime '{regime}' has terrain type '{terrain}', which is not configured")]
+    UnknownRegimeTerrain { regime: String, terrain: String },
+    /// Regime changing a resource not in the config.
+    #[error("Regime '{regime}' has resource '{resource}', which is not configured")]
+    UnknownRegimeResource { regime: String, resource: String },
+    /// Agents in groups outnumbering the agents in the world.
+    #[error("Groups have {grouped} agents in total, more than N_AGENTS ({n_agents})")]
+    GroupSizesExceedNAgents { grouped: u64, n_agents: u32 },
+    /// Agents placed at spawn points without a grid map with spawn points.
+    #[error("Agents are placed at spawn points, but MAP_FILE gives none")]
+    NoSpawnPoints,
+    /// Agents placed at a group of spawn points not in the grid map.
+    #[error("Agents are placed at spawn points in group '{0}', but MAP_FILE gives none")]
+    UnknownSpawnGroup(String),
+    /// Resource location outside the board.
+    #[error("{resource} location ({x}, {y}) is outside the {width}x{height} board")]
+    ResourceOutOfBounds {
+        resource: String,
+        x: i32,
+        y: i32,
+        width: u16,
+        height: u16,
+    },
+}
+
+/// An error listing every violation found in a config.
+#[derive(Error, Debug)]
+pub struct ValidationError(pub Vec<Violation>);
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid config ({} violations):", self.0.len())?;
+        for violation in &self.0 {
+            write!(f, "\n  - {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Validates the config, including the resource locations file if given.
+pub fn validate(config: &Config) -> Result<(), ValidationError> {
+    let mut violations = validate_values(config);
+    if let Some(file_name) = &config.world.MAP_FILE {
+        match GridMap::load(Path::new(file_name)) {
+            Ok(grid_map) => {
+                violations.extend(validate_grid_map(config, &grid_map));
+                violations.extend(validate_spawn_points(config, Some(&grid_map)));
+            }
+            Err(err) => violations.push(Violation::UnreadableMap(err.to_string())),
+        }
+    } else {
+        violations.extend(validate_spawn_points(config, None));
+    }
+    if let Some(file_name) = &config.world.RESOURCE_LOCATIONS_FILE {
+        match load_resource_locations(Path::new(file_name)) {
+            Ok(resource_locations) => {
+                violations.extend(validate_resource_locations(config, &resource_locations))
+            }
+            Err(err) => violations.push(Violation::UnreadableMap(err.to_string())),
+        }
+    }
+    if let Some(file_name) = &config.world.TIMELINE_FILE {
+        match load_timeline(Path::new(file_name)) {
+            Ok(regimes) => violations.extend(validate_timeline(config, &regimes)),
+            Err(err) => violations.push(Violation::UnreadableTimeline(err.to_string())),
+        }
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError(violations))
+    }
+}
+
+/// Validates the config with the given map in place of that in the map files.
+pub fn validate_with_map(
+    config: &Config,
+    resource_locations: Option<&BTreeMap<String, Vec<Int2D>>>,
+    grid_map: Option<&GridMap>,
+) -> Result<(), ValidationError> {
+    let mut violations = validate_values(config);
+    violations.extend(validate_spawn_points(config, grid_map));
+    if let Some(grid_map) = grid_map {
+        violations.extend(validate_grid_map(config, grid_map));
+    } else if let Some(resource_locations) = resource_locations {
+        violations.extend(validate_resource_locations(config, resource_locations));
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError(violations))
+    }
+}
+
+/// Checks a set of thresholds is strictly increasing.
+fn check_levels(prefix: &'static str, values: [i64; 3], violations: &mut Vec<Violation>) {
+    if !(values[0] < values[1] && values[1] < values[2]) {
+        violations.push(Violation::UnorderedLevels { prefix, values });
+    }
+}
+
+/// Checks a value is a probability.
+fn check_probability(key: &'static str, value: f32, violations: &mut Vec<Violation>) {
+    if !(0.0..=1.0).contains(&value) {
+        violations.push(Violation::NotAProbability { key, value });
+    }
+}
+
+/// Checks a value is positive.
+fn check_positive(key: &'static str, value: i64, violations: &mut Vec<Violation>) {
+    if value <= 0 {
+        violations.push(Violation::NotPositive { key, value });
+    }
+}
+
+/// Validates the values in the config.
+fn validate_values(config: &Config) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let agent = &config.agent;
+    let world = &config.world;
+    let rl = &config.rl;
+
+    violations.extend(validate_resources(config));
+    check_levels(
+        "DISTANCE_LEVEL",
+        [
+            agent.DISTANCE_LEVEL_CRITICAL_LOW.into(),
+            agent.DISTANCE_LEVEL_LOW_MEDIUM.into(),
+            agent.DISTANCE_LEVEL_MEDIUM_HIGH.into(),
+        ],
+        &mut violations,
+    );
+
+    check_probability("GAMMA", rl.GAMMA, &mut violations);
+    check_probability("ALPHA", rl.ALPHA, &mut violations);
+    check_probability("EPSILON", rl.EPSILON, &mut violations);
+
+    check_positive("N_STEPS", world.N_STEPS.into(), &mut violations);
+    check_positive("WIDTH", world.WIDTH.into(), &mut violations);
+    check_positive("HEIGHT", world.HEIGHT.into(), &mut violations);
+    check_positive("N_AGENTS", world.N_AGENTS.into(), &mut violations);
+    if let MortalityConfig::Starvation { STEPS, .. } = agent.MORTALITY {
+        check_positive("STEPS", STEPS.into(), &mut violations);
+    }
+    if let ReproductionConfig::Surplus {
+        THRESHOLDS,
+        SHARE,
+        NOISE,
+        ..
+    } = &agent.REPRODUCTION
+    {
+        for resource in THRESHOLDS.keys() {
+            check_resource("THRESHOLDS", resource, &config.resources, &mut violations);
+        }
+        check_probability("SHARE", *SHARE, &mut violations);
+        if *NOISE < 0.0 {
+            violations.push(Violation::NegativeReal {
+                key: "NOISE",
+                value: *NOISE,
+            });
+        }
+    }
+
+    if i32::from(rl.SARSA_N) >= world.N_STEPS {
+        violations.push(Violation::SarsaNNotLessThanSteps {
+            sarsa_n: rl.SARSA_N,
+            n_steps: world.N_STEPS,
+        });
+    }
+    if world.MAP_FILE.is_some() && world.RESOURCE_LOCATIONS_FILE.is_some() {
+        violations.push(Violation::MultipleMaps);
+    }
+    if rl.LOAD_MODEL && rl.MODEL_CHECKPOINT_FILE.is_none() {
+        violations.push(Violation::MissingCheckpointFile);
+    }
+    let grouped: u64 = config.groups.iter().map(|g| u64::from(g.N_AGENTS)).sum();
+    if grouped > u64::from(world.N_AGENTS) {
+        violations.push(Violation::GroupSizesExceedNAgents {
+            grouped,
+            n_agents: world.N_AGENTS,
+        });
+    }
+    violations.extend(validate_map_generator(
+        &world.MAP_GENERATOR,
+        &config.resources,
+    ));
+    violations.extend(validate_regrowth(&world.REGROWTH));
+    for placement in placements(config) {
+        violations.extend(validate_placement(placement, &config.resources));
+    }
+    violations.extend(validate_terrain(&config.terrain));
+    violations.extend(validate_timeline(config, &config.timeline));
+    violations
+}
+
+/// Checks a name is of a configured resource.
+fn check_resource(
+    key: &'static str,
+    resource: &str,
+    resources: &[ResourceConfig],
+    violations: &mut Vec<Violation>,
+) {
+    if !resources.iter().any(|r| r.NAME == resource) {
+        violations.push(Violation::UnknownResource {
+            key,
+            resource: resource.to_owned(),
+        });
+    }
+}
+
+/// Validates the resources, and the initial inventories of groups of agents.
+fn validate_resources(config: &Config) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let resources = &config.resources;
+    if resources.len() > MAX_RESOURCES {
+        violations.push(Violation::TooManyResources(resources.len()));
+    }
+    for (i, r) in resources.iter().enumerate() {
+        if resources[..i].iter().any(|other| other.NAME == r.NAME) {
+            violations.push(Violation::DuplicateResource(r.NAME.to_owned()));
+        }
+        if !(0.0..=1.0).contains(&r.ABUNDANCE) {
+            violations.push(Violation::ResourceAbundanceNotAProbability {
+                name: r.NAME.to_owned(),
+                value: r.ABUNDANCE,
+            });
+        }
+        let levels = [
+            ("INVENTORY_LEVEL", r.inventory_levels().map(i64::from)),
+            ("DISTANCE_LEVEL", r.distance_levels().map(i64::from)),
+        ];
+        for (prefix, values) in levels {
+            if !(values[0] < values[1] && values[1] < values[2]) {
+                violations.push(Violation::UnorderedResourceLevels {
+                    resource: r.NAME.to_owned(),
+                    prefix,
+                    values,
+                });
+            }
+        }
+    }
+    let abundance: f32 = resources.iter().map(|r| r.ABUNDANCE).sum();
+    if abundance > 1.0 {
+        violations.push(Violation::AbundanceExceedsOne(abundance));
+    }
+    for group in &config.groups {
+        for resource in group.INIT.keys() {
+            check_resource("INIT", resource, resources, &mut violations);
+        }
+    }
+    violations
+}
+
+/// Validates the parameters of the map generator.
+fn validate_map_generator(
+    generator: &MapGeneratorConfig,
+    resources: &[ResourceConfig],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    match generator {
+        MapGeneratorConfig::Scatter => {}
+        MapGeneratorConfig::River {
+            RIVER_WIDTH,
+            RIVER_RESOURCE,
+            BLOCK_RESOURCE,
+            ..
+        } => {
+            check_positive("RIVER_WIDTH", (*RIVER_WIDTH).into(), &mut violations);
+            check_resource("RIVER_RESOURCE", RIVER_RESOURCE, resources, &mut violations);
+            check_resource("BLOCK_RESOURCE", BLOCK_RESOURCE, resources, &mut violations);
+        }
+        MapGeneratorConfig::Clusters { N_CLUSTERS, SPREAD } => {
+            check_positive("N_CLUSTERS", (*N_CLUSTERS).into(), &mut violations);
+            if *SPREAD <= 0.0 {
+                violations.push(Violation::NotPositiveReal {
+                    key: "SPREAD",
+                    value: *SPREAD,
+                });
+            }
+        }
+        MapGeneratorConfig::Islands { FILL, .. } => {
+            check_probability("FILL", *FILL, &mut violations)
+        }
+        MapGeneratorConfig::Noise { SCALE } => {
+            check_positive("SCALE", (*SCALE).into(), &mut violations)
+        }
+    }
+    violations
+}
+
+/// Validates the parameters of the regrowth model.
+fn validate_regrowth(regrowth: &RegrowthConfig) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    match *regrowth {
+        RegrowthConfig::Unlimited => {}
+        RegrowthConfig::Constant { CAPACITY, RATE }
+        | RegrowthConfig::Logistic { CAPACITY, RATE }
+        | RegrowthConfig::Sugarscape { CAPACITY, RATE } => {
+            check_positive("CAPACITY", CAPACITY.into(), &mut violations);
+            if RATE <= 0.0 {
+                violations.push(Violation::NotPositiveReal {
+                    key: "RATE",
+                    value: RATE,
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Gets the placements of the groups of agents and of any other agents.
+fn placements(config: &Config) -> impl Iterator<Item = &PlacementConfig> {
+    config
+        .groups
+        .iter()
+        .map(|group| &group.PLACEMENT)
+        .chain([&config.world.PLACEMENT])
+}
+
+/// Validates the parameters of a placement of agents.
+fn validate_placement(placement: &PlacementConfig, resources: &[ResourceConfig]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    match placement {
+        PlacementConfig::Clusters { N_CLUSTERS, SPREAD } => {
+            check_positive("N_CLUSTERS", (*N_CLUSTERS).into(), &mut violations);
+            if *SPREAD <= 0.0 {
+                violations.push(Violation::NotPositiveReal {
+                    key: "SPREAD",
+                    value: *SPREAD,
+                });
+            }
+        }
+        PlacementConfig::NearResource { RESOURCE, .. } => {
+            check_resource("RESOURCE", RESOURCE, resources, &mut violations)
+        }
+        _ => {}
+    }
+    violations
+}
+
+/// Validates the grid map, if any, has the spawn points agents are placed at.
+fn validate_spawn_points(config: &Config, grid_map: Option<&GridMap>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for placement in placements(config) {
+        match (placement, grid_map) {
+            (PlacementConfig::SpawnPoints { GROUP: Some(group) }, Some(grid_map))
+                if !grid_map.spawn_groups().contains_key(group) =>
+            {
+                violations.push(Violation::UnknownSpawnGroup(group.to_owned()));
+            }
+            (PlacementConfig::SpawnPoints { GROUP: None }, Some(grid_map))
+                if grid_map.spawn_points().is_empty() =>
+            {
+                violations.push(Violation::NoSpawnPoints);
+            }
+            (PlacementConfig::SpawnPoints { .. }, None) => {
+                violations.push(Violation::NoSpawnPoints)
+            }
+            _ => {}
+        }
+    }
+    violations
+}
+
+/// Validates the regimes of a timeline.
+fn validate_timeline(config: &Config, regimes: &[RegimeConfig]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let on_board = |region: &Option<Region>| {
+        region.is_none_or(|r| {
+            0 <= r.X_MIN
+                && r.X_MIN <= r.X_MAX
+                && r.X_MAX < config.world.WIDTH.into()
+                && 0 <= r.Y_MIN
+                && r.Y_MIN <= r.Y_MAX
+                && r.Y_MAX < config.world.HEIGHT.into()
+        })
+    };
+    for regime in regimes {
+        if let Some(duration) = regime.DURATION {
+            check_positive("DURATION", duration as i64, &mut violations);
+        }
+        if let Some(period) = regime.PERIOD {
+            check_positive("PERIOD", period as i64, &mut violations);
+            if regime.DURATION.is_none_or(|duration| duration > period) {
+                violations.push(Violation::RegimeDurationExceedsPeriod {
+                    regime: regime.NAME.to_owned(),
+                    period,
+                });
+            }
+        }
+        let resource = match &regime.CHANGE {
+            RegimeChange::Disappear { RESOURCE, .. }
+            | RegimeChange::AcquireRate { RESOURCE, .. }
+            | RegimeChange::Shift { RESOURCE, .. } => RESOURCE,
+        };
+        if !config.resources.iter().any(|r| r.NAME == *resource) {
+            violations.push(Violation::UnknownRegimeResource {
+                regime: regime.NAME.to_owned(),
+                resource: resource.to_owned(),
+            });
+        }
+        let (region, terrain) = match &regime.CHANGE {
+            RegimeChange::Disappear {
+                REGION, TERRAIN, ..
+            }
+            | RegimeChange::Shift {
+                REGION, TERRAIN, ..
+            } => (REGION, TERRAIN),
+            RegimeChange::AcquireRate { MULTIPLIER, .. } => {
+                if *MULTIPLIER < 0.0 {
+                    violations.push(Violation::NegativeReal {
+                        key: "MULTIPLIER",
+                        value: *MULTIPLIER,
+                    });
+                }
+                continue;
+            }
+        };
+        if !on_board(region) {
+            violations.push(Violation::RegimeRegionOutsideBoard(regime.NAME.to_owned()));
+        }
+        if let Some(name) = terrain {
+            if !config.terrain.iter().any(|t| t.NAME == *name) {
+                violations.push(Violation::UnknownRegimeTerrain {
+                    regime: regime.NAME.to_owned(),
+                    terrain: name.to_owned(),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Validates the terrain types.
+fn validate_terrain(terrain: &[TerrainConfig]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if terrain.is_empty() {
+        violations.push(Violation::NoTerrain);
+        return violations;
+    }
+    for (i, t) in terrain.iter().enumerate() {
+        if terrain[..i].iter().any(|other| other.NAME == t.NAME) {
+            violations.push(Violation::DuplicateTerrain(t.NAME.to_owned()));
+        }
+        if !(0.0..=1.0).contains(&t.PROB) {
+            violations.push(Violation::TerrainProbNotAProbability {
+                name: t.NAME.to_owned(),
+                value: t.PROB,
+            });
+        }
+        if t.MOVEMENT_COST == 0 {
+            violations.push(Violation::TerrainMovementCostNotPositive(t.NAME.to_owned()));
+        }
+    }
+    let total: f32 = terrain.iter().map(|t| t.PROB).sum();
+    if (total - 1.0).abs() > 1e-4 {
+        violations.push(Violation::TerrainProbsNotOne(total));
+    }
+    violations
+}
+
+/// Validates a grid map covers the board and its legend refers to configured terrain types.
+fn validate_grid_map(config: &Config, grid_map: &GridMap) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let (width, height) = (config.world.WIDTH, config.world.HEIGHT);
+    if grid_map.grid.len() != usize::from(height) {
+        violations.push(Violation::GridMapRows {
+            found: grid_map.grid.len(),
+            height,
+        });
+    }
+    for (row, symbols) in grid_map.grid.iter().enumerate() {
+        let found = symbols.chars().count();
+        if found != usize::from(width) {
+            violations.push(Violation::GridMapRowLength { row, found, width });
+        }
+    }
+    let mut unknown: Vec<char> = grid_map
+        .symbols()
+        .map(|(_, symbol)| symbol)
+        .filter(|symbol| !grid_map.legend.contains_key(symbol))
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    violations.extend(unknown.into_iter().map(Violation::UnknownMapSymbol));
+
+    for (&symbol, entry) in &grid_map.legend {
+        let well_formed = match (&entry.terrain, &entry.resource) {
+            (Some(terrain), None) if entry.resource_yield.is_none() => {
+                if !config.terrain.iter().any(|t| t.NAME == *terrain) {
+                    violations.push(Violation::UnknownTerrain {
+                        symbol,
+                        terrain: terrain.to_owned(),
+                    });
+                }
+                true
+            }
+            (None, Some(resource)) => {
+                if !config.resources.iter().any(|r| r.NAME == *resource) {
+                    violations.push(Violation::UnknownLegendResource {
+                        symbol,
+                        resource: resource.to_owned(),
+                    });
+                }
+                true
+            }
+            _ => false,
+        };
+        if !well_formed || (entry.group.is_some() && !entry.spawn) {
+            violations.push(Violation::MalformedLegendEntry(symbol));
+        }
+    }
+    violations
+}
+
+/// Validates resource locations are of configured resources and lie on the board.
+fn validate_resource_locations(
+    config: &Config,
+    resource_locations: &BTreeMap<String, Vec<Int2D>>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for resource in resource_locations.keys() {
+        check_resource(
+            "RESOURCE_LOCATIONS_FILE",
+            resource,
+            &config.resources,
+            &mut violations,
+        );
+    }
+    let (width, height) = (config.world.WIDTH, config.world.HEIGHT);
+    let out_of_bounds = resource_locations.iter().flat_map(|(resource, locations)| {
+        locations
+            .iter()
+            .filter(|loc| {
+                !(0..i32::from(width)).contains(&loc.x) || !(0..i32::from(height)).contains(&loc.y)
+            })
+            .map(move |loc| Violation::ResourceOutOfBounds {
+                resource: resource.to_owned(),
+                x: loc.x,
+                y: loc.y,
+                width,
+                height,
+            })
+    });
+    violations.extend(out_of_bounds);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AgentGroupConfig;
+    use crate::model::test_config;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&test_config()).is_ok());
+
+        let mut config = test_config();
+        config.resources[0].INVENTORY_LEVEL_LOW_MEDIUM = 100;
+        config.agent.DISTANCE_LEVEL_MEDIUM_HIGH = 2;
+        config.resources[0].ABUNDANCE = 0.6;
+        config.resources[1].ABUNDANCE = 0.6;
+        config.rl.SARSA_N = 200;
+        config.rl.LOAD_MODEL = true;
+        config.agent.MORTALITY = MortalityConfig::Starvation {
+            THRESHOLD: 0,
+            STEPS: 0,
+        };
+        config.agent.REPRODUCTION = ReproductionConfig::Surplus {
+            THRESHOLD: 100,
+            THRESHOLDS: BTreeMap::from([("Salt".to_string(), 50)]),
+            SHARE: 1.5,
+            NOISE: 0.0,
+        };
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::UnorderedResourceLevels {
+                    resource: "Food".to_string(),
+                    prefix: "INVENTORY_LEVEL",
+                    values: [0, 100, 50]
+                },
+                Violation::AbundanceExceedsOne(1.2),
+                Violation::UnorderedLevels {
+                    prefix: "DISTANCE_LEVEL",
+                    values: [2, 10, 2]
+                },
+                Violation::NotPositive {
+                    key: "STEPS",
+                    value: 0
+                },
+                Violation::UnknownResource {
+                    key: "THRESHOLDS",
+                    resource: "Salt".to_string()
+                },
+                Violation::NotAProbability {
+                    key: "SHARE",
+                    value: 1.5
+                },
+                Violation::SarsaNNotLessThanSteps {
+                    sarsa_n: 200,
+                    n_steps: 100
+                },
+                Violation::MissingCheckpointFile,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_resources() {
+        let mut config = test_config();
+        config.resources[1].NAME = config.resources[0].NAME.to_owned();
+        config.resources[1].ABUNDANCE = 1.5;
+        config
+            .resources
+            .extend(vec![config.resources[0].clone(); 7]);
+        config.world.MAP_GENERATOR = MapGeneratorConfig::River {
+            RIVER_WIDTH: 2,
+            MEANDER: 1,
+            FOOD_BLOCK: 2,
+            RIVER_RESOURCE: "Salt".to_string(),
+            BLOCK_RESOURCE: "Food".to_string(),
+        };
+        config.groups = vec![AgentGroupConfig {
+            N_AGENTS: 1,
+            INIT: BTreeMap::from([("Salt".to_string(), 50)]),
+            PLACEMENT: PlacementConfig::Random,
+        }];
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(violations[0], Violation::TooManyResources(9));
+        assert_eq!(
+            violations[1],
+            Violation::DuplicateResource("Food".to_string())
+        );
+        assert_eq!(
+            violations[2],
+            Violation::ResourceAbundanceNotAProbability {
+                name: "Food".to_string(),
+                value: 1.5
+            }
+        );
+        assert!(violations.contains(&Violation::UnknownResource {
+            key: "INIT",
+            resource: "Salt".to_string()
+        }));
+        assert_eq!(
+            violations.last(),
+            Some(&Violation::UnknownResource {
+                key: "RIVER_RESOURCE",
+                resource: "Salt".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_terrain() {
+        let mut config = test_config();
+        config.terrain[1].NAME = config.terrain[0].NAME.to_owned();
+        config.terrain[0].PROB = 0.5;
+        config.terrain[1].PROB = 1.5;
+        config.terrain[1].MOVEMENT_COST = 0;
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::DuplicateTerrain("land".to_string()),
+                Violation::TerrainProbNotAProbability {
+                    name: "land".to_string(),
+                    value: 1.5
+                },
+                Violation::TerrainMovementCostNotPositive("land".to_string()),
+                Violation::TerrainProbsNotOne(2.0),
+            ]
+        );
+
+        config.terrain.clear();
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(violations, vec![Violation::NoTerrain]);
+    }
+
+    #[test]
+    fn test_validate_map_generator() {
+        let mut config = test_config();
+        config.world.MAP_GENERATOR = MapGeneratorConfig::Clusters {
+            N_CLUSTERS: 0,
+            SPREAD: 0.0,
+        };
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::NotPositive {
+                    key: "N_CLUSTERS",
+                    value: 0
+                },
+                Violation::NotPositiveReal {
+                    key: "SPREAD",
+                    value: 0.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_regrowth() {
+        let mut config = test_config();
+        config.world.REGROWTH = RegrowthConfig::Logistic {
+            CAPACITY: 0,
+            RATE: -0.1,
+        };
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::NotPositive {
+                    key: "CAPACITY",
+                    value: 0
+                },
+                Violation::NotPositiveReal {
+                    key: "RATE",
+                    value: -0.1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_timeline() {
+        let mut config = test_config();
+        let regime = |change| RegimeConfig {
+            NAME: "shock".to_string(),
+            START: 0,
+            DURATION: Some(10),
+            PERIOD: Some(5),
+            CHANGE: change,
+        };
+        config.timeline = vec![
+            regime(RegimeChange::AcquireRate {
+                RESOURCE: "Food".to_string(),
+                MULTIPLIER: -1.0,
+            }),
+            regime(RegimeChange::Shift {
+                RESOURCE: "Salt".to_string(),
+                DX: 1,
+                DY: 0,
+                REGION: Some(Region {
+                    X_MIN: 0,
+                    Y_MIN: 0,
+                    X_MAX: 6,
+                    Y_MAX: 2,
+                }),
+                TERRAIN: Some("lava".to_string()),
+            }),
+        ];
+        config.timeline[1].DURATION = Some(5);
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::RegimeDurationExceedsPeriod {
+                    regime: "shock".to_string(),
+                    period: 5
+                },
+                Violation::NegativeReal {
+                    key: "MULTIPLIER",
+                    value: -1.0
+                },
+                Violation::UnknownRegimeResource {
+                    regime: "shock".to_string(),
+                    resource: "Salt".to_string()
+                },
+                Violation::RegimeRegionOutsideBoard("shock".to_string()),
+                Violation::UnknownRegimeTerrain {
+                    regime: "shock".to_string(),
+                    terrain: "lava".to_string()
+                },
+            ]
+        );
+
+        config.timeline.clear();
+        config.world.TIMELINE_FILE = Some("does_not_exist.toml".to_string());
+        let violations = validate(&config).unwrap_err().0;
+        assert!(matches!(violations[..], [Violation::UnreadableTimeline(_)]));
+    }
+
+    #[test]
+    fn test_validate_placement() {
+        let mut config = test_config();
+        let group = |n_agents, placement| AgentGroupConfig {
+            N_AGENTS: n_agents,
+            INIT: BTreeMap::from([("Food".to_string(), 50)]),
+            PLACEMENT: placement,
+        };
+        config.groups = vec![
+            group(
+                20,
+                PlacementConfig::SpawnPoints {
+                    GROUP: Some("north".to_string()),
+                },
+            ),
+            group(
+                20,
+                PlacementConfig::Clusters {
+                    N_CLUSTERS: 0,
+                    SPREAD: 1.0,
+                },
+            ),
+        ];
+        let violations = validate(&config).unwrap_err().0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::GroupSizesExceedNAgents {
+                    grouped: 40,
+                    n_agents: config.world.N_AGENTS
+                },
+                Violation::NotPositive {
+                    key: "N_CLUSTERS",
+                    value: 0
+                },
+                Violation::NoSpawnPoints,
+            ]
+        );
+
+        // Groups of spawn points are given by the grid map
+        config.groups.truncate(1);
+        let grid_map = GridMap::read(
+            r#"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "n": {"terrain": "land", "spawn": true, "group": "north"},
+                    "s": {"terrain": "land", "group": "south"}
+                },
+                "grid": ["n..", "..s"]
+            }"#,
+        )
+        .unwrap();
+        config.world.WIDTH = 3;
+        config.world.HEIGHT = 2;
+        let violations = validate_with_map(&config, None, Some(&grid_map))
+            .unwrap_err()
+            .0;
+        assert_eq!(violations, vec![Violation::MalformedLegendEntry('s')]);
+        config.groups[0].PLACEMENT = PlacementConfig::SpawnPoints {
+            GROUP: Some("south".to_string()),
+        };
+        let violations = validate_with_map(&config, None, Some(&grid_map))
+            .unwrap_err()
+            .0;
+        assert_eq!(
+            violations[0],
+            Violation::UnknownSpawnGroup("south".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_grid_map() {
+        let mut config = test_config();
+        config.world.WIDTH = 3;
+        config.world.HEIGHT = 4;
+        let grid_map = GridMap::read(
+            r#"{
+                "legend": {
+                    ".": {"terrain": "land"},
+                    "~": {"terrain": "swamp"},
+                    "f": {"resource": "Food", "yield": 5},
+                    "x": {"terrain": "land", "resource": "Water"},
+                    "y": {"terrain": "land", "yield": 5}
+                },
+                "grid": ["..f", ".~", "..?", "..."]
+            }"#,
+        )
+        .unwrap();
+        let violations = validate_with_map(&config, None, Some(&grid_map))
+            .unwrap_err()
+            .0;
+        assert_eq!(
+            violations,
+            vec![
+                Violation::GridMapRowLength {
+                    row: 1,
+                    found: 2,
+                    width: 3
+                },
+                Violation::UnknownMapSymbol('?'),
+                Violation::MalformedLegendEntry('x'),
+                Violation::MalformedLegendEntry('y'),
+                Violation::UnknownTerrain {
+                    symbol: '~',
+                    terrain: "swamp".to_string()
+                },
+            ]
+        );
+
+        config.world.MAP_FILE = Some("grid_map.json".to_string());
+        config.world.RESOURCE_LOCATIONS_FILE = Some("resource_locations.json".to_string());
+        let violations = validate(&config).unwrap_err().0;
+        assert!(violations.contains(&Violation::MultipleMaps));
+        assert!(violations.contains(&Violation::GridMapRows {
+            found: 20,
+            height: 4
+        }));
+    }
+
+    #[test]
+    fn test_validate_resource_locations() {
+        let config = test_config();
+        let resource_locations = BTreeMap::from([
+            (
+                "Food".to_string(),
+                vec![Int2D { x: 0, y: 5 }, Int2D { x: 6, y: 0 }],
+            ),
+            ("Water".to_string(), vec![Int2D { x: 2, y: -1 }]),
+            ("Salt".to_string(), vec![]),
+        ]);
+        let violations = validate_resource_locations(&config, &resource_locations);
+        assert_eq!(violations.len(), 3);
+        assert_eq!(
+            violations[0],
+            Violation::UnknownResource {
+                key: "RESOURCE_LOCATIONS_FILE",
+                resource: "Salt".to_string()
+            }
+        );
+        assert!(matches!(
+            violations[1],
+            Violation::ResourceOutOfBounds {
+                ref resource,
+                x: 6,
+                y: 0,
+                ..
+            } if resource == "Food"
+        ));
+
+        let mut config = test_config();
+        config.world.RESOURCE_LOCATIONS_FILE = Some("does_not_exist.json".to_string());
+        let violations = validate(&config).unwrap_err().0;
+        assert!(matches!(violations[..], [Violation::UnreadableMap(_)]));
+    }
+}
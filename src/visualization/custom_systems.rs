@@ -1,5 +1,4 @@
 use crate::model::board::*;
-use crate::model::environment::EnvItem;
 use krabmaga::bevy::ecs::component::TableStorage;
 use krabmaga::bevy::prelude::Component;
 use krabmaga::engine::fields::dense_object_grid_2d::DenseGrid2D;
@@ -20,11 +19,9 @@ impl RenderObjectGrid2D<Board, Patch> for DenseGrid2D<Patch> {
     }
     fn fetch_emoji(state: &Board, obj: &Patch) -> String {
         let obj_real = state.resource_grid.get(obj).unwrap();
-        match obj_real.env_item {
-            EnvItem::Land => "land".to_string(),
-            EnvItem::Bush => "tree".to_string(),
-            EnvItem::Resource(r) => r.texture(),
-        }
+        obj_real
+            .env_item
+            .texture(&state.config.resources, &state.config.terrain)
     }
     fn fetch_loc(state: &Board, obj: &Patch) -> Option<Int2D> {
         if let Some(loc) = state.resource_grid.get_location(*obj) {